@@ -0,0 +1,254 @@
+// `#[derive(IntoValue, FromValue)]` for v-lang. Structs with named fields convert to and
+// from objects, tuple structs to and from arrays (a single field converts as itself), unit
+// structs to null and enums to and from variants.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{
+    parse_macro_input, parse_quote, Data, DataEnum, DeriveInput, Fields, Generics, Ident, Index,
+    Path,
+};
+
+#[proc_macro_derive(IntoValue)]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = with_bound(input.generics.clone(), parse_quote!(::v_lang::IntoValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => struct_into_value(&data.fields),
+        Data::Enum(data) => {
+            let enumeration = enumeration(name, data);
+            let body = enum_into_value(data);
+            return quote! {
+                impl #impl_generics ::v_lang::runtime::convert::EnumValue
+                    for #name #ty_generics #where_clause
+                {
+                    fn enumeration() -> ::std::rc::Rc<::v_lang::runtime::types::Enum> {
+                        #enumeration
+                    }
+                }
+
+                impl #impl_generics ::v_lang::IntoValue for #name #ty_generics #where_clause {
+                    fn into_value(self) -> ::v_lang::Value {
+                        #body
+                    }
+                }
+            }
+            .into();
+        }
+        Data::Union(_) => return error(&input, "IntoValue cannot be derived for unions"),
+    };
+    quote! {
+        impl #impl_generics ::v_lang::IntoValue for #name #ty_generics #where_clause {
+            fn into_value(self) -> ::v_lang::Value {
+                #body
+            }
+        }
+    }
+    .into()
+}
+
+#[proc_macro_derive(FromValue)]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = with_bound(input.generics.clone(), parse_quote!(::v_lang::FromValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => struct_from_value(name, &data.fields),
+        Data::Enum(data) => enum_from_value(name, data),
+        Data::Union(_) => return error(&input, "FromValue cannot be derived for unions"),
+    };
+    quote! {
+        impl #impl_generics ::v_lang::FromValue for #name #ty_generics #where_clause {
+            fn from_value(
+                value: ::v_lang::Value,
+            ) -> ::std::result::Result<Self, ::v_lang::ConversionError> {
+                #body
+            }
+        }
+    }
+    .into()
+}
+
+fn with_bound(mut generics: Generics, bound: Path) -> Generics {
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn error(input: &DeriveInput, message: &str) -> TokenStream {
+    syn::Error::new_spanned(input, message)
+        .to_compile_error()
+        .into()
+}
+
+// Field names as scripts see them, without any `r#` prefix.
+fn key(ident: &Ident) -> String {
+    ident.unraw().to_string()
+}
+
+fn struct_into_value(fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let properties = fields.named.iter().map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                let key = key(ident);
+                quote!((#key, ::v_lang::IntoValue::into_value(self.#ident)))
+            });
+            quote!(::v_lang::runtime::convert::object(vec![#(#properties),*]))
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            quote!(::v_lang::IntoValue::into_value(self.0))
+        }
+        Fields::Unnamed(fields) => {
+            let elements = (0..fields.unnamed.len()).map(|index| {
+                let index = Index::from(index);
+                quote!(::v_lang::IntoValue::into_value(self.#index))
+            });
+            quote!(::v_lang::Value::array(vec![#(#elements),*]))
+        }
+        Fields::Unit => quote!(::v_lang::Value::Null),
+    }
+}
+
+fn struct_from_value(name: &Ident, fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let expected = format!("{} object", name);
+            let fields = fields.named.iter().map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                let key = key(ident);
+                quote!(#ident: ::v_lang::runtime::convert::field(&properties, #key)?)
+            });
+            quote! {
+                let properties = ::v_lang::runtime::convert::properties(value, #expected)?;
+                Ok(Self { #(#fields),* })
+            }
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            quote!(Ok(Self(::v_lang::FromValue::from_value(value)?)))
+        }
+        Fields::Unnamed(fields) => {
+            let length = fields.unnamed.len();
+            let expected = format!("{} array", name);
+            let elements = (0..length)
+                .map(|index| quote!(::v_lang::runtime::convert::element(&elements, #index)?));
+            quote! {
+                let elements = ::v_lang::runtime::convert::array_elements(value, #expected)?;
+                ::v_lang::runtime::convert::check_length(&elements, #length, "array")?;
+                Ok(Self(#(#elements),*))
+            }
+        }
+        Fields::Unit => quote! {
+            <() as ::v_lang::FromValue>::from_value(value)?;
+            Ok(Self)
+        },
+    }
+}
+
+// The enum's description, built once per thread so every converted variant shares it.
+fn enumeration(name: &Ident, data: &DataEnum) -> TokenStream2 {
+    let name = name.to_string();
+    let variants = data.variants.iter().map(|variant| {
+        let variant_name = variant.ident.to_string();
+        let fields: Vec<String> = match &variant.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| key(field.ident.as_ref().unwrap()))
+                .collect(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len()).map(|i| i.to_string()).collect(),
+            Fields::Unit => vec![],
+        };
+        quote!((#variant_name, &[#(#fields),*]))
+    });
+    quote! {
+        ::std::thread_local! {
+            static ENUMERATION: ::std::rc::Rc<::v_lang::runtime::types::Enum> =
+                ::v_lang::runtime::convert::enumeration(#name, &[#(#variants),*]);
+        }
+        ENUMERATION.with(|enumeration| enumeration.clone())
+    }
+}
+
+fn enum_into_value(data: &DataEnum) -> TokenStream2 {
+    let arms = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let variant_name = ident.to_string();
+        let bindings: Vec<Ident> = match &variant.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| field.ident.clone().unwrap())
+                .collect(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                .map(|index| format_ident!("field_{}", index))
+                .collect(),
+            Fields::Unit => vec![],
+        };
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote!(Self::#ident { #(#bindings),* }),
+            Fields::Unnamed(_) => quote!(Self::#ident(#(#bindings),*)),
+            Fields::Unit => quote!(Self::#ident),
+        };
+        quote! {
+            #pattern => ::v_lang::runtime::convert::variant(
+                enumeration,
+                #variant_name,
+                vec![#(::v_lang::IntoValue::into_value(#bindings)),*],
+            )
+        }
+    });
+    quote! {
+        let enumeration = <Self as ::v_lang::runtime::convert::EnumValue>::enumeration();
+        match self {
+            #(#arms,)*
+        }
+    }
+}
+
+fn enum_from_value(name: &Ident, data: &DataEnum) -> TokenStream2 {
+    let expected = format!("{} variant", name);
+    let arms = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let variant_name = ident.to_string();
+        let segment = format!(".{}", variant_name);
+        let length = variant.fields.len();
+        let construct = match &variant.fields {
+            Fields::Named(fields) => {
+                let fields = fields.named.iter().enumerate().map(|(index, field)| {
+                    let field = field.ident.as_ref().unwrap();
+                    let key = key(field);
+                    quote! {
+                        #field: ::v_lang::runtime::convert::named_element(&values, #index, #key)?
+                    }
+                });
+                quote!(Self::#ident { #(#fields),* })
+            }
+            Fields::Unnamed(_) => {
+                let elements = (0..length)
+                    .map(|index| quote!(::v_lang::runtime::convert::element(&values, #index)?));
+                quote!(Self::#ident(#(#elements),*))
+            }
+            Fields::Unit => quote!(Self::#ident),
+        };
+        quote! {
+            #variant_name => (|| -> ::std::result::Result<Self, ::v_lang::ConversionError> {
+                ::v_lang::runtime::convert::check_length(&values, #length, "variant fields")?;
+                Ok(#construct)
+            })()
+            .map_err(|error| error.within(#segment))
+        }
+    });
+    quote! {
+        let (variant, values) = ::v_lang::runtime::convert::variant_values(value, #expected)?;
+        match variant.as_str() {
+            #(#arms,)*
+            other => Err(::v_lang::runtime::convert::unknown_variant(#expected, other)),
+        }
+    }
+}
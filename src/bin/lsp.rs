@@ -0,0 +1,8 @@
+use std::io;
+use std::process::exit;
+
+use v_lang::lsp;
+
+fn main() {
+    exit(lsp::serve(io::stdin().lock(), io::stdout().lock()))
+}
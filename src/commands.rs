@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::frontend::ast::Ast;
+use crate::frontend::linter::LintConfig;
+use crate::frontend::span::Span;
+use crate::frontend::{checker, formatter, lexer, linter, parser};
+use crate::runtime::environment;
+
+// Read by `lint` from the working directory unless `--config` names another file.
+pub(crate) const LINT_CONFIG: &str = ".vlint";
+
+// `v-lang fmt [--check] [paths...]` formats files in place, or stdin to stdout without
+// paths. Directories are searched for `.v` files. With `--check` nothing is written; files
+// that are not formatted are listed and the status is 1.
+pub fn fmt(args: Vec<String>) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<PathBuf> = args
+        .iter()
+        .filter(|arg| *arg != "--check")
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        return fmt_stdin(check);
+    }
+    let mut status = 0;
+    for path in source_files(&paths) {
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("cannot read {}: {}", path.display(), error);
+                status = 1;
+                continue;
+            }
+        };
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                status = 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path.display());
+            status = 1;
+        } else if let Err(error) = fs::write(&path, formatted) {
+            eprintln!("cannot write {}: {}", path.display(), error);
+            status = 1;
+        }
+    }
+    status
+}
+
+fn read_stdin() -> Option<String> {
+    let mut source = String::new();
+    match io::stdin().read_to_string(&mut source) {
+        Ok(_) => Some(source),
+        Err(error) => {
+            eprintln!("cannot read stdin: {}", error);
+            None
+        }
+    }
+}
+
+fn fmt_stdin(check: bool) -> i32 {
+    let Some(source) = read_stdin() else {
+        return 1;
+    };
+    match formatter::format(&source) {
+        Ok(formatted) if check => i32::from(formatted != source),
+        Ok(formatted) => {
+            print!("{}", formatted);
+            0
+        }
+        Err(error) => {
+            eprintln!("<stdin>: {}", error);
+            1
+        }
+    }
+}
+
+// `v-lang lint [--config file] [paths...]` reports likely mistakes in files, or in stdin
+// without paths. Rules are switched off in the config file; see `LintConfig::parse`. The
+// status is 1 when anything was reported.
+pub fn lint(args: Vec<String>) -> i32 {
+    let mut args = args.into_iter();
+    let mut config_path = None;
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => match args.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--config expects a file");
+                    return 1;
+                }
+            },
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let config = match lint_config(config_path) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 1;
+        }
+    };
+    let globals = environment::global_env().globals();
+    each_program(&paths, |name, source, program| {
+        let diagnostics = linter::lint(program, &globals, &config);
+        for diagnostic in &diagnostics {
+            let message = format!("{} [{}]", diagnostic.message, diagnostic.rule.name());
+            print_diagnostic(name, source, diagnostic.span, &message);
+        }
+        i32::from(!diagnostics.is_empty())
+    })
+}
+
+// `v-lang check [paths...]` checks the type annotations of files, or of stdin without
+// paths, without running them. The status is 1 when a type error was found.
+pub fn check(args: Vec<String>) -> i32 {
+    let paths: Vec<PathBuf> = args.iter().map(PathBuf::from).collect();
+    each_program(&paths, |name, source, program| {
+        let errors = checker::check(program);
+        for error in &errors {
+            print_diagnostic(name, source, error.span, &error.message);
+        }
+        i32::from(!errors.is_empty())
+    })
+}
+
+// Parses stdin, or each source file of `paths`, and passes it to `check` with its name.
+// The status is 1 when anything failed to read or parse or `check` returned 1.
+fn each_program(paths: &[PathBuf], check: impl Fn(&str, &str, &Ast) -> i32) -> i32 {
+    let parse = |name: &str, source: &str| match lexer::tokenize(source.to_string())
+        .and_then(parser::parse)
+    {
+        Ok(program) => check(name, source, &program),
+        Err(error) => {
+            eprintln!("{}: {}", name, error);
+            1
+        }
+    };
+    if paths.is_empty() {
+        return read_stdin().map_or(1, |source| parse("<stdin>", &source));
+    }
+    let mut status = 0;
+    for path in source_files(paths) {
+        status |= match fs::read_to_string(&path) {
+            Ok(source) => parse(&path.display().to_string(), &source),
+            Err(error) => {
+                eprintln!("cannot read {}: {}", path.display(), error);
+                1
+            }
+        };
+    }
+    status
+}
+
+pub(crate) fn lint_config(path: Option<PathBuf>) -> Result<LintConfig, String> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(LINT_CONFIG).is_file() => PathBuf::from(LINT_CONFIG),
+        None => return Ok(LintConfig::default()),
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    LintConfig::parse(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// `file:line:column: message`, followed by the line and a caret under the column.
+fn print_diagnostic(name: &str, source: &str, span: Span, message: &str) {
+    let Span { line, column, .. } = span;
+    println!("{}:{}: {}", name, span, message);
+    if let Some(text) = source.lines().nth(line - 1) {
+        println!("    {}", text);
+        println!("    {}^", " ".repeat(column - 1));
+    }
+}
+
+// The given files, and the `.v` files anywhere inside the given directories, in order.
+fn source_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        collect_source_files(path, &mut files);
+    }
+    files
+}
+
+fn collect_source_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        files.push(path.to_path_buf());
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    children.sort();
+    for child in children {
+        if child.is_dir() || child.extension().is_some_and(|extension| extension == "v") {
+            collect_source_files(&child, files);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::frontend::span::Span;
+use crate::repl;
+use crate::runtime::capabilities::Capabilities;
+use crate::runtime::convert::IntoValue;
+use crate::runtime::environment::{self, Environment};
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::eval::expressions;
+use crate::runtime::limits::Limits;
+use crate::runtime::types::RuntimeVal;
+
+// A v-lang program held by a Rust host. Globals and declarations persist between calls
+// to `eval`, like lines typed into the repl.
+pub struct Interpreter {
+    environment: Environment,
+}
+
+impl Interpreter {
+    // An interpreter whose scripts cannot perform any I/O.
+    pub fn new() -> Self {
+        Self::with_capabilities(Capabilities::none())
+    }
+
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        Interpreter {
+            environment: environment::global_env_with(capabilities),
+        }
+    }
+
+    // Each call is a new run against the limits: steps, heap and time count from zero.
+    pub fn eval(&mut self, source: &str) -> Result<RuntimeVal, RuntimeError> {
+        self.environment.reset_usage();
+        repl::execute(&mut self.environment, source.to_string())
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.environment.set_limits(limits);
+    }
+
+    // Declares `name` as a global, or replaces its value if it already exists. Constants,
+    // including `null`, `true` and `false`, cannot be replaced.
+    pub fn set_global(&mut self, name: &str, value: impl IntoValue) -> Result<(), RuntimeError> {
+        let value = value.into_value();
+        if self.get_global(name).is_some() {
+            self.environment.assign_variable(name, value)?;
+        } else {
+            self.environment.declare_variable(name, value, false)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<RuntimeVal> {
+        self.environment.clone().lookup_variable(name).ok()
+    }
+
+    // Calls the global function or class `name` with `args`, as a new run like `eval`.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<RuntimeVal>,
+    ) -> Result<RuntimeVal, RuntimeError> {
+        let Some(function) = self.get_global(name) else {
+            return Err(self.environment.error(
+                ErrorKind::Reference,
+                format!("{} variable is not defined", name),
+            ));
+        };
+        self.environment.reset_usage();
+        expressions::call_function(&mut self.environment, function, args, Span::default())
+    }
+
+    // The global environment, for settings such as `set_max_call_depth`.
+    pub fn environment(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
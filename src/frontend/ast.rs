@@ -1,39 +1,359 @@
-#[derive(Debug)]
+use std::fmt::{Display, Formatter};
+
+use crate::frontend::span::Span;
+
+#[derive(Debug, Clone)]
 pub enum Ast {
     Program {
         statements: Vec<Ast>,
     },
+    // `let x: int = 5;`; annotations are read by the type checker and ignored at runtime.
     VariableDeclaration {
         constant: bool,
         identifier: String,
+        annotation: Option<Annotation>,
         value: Box<Ast>,
+        span: Span,
+    },
+    // `let { a, b: renamed } = value;` and `let [x, ...tail] = value;`
+    DestructuringDeclaration {
+        constant: bool,
+        pattern: Box<Pattern>,
+        value: Box<Ast>,
+        span: Span,
     },
     BinaryExpr {
         left: Box<Ast>,
         right: Box<Ast>,
-        operator: char,
+        operator: String,
+        span: Span,
+    },
+    UnaryExpr {
+        operator: String,
+        argument: Box<Ast>,
     },
     AssignmentExpr {
         assignee: Box<Ast>,
         value: Box<Ast>,
+        span: Span,
     },
+    // `a += b`; `operator` is the binary operator applied, `+` here.
+    CompoundAssignmentExpr {
+        assignee: Box<Ast>,
+        operator: String,
+        value: Box<Ast>,
+        span: Span,
+    },
+    // `++a`, `a--`
+    UpdateExpr {
+        operator: String,
+        argument: Box<Ast>,
+        prefix: bool,
+        span: Span,
+    },
+    // `span` is where the `.` or `[` before the property is.
     MemberExpr {
         object: Box<Ast>,
         property: Box<Ast>,
         computed: bool,
+        span: Span,
     },
     CallExpr {
         caller: Box<Ast>,
         args: Vec<Ast>,
+        span: Span,
+    },
+    // `span` is where the name is, or the `fn` keyword of an anonymous function.
+    // `parameter_types` has an optional annotation for each parameter.
+    FunctionDeclaration {
+        name: Option<String>,
+        parameters: Vec<Pattern>,
+        parameter_types: Vec<Option<Annotation>>,
+        return_type: Option<Annotation>,
+        body: Vec<Ast>,
+        span: Span,
+    },
+    ClassDeclaration {
+        name: String,
+        parent: Option<Box<Ast>>,
+        fields: Vec<(String, Option<Ast>)>,
+        methods: Vec<Ast>,
+        span: Span,
+    },
+    EnumDeclaration {
+        name: String,
+        variants: Vec<(String, Vec<String>)>,
+        span: Span,
     },
-    Identifier(String),
+    MatchExpr {
+        subject: Box<Ast>,
+        arms: Vec<MatchArm>,
+    },
+    // `import { name, other as alias } from "./path.v";` as (exported, local) name pairs.
+    ImportDeclaration {
+        names: Vec<(String, String)>,
+        source: String,
+        span: Span,
+    },
+    // `export` in front of a top level declaration.
+    ExportDeclaration {
+        declaration: Box<Ast>,
+    },
+    ReturnStatement {
+        argument: Option<Box<Ast>>,
+        span: Span,
+    },
+    BlockStatement {
+        statements: Vec<Ast>,
+    },
+    TryStatement {
+        block: Box<Ast>,
+        param: Option<(String, Span)>,
+        handler: Option<Box<Ast>>,
+        finalizer: Option<Box<Ast>>,
+    },
+    ThrowStatement {
+        argument: Box<Ast>,
+        span: Span,
+    },
+    Identifier(String, Span),
 
     NumericLiteral(isize),
+    FloatLiteral(f64),
+    StringLiteral(String),
+    // `text ${expression}`; text segments are string literals.
+    TemplateLiteral {
+        parts: Vec<Ast>,
+    },
     PropertyLiteral {
         key: String,
         value: Option<Box<Ast>>,
     },
     ObjectLiteral {
-        properties: Vec<(String, Option<Box<Ast>>)>,
+        properties: Vec<Property>,
+    },
+    ArrayLiteral {
+        elements: Vec<Ast>,
+    },
+    // An access chain containing `?.`; evaluates to null when a guarded value is null.
+    OptionalChain {
+        expression: Box<Ast>,
+    },
+    // The value to the left of `?.`, which stops the enclosing chain when null.
+    NullGuard {
+        argument: Box<Ast>,
     },
+    // `...argument` inside an array literal or call arguments.
+    SpreadElement {
+        argument: Box<Ast>,
+    },
+}
+
+impl Ast {
+    // Where the node is, for the nodes that record it.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Ast::VariableDeclaration { span, .. }
+            | Ast::DestructuringDeclaration { span, .. }
+            | Ast::BinaryExpr { span, .. }
+            | Ast::AssignmentExpr { span, .. }
+            | Ast::CompoundAssignmentExpr { span, .. }
+            | Ast::UpdateExpr { span, .. }
+            | Ast::MemberExpr { span, .. }
+            | Ast::CallExpr { span, .. }
+            | Ast::FunctionDeclaration { span, .. }
+            | Ast::ClassDeclaration { span, .. }
+            | Ast::EnumDeclaration { span, .. }
+            | Ast::ImportDeclaration { span, .. }
+            | Ast::ReturnStatement { span, .. }
+            | Ast::ThrowStatement { span, .. }
+            | Ast::Identifier(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Property {
+    // `key`, `key: value` or `"quoted key": value`; the shorthand form has no value.
+    Named(String, Option<Box<Ast>>),
+    // `[expression]: value`
+    Computed(Ast, Ast),
+    // `key(parameters) { body }`; holds an anonymous function declaration.
+    Method(String, Ast),
+    // `...source` copies the properties of `source`.
+    Spread(Ast),
+}
+
+// A type as written after `:` in a declaration.
+#[derive(Debug, Clone)]
+pub enum Annotation {
+    // `int`, `string`, `any`, `object` or the name of a class or enum.
+    Named(String, Span),
+    // `{ name: string, age: int }`: objects with at least these fields.
+    Object(Vec<(String, Annotation)>, Span),
+    // `int[]`
+    Array(Box<Annotation>),
+    // `int | string`
+    Union(Vec<Annotation>),
+    // `int?`, short for `int | null`.
+    Nullable(Box<Annotation>),
+}
+
+impl Annotation {
+    // Where the annotation starts.
+    pub fn span(&self) -> Span {
+        match self {
+            Annotation::Named(_, span) | Annotation::Object(_, span) => *span,
+            Annotation::Array(element) | Annotation::Nullable(element) => element.span(),
+            Annotation::Union(members) => members[0].span(),
+        }
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Annotation::Named(name, _) => write!(f, "{}", name),
+            Annotation::Object(fields, _) if fields.is_empty() => write!(f, "{{}}"),
+            Annotation::Object(fields, _) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(key, annotation)| format!("{}: {}", key, annotation))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Annotation::Array(element) | Annotation::Nullable(element) => {
+                match **element {
+                    Annotation::Union(_) => write!(f, "({})", element)?,
+                    _ => write!(f, "{}", element)?,
+                }
+                match self {
+                    Annotation::Array(_) => write!(f, "[]"),
+                    _ => write!(f, "?"),
+                }
+            }
+            Annotation::Union(members) => {
+                let members: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+                write!(f, "{}", members.join(" | "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Ast>,
+    pub body: Ast,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // `_`
+    Wildcard,
+    // Numbers, strings, `true`, `false` and `null`; matches by equality.
+    Literal(Ast),
+    // A plain identifier binds the matched value.
+    Binding(String, Span),
+    // `{ key, key: pattern, ...rest }`
+    Object {
+        properties: Vec<(String, Pattern)>,
+        rest: Option<String>,
+    },
+    // `[first, second, ...rest]`
+    Array {
+        elements: Vec<Pattern>,
+        rest: Option<String>,
+    },
+    // `Shape.Circle(r)` or `Shape.Empty`; `enumeration` evaluates to the enum.
+    Variant {
+        enumeration: Box<Ast>,
+        variant: String,
+        fields: Vec<Pattern>,
+    },
+    // `pattern = default`; the default is used when the value is missing or null.
+    Default {
+        pattern: Box<Pattern>,
+        default: Box<Ast>,
+    },
+}
+
+impl Pattern {
+    // The names a declaration using this pattern introduces, in order.
+    pub fn bound_names(&self) -> Vec<String> {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => vec![],
+            Pattern::Binding(name, _) => vec![name.clone()],
+            Pattern::Object { properties, rest } => properties
+                .iter()
+                .flat_map(|(_, pattern)| pattern.bound_names())
+                .chain(rest.clone())
+                .collect(),
+            Pattern::Array { elements, rest } => elements
+                .iter()
+                .flat_map(|pattern| pattern.bound_names())
+                .chain(rest.clone())
+                .collect(),
+            Pattern::Variant { fields, .. } => fields
+                .iter()
+                .flat_map(|pattern| pattern.bound_names())
+                .collect(),
+            Pattern::Default { pattern, .. } => pattern.bound_names(),
+        }
+    }
+
+    pub fn without_default(&self) -> &Pattern {
+        match self {
+            Pattern::Default { pattern, .. } => pattern,
+            pattern => pattern,
+        }
+    }
+}
+
+// Renders the pattern as written, leaving out default values.
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Literal(Ast::NumericLiteral(num)) => write!(f, "{}", num),
+            Pattern::Literal(Ast::FloatLiteral(num)) => write!(f, "{:?}", num),
+            Pattern::Literal(Ast::StringLiteral(str)) => write!(f, "{:?}", str),
+            Pattern::Literal(Ast::Identifier(name, _)) | Pattern::Binding(name, _) => {
+                write!(f, "{}", name)
+            }
+            Pattern::Literal(literal) => write!(f, "{:?}", literal),
+            Pattern::Object { properties, rest } => {
+                let mut parts: Vec<String> = properties
+                    .iter()
+                    .map(|(key, pattern)| match pattern.without_default() {
+                        Pattern::Binding(name, _) if name == key => key.clone(),
+                        pattern => format!("{}: {}", key, pattern),
+                    })
+                    .collect();
+                if let Some(rest) = rest {
+                    parts.push(format!("...{}", rest));
+                }
+                write!(f, "{{ {} }}", parts.join(", "))
+            }
+            Pattern::Array { elements, rest } => {
+                let mut parts: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                if let Some(rest) = rest {
+                    parts.push(format!("...{}", rest));
+                }
+                write!(f, "[{}]", parts.join(", "))
+            }
+            Pattern::Variant {
+                variant, fields, ..
+            } => {
+                write!(f, "{}", variant)?;
+                if !fields.is_empty() {
+                    let fields: Vec<String> = fields.iter().map(|e| e.to_string()).collect();
+                    write!(f, "({})", fields.join(", "))?;
+                }
+                Ok(())
+            }
+            Pattern::Default { pattern, .. } => write!(f, "{}", pattern),
+        }
+    }
 }
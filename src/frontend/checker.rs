@@ -0,0 +1,954 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::frontend::ast::{Annotation, Ast, Pattern, Property};
+use crate::frontend::error::TypeError;
+use crate::frontend::parser::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::frontend::span::Span;
+
+// What the checker knows about a value. Unannotated code is `Any`, which is assignable to
+// and from every type, so code without annotations is never reported.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Any,
+    Int,
+    Float,
+    String,
+    Bool,
+    Null,
+    Array(Box<Type>),
+    // Objects with at least these fields.
+    Object(Vec<(String, Type)>),
+    // The signature is none for values only known to be functions.
+    Function(Option<Box<Signature>>),
+    // A class itself, which is called to make instances of it.
+    Class(String),
+    Instance(String),
+    Enum(String),
+    // A variant of the named enum.
+    Variant(String),
+    Union(Vec<Type>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Signature {
+    parameters: Vec<Parameter>,
+    result: Type,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Parameter {
+    name: String,
+    ty: Type,
+    // Parameters with a default value may be left out.
+    optional: bool,
+}
+
+impl Type {
+    // Whether the type says nothing about some of its values.
+    fn is_gradual(&self) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Union(members) => members.iter().any(Type::is_gradual),
+            _ => false,
+        }
+    }
+
+    fn members(&self) -> Vec<Type> {
+        match self {
+            Type::Union(members) => members.clone(),
+            ty => vec![ty.clone()],
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Null => write!(f, "null"),
+            Type::Array(element) if matches!(**element, Type::Union(_)) => {
+                write!(f, "({})[]", element)
+            }
+            Type::Array(element) => write!(f, "{}[]", element),
+            Type::Object(fields) if fields.is_empty() => write!(f, "object"),
+            Type::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(key, ty)| format!("{}: {}", key, ty))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Type::Function(_) => write!(f, "function"),
+            Type::Class(name) => write!(f, "class {}", name),
+            Type::Enum(name) => write!(f, "enum {}", name),
+            Type::Instance(name) | Type::Variant(name) => write!(f, "{}", name),
+            Type::Union(members) => {
+                let members: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+                write!(f, "{}", members.join(" | "))
+            }
+        }
+    }
+}
+
+// The union of the given types, flattened and without repeats.
+fn union(types: impl IntoIterator<Item = Type>) -> Type {
+    let mut members: Vec<Type> = vec![];
+    for ty in types.into_iter().flat_map(|ty| ty.members()) {
+        if ty == Type::Any {
+            return Type::Any;
+        }
+        if !members.contains(&ty) {
+            members.push(ty);
+        }
+    }
+    match members.len() {
+        0 => Type::Any,
+        1 => members.remove(0),
+        _ => Type::Union(members),
+    }
+}
+
+// Checks the annotations of a parsed program against the values that reach them, and the
+// operators and calls whose operand types are known. Errors come back in source order.
+pub fn check(program: &Ast) -> Vec<TypeError> {
+    let mut checker = Checker {
+        scopes: vec![],
+        classes: HashMap::new(),
+        enums: HashMap::new(),
+        results: vec![],
+        errors: vec![],
+    };
+    checker.push_scope();
+    if let Ast::Program { statements } = program {
+        checker.statements(statements);
+    }
+    checker.pop_scope();
+    let mut errors = checker.errors;
+    // Annotations are read again wherever their function is called, so errors in them
+    // would otherwise repeat.
+    errors.sort_by_key(|error| (error.span.start, error.message.clone()));
+    errors.dedup();
+    errors
+}
+
+// A name in scope; `declared` is its annotation, which assignments are checked against.
+struct Variable {
+    ty: Type,
+    declared: Option<Type>,
+}
+
+// Function and class bodies are checked once every declaration of the scope they are
+// written in is known, as the resolver does.
+struct Scope<'a> {
+    variables: HashMap<String, Variable>,
+    deferred: Vec<&'a Ast>,
+}
+
+struct Checker<'a> {
+    scopes: Vec<Scope<'a>>,
+    // Classes and enums by name, which annotations can name anywhere in the program.
+    classes: HashMap<String, &'a Ast>,
+    enums: HashMap<String, &'a [(String, Vec<String>)]>,
+    // The declared result of each function being checked, innermost last.
+    results: Vec<Option<Type>>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, span: Span, message: String) {
+        self.errors.push(TypeError::new(message, span));
+    }
+
+    // Reports a value of type `ty` where `expected` is required.
+    fn expect(&mut self, ty: &Type, expected: &Type, span: Span, context: &str) {
+        if !self.assignable(ty, expected) {
+            self.report(
+                span,
+                format!("expected {} {}, found {}", expected, context, ty),
+            );
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope {
+            variables: HashMap::new(),
+            deferred: vec![],
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        loop {
+            let deferred = std::mem::take(&mut self.scopes.last_mut().unwrap().deferred);
+            if deferred.is_empty() {
+                break;
+            }
+            for ast in deferred {
+                self.deferred(ast);
+            }
+        }
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, ty: Type, declared: Option<Type>) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope
+            .variables
+            .insert(name.to_string(), Variable { ty, declared });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+    }
+
+    // The type an annotation stands for; unknown names are reported and taken as `any`.
+    fn annotation(&mut self, annotation: &Annotation) -> Type {
+        match annotation {
+            Annotation::Named(name, span) => match name.as_str() {
+                "any" => Type::Any,
+                "int" => Type::Int,
+                "float" => Type::Float,
+                "string" => Type::String,
+                "bool" => Type::Bool,
+                "null" => Type::Null,
+                "array" => Type::Array(Box::new(Type::Any)),
+                "object" => Type::Object(vec![]),
+                "function" => Type::Function(None),
+                name if self.classes.contains_key(name) => Type::Instance(name.to_string()),
+                name if self.enums.contains_key(name) => Type::Variant(name.to_string()),
+                name => {
+                    self.report(*span, format!("unknown type {}", name));
+                    Type::Any
+                }
+            },
+            Annotation::Object(fields, _) => Type::Object(
+                fields
+                    .iter()
+                    .map(|(key, annotation)| (key.clone(), self.annotation(annotation)))
+                    .collect(),
+            ),
+            Annotation::Array(element) => Type::Array(Box::new(self.annotation(element))),
+            Annotation::Union(members) => {
+                let members: Vec<Type> = members.iter().map(|m| self.annotation(m)).collect();
+                union(members)
+            }
+            Annotation::Nullable(inner) => union([self.annotation(inner), Type::Null]),
+        }
+    }
+
+    fn optional_annotation(&mut self, annotation: &Option<Annotation>) -> Option<Type> {
+        annotation
+            .as_ref()
+            .map(|annotation| self.annotation(annotation))
+    }
+
+    // The signature a function declaration is called with; `this` is not a parameter.
+    fn signature(&mut self, function: &Ast) -> Signature {
+        let Ast::FunctionDeclaration {
+            parameters,
+            parameter_types,
+            return_type,
+            ..
+        } = function
+        else {
+            return Signature {
+                parameters: vec![],
+                result: Type::Any,
+            };
+        };
+        let parameters = parameters
+            .iter()
+            .zip(parameter_types)
+            .map(|(pattern, annotation)| Parameter {
+                name: pattern.to_string(),
+                ty: self.optional_annotation(annotation).unwrap_or(Type::Any),
+                optional: matches!(pattern, Pattern::Default { .. }),
+            })
+            .collect();
+        let result = self.optional_annotation(return_type).unwrap_or(Type::Any);
+        Signature { parameters, result }
+    }
+
+    fn subclass(&self, class: &str, ancestor: &str) -> bool {
+        let mut class = Some(class.to_string());
+        while let Some(name) = class {
+            if name == ancestor {
+                return true;
+            }
+            class = self.parent(&name);
+        }
+        false
+    }
+
+    fn parent(&self, class: &str) -> Option<String> {
+        match self.classes.get(class)? {
+            Ast::ClassDeclaration {
+                parent: Some(parent),
+                ..
+            } => match parent.as_ref() {
+                Ast::Identifier(parent, _) => Some(parent.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // The method with the given name that instances of the class have, inherited or not.
+    fn method(&self, class: &str, name: &str) -> Option<&'a Ast> {
+        let mut class = Some(class.to_string());
+        while let Some(current) = class {
+            if let Some(Ast::ClassDeclaration { methods, .. }) = self.classes.get(&current) {
+                let method = methods.iter().find(|method| {
+                    matches!(method, Ast::FunctionDeclaration { name: Some(n), .. } if n == name)
+                });
+                if method.is_some() {
+                    return method;
+                }
+            }
+            class = self.parent(&current);
+        }
+        None
+    }
+
+    // The type of a field or method of an instance; none when the class does not have it.
+    fn instance_member(&mut self, class: &str, name: &str) -> Option<Type> {
+        if let Some(method) = self.method(class, name) {
+            let signature = self.signature(method);
+            return Some(Type::Function(Some(Box::new(signature))));
+        }
+        let mut class = Some(class.to_string());
+        while let Some(current) = class {
+            if let Some(Ast::ClassDeclaration { fields, .. }) = self.classes.get(&current) {
+                if fields.iter().any(|(field, _)| field == name) {
+                    return Some(Type::Any);
+                }
+            }
+            class = self.parent(&current);
+        }
+        None
+    }
+
+    // Whether a value of type `from` can be used where `to` is required.
+    fn assignable(&mut self, from: &Type, to: &Type) -> bool {
+        match (from, to) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Union(members), to) => members.iter().all(|m| self.assignable(m, to)),
+            (from, Type::Union(members)) => members.iter().any(|m| self.assignable(from, m)),
+            (Type::Int, Type::Float) => true,
+            (Type::Array(from), Type::Array(to)) => self.assignable(from, to),
+            (Type::Object(from), Type::Object(to)) => {
+                to.iter().all(
+                    |(key, to)| match from.iter().find(|(field, _)| field == key) {
+                        Some((_, from)) => self.assignable(from, to),
+                        None => false,
+                    },
+                )
+            }
+            (Type::Instance(class), Type::Object(to)) => {
+                to.iter()
+                    .all(|(key, to)| match self.instance_member(class, key) {
+                        Some(from) => self.assignable(&from, to),
+                        None => false,
+                    })
+            }
+            (Type::Instance(from), Type::Instance(to)) => self.subclass(from, to),
+            (Type::Function(_), Type::Function(None)) => true,
+            (from, to) => from == to,
+        }
+    }
+
+    // Makes the classes and enums of a block known before its statements are checked.
+    fn declare_types(&mut self, statements: &'a [Ast]) {
+        for statement in statements {
+            let statement = match statement {
+                Ast::ExportDeclaration { declaration } => declaration.as_ref(),
+                statement => statement,
+            };
+            match statement {
+                Ast::ClassDeclaration { name, .. } => {
+                    self.classes.insert(name.clone(), statement);
+                }
+                Ast::EnumDeclaration { name, variants, .. } => {
+                    self.enums.insert(name.clone(), variants);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // The type of the block's value: that of its last statement.
+    fn statements(&mut self, statements: &'a [Ast]) -> Type {
+        self.declare_types(statements);
+        let mut ty = Type::Any;
+        for statement in statements {
+            ty = self.statement(statement);
+        }
+        ty
+    }
+
+    fn statement(&mut self, statement: &'a Ast) -> Type {
+        match statement {
+            Ast::VariableDeclaration {
+                constant,
+                identifier,
+                annotation,
+                value,
+                span,
+            } => {
+                let ty = self.expression(value);
+                let declared = self.optional_annotation(annotation);
+                if let Some(declared) = &declared {
+                    let context = format!("for {}", identifier);
+                    self.expect(&ty, declared, value.span().unwrap_or(*span), &context);
+                }
+                // Variables may be assigned other values later, unlike constants.
+                let known = match (&declared, constant) {
+                    (Some(declared), _) => declared.clone(),
+                    (None, true) => ty,
+                    (None, false) => Type::Any,
+                };
+                self.declare(identifier, known, declared);
+                Type::Any
+            }
+            Ast::DestructuringDeclaration { pattern, value, .. } => {
+                self.expression(value);
+                self.pattern(pattern);
+                Type::Any
+            }
+            Ast::FunctionDeclaration {
+                name: Some(name), ..
+            } => {
+                let signature = self.signature(statement);
+                self.declare(name, Type::Function(Some(Box::new(signature))), None);
+                self.defer(statement);
+                Type::Any
+            }
+            Ast::ClassDeclaration { name, parent, .. } => {
+                if let Some(parent) = parent {
+                    self.expression(parent);
+                }
+                self.declare(name, Type::Class(name.clone()), None);
+                self.defer(statement);
+                Type::Any
+            }
+            Ast::EnumDeclaration { name, .. } => {
+                self.declare(name, Type::Enum(name.clone()), None);
+                Type::Any
+            }
+            Ast::ImportDeclaration { names, .. } => {
+                for (_, alias) in names {
+                    self.declare(alias, Type::Any, None);
+                }
+                Type::Any
+            }
+            Ast::ExportDeclaration { declaration } => self.statement(declaration),
+            Ast::Program { statements } => self.statements(statements),
+            Ast::BlockStatement { statements } => {
+                self.push_scope();
+                let ty = self.statements(statements);
+                self.pop_scope();
+                ty
+            }
+            Ast::TryStatement {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => {
+                self.statement(block);
+                if let Some(handler) = handler {
+                    self.push_scope();
+                    if let Some((param, _)) = param {
+                        self.declare(param, Type::Any, None);
+                    }
+                    self.statement(handler);
+                    self.pop_scope();
+                }
+                if let Some(finalizer) = finalizer {
+                    self.statement(finalizer);
+                }
+                Type::Any
+            }
+            Ast::ReturnStatement { argument, span } => {
+                let ty = match argument {
+                    Some(argument) => self.expression(argument),
+                    None => Type::Null,
+                };
+                if let Some(Some(result)) = self.results.last().cloned() {
+                    let at = argument.as_ref().and_then(|a| a.span()).unwrap_or(*span);
+                    self.expect(&ty, &result, at, "to be returned");
+                }
+                Type::Any
+            }
+            Ast::ThrowStatement { argument, .. } => {
+                self.expression(argument);
+                Type::Any
+            }
+            expression => self.expression(expression),
+        }
+    }
+
+    fn defer(&mut self, ast: &'a Ast) {
+        self.scopes.last_mut().unwrap().deferred.push(ast);
+    }
+
+    fn deferred(&mut self, ast: &'a Ast) {
+        match ast {
+            Ast::FunctionDeclaration { .. } => self.function(ast, false),
+            Ast::ClassDeclaration {
+                name,
+                fields,
+                methods,
+                ..
+            } => {
+                for (_, value) in fields {
+                    if let Some(value) = value {
+                        self.push_scope();
+                        self.declare("this", Type::Instance(name.clone()), None);
+                        self.expression(value);
+                        self.pop_scope();
+                    }
+                }
+                for method in methods {
+                    self.push_scope();
+                    self.declare("this", Type::Instance(name.clone()), None);
+                    self.function(method, true);
+                    self.pop_scope();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Checks a function body against its parameter and result annotations. The result is
+    // what `return` gives, or the value of the last statement.
+    fn function(&mut self, function: &'a Ast, method: bool) {
+        let Ast::FunctionDeclaration {
+            name,
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+            span,
+        } = function
+        else {
+            return;
+        };
+        self.push_scope();
+        for (pattern, annotation) in parameters.iter().zip(parameter_types) {
+            let ty = self.optional_annotation(annotation);
+            match (pattern.without_default(), ty) {
+                (Pattern::Binding(name, _), Some(ty)) => {
+                    if let Pattern::Default { default, .. } = pattern {
+                        let default_ty = self.expression(default);
+                        let context = format!("for parameter {}", name);
+                        self.expect(&default_ty, &ty, default.span().unwrap_or(*span), &context);
+                    }
+                    self.declare(name, ty.clone(), Some(ty));
+                }
+                _ => self.pattern(pattern),
+            }
+        }
+        // A constructor's value is the instance, whatever its body ends with.
+        let constructor = method && name.as_deref() == Some("constructor");
+        let result = self.optional_annotation(return_type);
+        self.results.push(result.clone());
+        let ty = self.statements(body);
+        self.results.pop();
+        let last = body.last().filter(|last| {
+            !matches!(
+                last,
+                Ast::VariableDeclaration { .. }
+                    | Ast::DestructuringDeclaration { .. }
+                    | Ast::FunctionDeclaration { .. }
+                    | Ast::ClassDeclaration { .. }
+                    | Ast::EnumDeclaration { .. }
+                    | Ast::ReturnStatement { .. }
+                    | Ast::ThrowStatement { .. }
+                    | Ast::TryStatement { .. }
+            )
+        });
+        if let (Some(result), Some(last), false) = (&result, last, constructor) {
+            self.expect(&ty, result, last.span().unwrap_or(*span), "to be returned");
+        }
+        self.pop_scope();
+    }
+
+    // Declares the names a pattern binds, which are of any type, and checks its defaults.
+    fn pattern(&mut self, pattern: &'a Pattern) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Binding(name, _) => self.declare(name, Type::Any, None),
+            Pattern::Object { properties, rest } => {
+                for (_, pattern) in properties {
+                    self.pattern(pattern);
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest, Type::Any, None);
+                }
+            }
+            Pattern::Array { elements, rest } => {
+                for pattern in elements {
+                    self.pattern(pattern);
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest, Type::Any, None);
+                }
+            }
+            Pattern::Variant {
+                enumeration,
+                fields,
+                ..
+            } => {
+                self.expression(enumeration);
+                for pattern in fields {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Default { pattern, default } => {
+                self.expression(default);
+                self.pattern(pattern);
+            }
+        }
+    }
+
+    // Operator and member chains make trees as deep as they are long, so checking grows the
+    // stack on the heap like parsing does.
+    fn expression(&mut self, ast: &'a Ast) -> Type {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.infer(ast))
+    }
+
+    fn infer(&mut self, ast: &'a Ast) -> Type {
+        match ast {
+            Ast::NumericLiteral(_) => Type::Int,
+            Ast::FloatLiteral(_) => Type::Float,
+            Ast::StringLiteral(_) => Type::String,
+            Ast::TemplateLiteral { parts } => {
+                for part in parts {
+                    self.expression(part);
+                }
+                Type::String
+            }
+            Ast::Identifier(name, _) => match name.as_str() {
+                "true" | "false" => Type::Bool,
+                "null" => Type::Null,
+                name => self.lookup(name).map_or(Type::Any, |v| v.ty.clone()),
+            },
+            Ast::BinaryExpr {
+                left,
+                right,
+                operator,
+                span,
+            } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                self.binary(&left, &right, operator, *span)
+            }
+            Ast::UnaryExpr { operator, argument } => {
+                let ty = self.expression(argument);
+                if operator == "!" {
+                    return Type::Bool;
+                }
+                if ty.is_gradual() || ty.members().iter().all(Type::is_numeric) {
+                    return ty;
+                }
+                if let Some(at) = argument.span() {
+                    let message = format!("unsupported operand for {}: {}", operator, ty);
+                    self.report(at, message);
+                }
+                Type::Any
+            }
+            Ast::AssignmentExpr {
+                assignee,
+                value,
+                span,
+            } => {
+                let ty = self.expression(value);
+                self.assign(assignee, &ty, value.span().unwrap_or(*span));
+                ty
+            }
+            Ast::CompoundAssignmentExpr {
+                assignee,
+                operator,
+                value,
+                span,
+            } => {
+                let current = self.expression(assignee);
+                let value = self.expression(value);
+                let ty = self.binary(&current, &value, operator, *span);
+                self.assign(assignee, &ty, *span);
+                ty
+            }
+            Ast::UpdateExpr {
+                operator,
+                argument,
+                span,
+                ..
+            } => {
+                let ty = self.expression(argument);
+                let ty = self.binary(&ty, &Type::Int, &operator[..1], *span);
+                self.assign(argument, &ty, *span);
+                ty
+            }
+            Ast::MemberExpr {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let object = self.expression(object);
+                match (property.as_ref(), computed) {
+                    (Ast::Identifier(key, _), false) => self.member(&object, key),
+                    (property, _) => {
+                        self.expression(property);
+                        match object {
+                            Type::Array(element) => *element,
+                            Type::String => Type::String,
+                            _ => Type::Any,
+                        }
+                    }
+                }
+            }
+            Ast::CallExpr { caller, args, span } => {
+                let caller = self.expression(caller);
+                let arguments: Vec<Type> = args.iter().map(|arg| self.expression(arg)).collect();
+                self.call(&caller, args, &arguments, *span)
+            }
+            Ast::FunctionDeclaration { .. } => {
+                let signature = self.signature(ast);
+                self.defer(ast);
+                Type::Function(Some(Box::new(signature)))
+            }
+            Ast::MatchExpr { subject, arms } => {
+                self.expression(subject);
+                let mut types = vec![];
+                for arm in arms {
+                    self.push_scope();
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    types.push(self.statement(&arm.body));
+                    self.pop_scope();
+                }
+                union(types)
+            }
+            Ast::ObjectLiteral { properties } => {
+                let mut fields: Vec<(String, Type)> = vec![];
+                let mut add = |key: &str, ty: Type| {
+                    fields.retain(|(field, _)| field != key);
+                    fields.push((key.to_string(), ty));
+                };
+                for property in properties {
+                    match property {
+                        Property::Named(key, Some(value)) => add(key, self.expression(value)),
+                        Property::Named(key, None) => {
+                            let ty = self.lookup(key).map_or(Type::Any, |v| v.ty.clone());
+                            add(key, ty)
+                        }
+                        Property::Method(key, function) => add(key, self.expression(function)),
+                        Property::Computed(key, value) => {
+                            self.expression(key);
+                            self.expression(value);
+                        }
+                        Property::Spread(source) => {
+                            if let Type::Object(source) = self.expression(source) {
+                                for (key, ty) in source {
+                                    add(&key, ty);
+                                }
+                            }
+                        }
+                    }
+                }
+                Type::Object(fields)
+            }
+            Ast::ArrayLiteral { elements } => {
+                let elements: Vec<Type> = elements
+                    .iter()
+                    .map(|element| match element {
+                        Ast::SpreadElement { argument } => match self.expression(argument) {
+                            Type::Array(element) => *element,
+                            _ => Type::Any,
+                        },
+                        element => self.expression(element),
+                    })
+                    .collect();
+                Type::Array(Box::new(union(elements)))
+            }
+            Ast::PropertyLiteral { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                Type::Any
+            }
+            Ast::OptionalChain { expression } => {
+                let ty = self.expression(expression);
+                union([ty, Type::Null])
+            }
+            Ast::NullGuard { argument } => {
+                let ty = self.expression(argument);
+                union(ty.members().into_iter().filter(|ty| *ty != Type::Null))
+            }
+            Ast::SpreadElement { argument } => {
+                self.expression(argument);
+                Type::Any
+            }
+            statement => {
+                self.statement(statement);
+                Type::Any
+            }
+        }
+    }
+
+    // The type of `left operator right`, reporting operand types it fails for. Unions are
+    // checked member by member.
+    fn binary(&mut self, left: &Type, right: &Type, operator: &str, span: Span) -> Type {
+        match operator {
+            "==" | "!=" => return Type::Bool,
+            "&&" | "||" => return union([left.clone(), right.clone()]),
+            "??" => {
+                let present = left.members().into_iter().filter(|ty| *ty != Type::Null);
+                return union(present.chain([right.clone()]));
+            }
+            _ => {}
+        }
+        let comparison = matches!(operator, "<" | ">" | "<=" | ">=");
+        if left.is_gradual() || right.is_gradual() {
+            return if comparison { Type::Bool } else { Type::Any };
+        }
+        let mut results = vec![];
+        for left in left.members() {
+            for right in right.members() {
+                let result = match (&left, &right) {
+                    (Type::Int, Type::Int) if comparison => Some(Type::Bool),
+                    (Type::String, Type::String) if comparison => Some(Type::Bool),
+                    (left, right) if left.is_numeric() && right.is_numeric() => {
+                        Some(if comparison {
+                            Type::Bool
+                        } else if *left == Type::Int && *right == Type::Int {
+                            Type::Int
+                        } else {
+                            Type::Float
+                        })
+                    }
+                    (Type::String, _) | (_, Type::String) if operator == "+" => Some(Type::String),
+                    _ => None,
+                };
+                match result {
+                    Some(result) => results.push(result),
+                    None => {
+                        self.report(
+                            span,
+                            format!(
+                                "unsupported operands for {}: {} and {}",
+                                operator, left, right
+                            ),
+                        );
+                        return Type::Any;
+                    }
+                }
+            }
+        }
+        union(results)
+    }
+
+    // Checks a value assigned to a variable against the variable's annotation.
+    fn assign(&mut self, assignee: &'a Ast, ty: &Type, span: Span) {
+        match assignee {
+            Ast::Identifier(name, _) => {
+                let declared = self.lookup(name).and_then(|v| v.declared.clone());
+                if let Some(declared) = declared {
+                    self.expect(ty, &declared, span, &format!("for {}", name));
+                }
+            }
+            Ast::MemberExpr { .. } => {}
+            assignee => {
+                self.expression(assignee);
+            }
+        }
+    }
+
+    fn member(&mut self, object: &Type, key: &str) -> Type {
+        match object {
+            Type::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map_or(Type::Any, |(_, ty)| ty.clone()),
+            Type::Instance(class) => self.instance_member(class, key).unwrap_or(Type::Any),
+            Type::Enum(name) => {
+                let variants = self.enums.get(name).copied().unwrap_or_default();
+                let variant = variants.iter().find(|(variant, _)| variant == key);
+                match variant {
+                    Some((_, fields)) if fields.is_empty() => Type::Variant(name.clone()),
+                    Some((_, fields)) => Type::Function(Some(Box::new(Signature {
+                        parameters: fields
+                            .iter()
+                            .map(|field| Parameter {
+                                name: field.clone(),
+                                ty: Type::Any,
+                                optional: false,
+                            })
+                            .collect(),
+                        result: Type::Variant(name.clone()),
+                    }))),
+                    None => Type::Any,
+                }
+            }
+            _ => Type::Any,
+        }
+    }
+
+    // The type of a call's value, reporting arguments that do not fit the parameters.
+    fn call(&mut self, caller: &Type, args: &[Ast], arguments: &[Type], span: Span) -> Type {
+        let (signature, result) = match caller {
+            Type::Function(Some(signature)) => {
+                (Some(signature.as_ref().clone()), signature.result.clone())
+            }
+            Type::Class(class) => {
+                let constructor = self.method(class, "constructor");
+                let signature = constructor.map(|constructor| self.signature(constructor));
+                (signature, Type::Instance(class.clone()))
+            }
+            Type::Any | Type::Function(None) | Type::Union(_) => return Type::Any,
+            caller => {
+                self.report(span, format!("cannot call a value of type {}", caller));
+                return Type::Any;
+            }
+        };
+        let spread = args
+            .iter()
+            .any(|arg| matches!(arg, Ast::SpreadElement { .. }));
+        if let (Some(signature), false) = (signature, spread) {
+            for (index, parameter) in signature.parameters.iter().enumerate() {
+                let context = format!("for parameter {}", parameter.name);
+                match arguments.get(index) {
+                    Some(argument) => {
+                        let at = args[index].span().unwrap_or(span);
+                        self.expect(argument, &parameter.ty, at, &context);
+                    }
+                    // Missing arguments are null, unless the parameter has a default.
+                    None if !parameter.optional && !self.assignable(&Type::Null, &parameter.ty) => {
+                        let message = format!(
+                            "missing argument {} of type {}",
+                            parameter.name, parameter.ty
+                        );
+                        self.report(span, message);
+                    }
+                    None => {}
+                }
+            }
+        }
+        result
+    }
+}
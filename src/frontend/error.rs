@@ -0,0 +1,41 @@
+use std::fmt::{Display, Formatter};
+
+use crate::frontend::span::Span;
+
+// Source the lexer or parser could not make sense of, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    pub fn new(message: String, span: Span) -> Self {
+        SyntaxError { message, span }
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+// A value whose type does not fit where it is used, found by the type checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl TypeError {
+    pub fn new(message: String, span: Span) -> Self {
+        TypeError { message, span }
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
@@ -0,0 +1,1186 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::frontend::ast::{Annotation, Ast, MatchArm, Pattern, Property};
+use crate::frontend::error::SyntaxError;
+use crate::frontend::lexer::{is_identifier, tokenize_with_comments};
+use crate::frontend::parser;
+use crate::frontend::parser::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::frontend::span::Span;
+use crate::frontend::token::{Comment, Token};
+
+const INDENT: &str = "    ";
+// Lists that would run past this column are broken over several lines.
+const MAX_WIDTH: usize = 100;
+
+// Binding strength of each expression form, loosest first, following the parser.
+const ASSIGNMENT: u8 = 1;
+const OBJECT: u8 = 2;
+const UNARY: u8 = 8;
+const POSTFIX: u8 = 9;
+const MEMBER: u8 = 10;
+
+// Reprints `source` in the canonical style. Comments and single blank lines between lines
+// are kept, and formatting the result again changes nothing.
+pub fn format(source: &str) -> Result<String, SyntaxError> {
+    let (tokens, comments) = tokenize_with_comments(source.to_string())?;
+    let program = parser::parse(tokens.clone())?;
+    let trivia = Trivia::collect(source, &tokens, &comments);
+    let mut broken_lists = BTreeSet::new();
+    loop {
+        let mut printer = Printer {
+            broken_lists: broken_lists.clone(),
+            class_members: class_members(&tokens),
+            ..Printer::default()
+        };
+        printer.program(&program);
+        let code = printer.out;
+        let (formatted_tokens, _) = tokenize_with_comments(code.clone())?;
+        // A comment inside a list that was joined onto one line keeps the list broken.
+        match trivia.misplaced(&code, &tokens, &formatted_tokens, &broken_lists) {
+            Some(list) => {
+                broken_lists.insert(list);
+            }
+            None => return Ok(trivia.restore(&code, &tokens, &formatted_tokens)),
+        }
+    }
+}
+
+fn precedence(ast: &Ast) -> u8 {
+    match ast {
+        Ast::AssignmentExpr { .. } | Ast::CompoundAssignmentExpr { .. } => ASSIGNMENT,
+        Ast::ObjectLiteral { .. } => OBJECT,
+        Ast::BinaryExpr { operator, .. } => match operator.as_str() {
+            "||" | "??" => 3,
+            "&&" => 4,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => 5,
+            "+" | "-" => 6,
+            _ => 7,
+        },
+        Ast::UnaryExpr { .. } | Ast::UpdateExpr { prefix: true, .. } => UNARY,
+        Ast::UpdateExpr { .. } => POSTFIX,
+        _ => MEMBER,
+    }
+}
+
+// Whether each member of every non-empty class, in source order, is a method. The AST keeps
+// fields and methods apart, and the printer puts them back in this order.
+fn class_members(tokens: &[(Token, Span)]) -> VecDeque<Vec<bool>> {
+    let mut classes = VecDeque::new();
+    for (start, (token, _)) in tokens.iter().enumerate() {
+        if *token != Token::Class {
+            continue;
+        }
+        let Some(open) = tokens[start..]
+            .iter()
+            .position(|(token, _)| *token == Token::OpenBrace)
+        else {
+            continue;
+        };
+        let mut members = vec![];
+        let (mut depth, mut member_start, mut in_method) = (0, true, false);
+        for (index, (token, _)) in tokens.iter().enumerate().skip(start + open) {
+            match token {
+                Token::OpenParen | Token::OpenBrace | Token::OpenBracket => depth += 1,
+                Token::CloseParen | Token::CloseBrace | Token::CloseBracket => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    if depth == 1 && in_method && *token == Token::CloseBrace {
+                        member_start = true;
+                    }
+                }
+                Token::Semicolon if depth == 1 => member_start = true,
+                Token::Identifier(_) if depth == 1 && member_start => {
+                    in_method = matches!(tokens.get(index + 1), Some((Token::OpenParen, _)));
+                    members.push(in_method);
+                    member_start = false;
+                }
+                _ => {}
+            }
+        }
+        if !members.is_empty() {
+            classes.push_back(members);
+        }
+    }
+    classes
+}
+
+enum Member<'a> {
+    Field(&'a (String, Option<Ast>)),
+    Method(&'a Ast),
+}
+
+// Prints an AST without comments. In flat mode everything goes on one line, and anything
+// that cannot marks the attempt as broken.
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+    flat: bool,
+    broken: bool,
+    // Output offsets of lists that must not be printed on one line.
+    broken_lists: BTreeSet<usize>,
+    class_members: VecDeque<Vec<bool>>,
+}
+
+impl Printer {
+    fn program(&mut self, program: &Ast) {
+        if let Ast::Program { statements } = program {
+            self.statements(statements, false);
+        }
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn newline(&mut self) {
+        // The first line of the program starts the output.
+        if self.out.is_empty() {
+            return;
+        }
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn column(&self) -> usize {
+        self.out.len() - self.out.rfind('\n').map_or(0, |newline| newline + 1)
+    }
+
+    // What `print` would write on a single line, if it fits on one.
+    fn flat(&self, print: impl FnOnce(&mut Printer)) -> Option<String> {
+        let mut printer = Printer {
+            flat: true,
+            depth: self.depth,
+            ..Printer::default()
+        };
+        print(&mut printer);
+        (!printer.broken && !printer.out.contains('\n')).then_some(printer.out)
+    }
+
+    fn statements(&mut self, statements: &[Ast], function_body: bool) {
+        for (index, statement) in statements.iter().enumerate() {
+            self.newline();
+            // The last expression of a function body is its value, written without `;`
+            // unless it is an assignment.
+            let implicit_return = function_body
+                && index + 1 == statements.len()
+                && !matches!(
+                    statement,
+                    Ast::AssignmentExpr { .. } | Ast::CompoundAssignmentExpr { .. }
+                );
+            self.statement(statement, implicit_return);
+        }
+    }
+
+    fn block(&mut self, statements: &[Ast], function_body: bool) {
+        if statements.is_empty() {
+            self.empty(("{", "}"));
+            return;
+        }
+        if self.flat {
+            self.broken = true;
+            return;
+        }
+        self.out.push('{');
+        self.depth += 1;
+        self.statements(statements, function_body);
+        self.depth -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn statement(&mut self, statement: &Ast, implicit_return: bool) {
+        match statement {
+            Ast::VariableDeclaration {
+                constant,
+                identifier,
+                annotation,
+                value,
+                ..
+            } => {
+                self.out.push_str(if *constant { "const " } else { "let " });
+                self.out.push_str(identifier);
+                if let Some(annotation) = annotation {
+                    self.out.push_str(&format!(": {}", annotation));
+                }
+                let uninitialised = matches!(&**value, Ast::Identifier(name, _) if name == "null");
+                if *constant || !uninitialised {
+                    self.out.push_str(" = ");
+                    self.expression(value, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Ast::DestructuringDeclaration {
+                constant,
+                pattern,
+                value,
+                ..
+            } => {
+                self.out.push_str(if *constant { "const " } else { "let " });
+                self.pattern(pattern);
+                self.out.push_str(" = ");
+                self.expression(value, ASSIGNMENT);
+                self.out.push(';');
+            }
+            Ast::ImportDeclaration { names, source, .. } => {
+                let names: Vec<String> = names
+                    .iter()
+                    .map(|(name, alias)| match name == alias {
+                        true => name.clone(),
+                        false => format!("{} as {}", name, alias),
+                    })
+                    .collect();
+                match names.is_empty() {
+                    true => self.out.push_str("import {}"),
+                    false => self
+                        .out
+                        .push_str(&format!("import {{ {} }}", names.join(", "))),
+                }
+                self.out.push_str(&format!(" from {};", quote(source)));
+            }
+            Ast::ExportDeclaration { declaration } => {
+                self.out.push_str("export ");
+                self.statement(declaration, false);
+            }
+            Ast::ReturnStatement { argument, .. } => {
+                self.out.push_str("return");
+                if let Some(argument) = argument {
+                    self.out.push(' ');
+                    self.expression(argument, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Ast::ThrowStatement { argument, .. } => {
+                self.out.push_str("throw ");
+                self.expression(argument, ASSIGNMENT);
+                self.out.push(';');
+            }
+            Ast::FunctionDeclaration { name: Some(_), .. }
+            | Ast::ClassDeclaration { .. }
+            | Ast::EnumDeclaration { .. }
+            | Ast::TryStatement { .. } => self.node(statement),
+            expression => {
+                self.expression(expression, ASSIGNMENT);
+                if !implicit_return {
+                    self.out.push(';');
+                }
+            }
+        }
+    }
+
+    // Prints `ast`, in parentheses if it binds more loosely than its position requires.
+    fn expression(&mut self, ast: &Ast, min_precedence: u8) {
+        if precedence(ast) < min_precedence {
+            self.out.push('(');
+            self.node(ast);
+            self.out.push(')');
+        } else {
+            self.node(ast);
+        }
+    }
+
+    // Operator and member chains make trees as deep as they are long, so printing grows the
+    // stack on the heap like parsing does.
+    fn node(&mut self, ast: &Ast) {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.print_node(ast))
+    }
+
+    fn print_node(&mut self, ast: &Ast) {
+        match ast {
+            Ast::Program { statements } | Ast::BlockStatement { statements } => {
+                self.block(statements, false)
+            }
+            Ast::Identifier(name, _) => self.out.push_str(name),
+            Ast::NumericLiteral(num) => self.out.push_str(&num.to_string()),
+            Ast::FloatLiteral(num) => self.out.push_str(&float_literal(*num)),
+            Ast::StringLiteral(text) => self.out.push_str(&quote(text)),
+            Ast::TemplateLiteral { parts } => {
+                self.out.push('`');
+                for part in parts {
+                    match part {
+                        Ast::StringLiteral(text) => self.out.push_str(&template_text(text)),
+                        expression => {
+                            self.out.push_str("${");
+                            self.expression(expression, ASSIGNMENT);
+                            self.out.push('}');
+                        }
+                    }
+                }
+                self.out.push('`');
+            }
+            Ast::ObjectLiteral { properties } => {
+                self.list(("{", "}"), true, true, properties, Printer::property)
+            }
+            Ast::ArrayLiteral { elements } => {
+                self.list(("[", "]"), false, true, elements, |printer, element| {
+                    printer.expression(element, ASSIGNMENT)
+                })
+            }
+            Ast::SpreadElement { argument } => {
+                self.out.push_str("...");
+                self.expression(argument, ASSIGNMENT);
+            }
+            Ast::BinaryExpr {
+                left,
+                right,
+                operator,
+                ..
+            } => {
+                let precedence = precedence(ast);
+                self.expression(left, precedence);
+                self.out.push_str(&format!(" {} ", operator));
+                self.expression(right, precedence + 1);
+            }
+            Ast::UnaryExpr { operator, argument } => {
+                self.out.push_str(operator);
+                // `- -x` must not run together into `--x`.
+                let negated = match &**argument {
+                    Ast::UnaryExpr { operator, .. } => operator == "-",
+                    Ast::UpdateExpr {
+                        operator, prefix, ..
+                    } => *prefix && operator == "--",
+                    _ => false,
+                };
+                self.expression(argument, if negated { u8::MAX } else { UNARY });
+            }
+            Ast::UpdateExpr {
+                operator,
+                argument,
+                prefix: true,
+                ..
+            } => {
+                self.out.push_str(operator);
+                self.expression(argument, UNARY);
+            }
+            Ast::UpdateExpr {
+                operator, argument, ..
+            } => {
+                self.expression(argument, MEMBER);
+                self.out.push_str(operator);
+            }
+            Ast::AssignmentExpr {
+                assignee, value, ..
+            } => {
+                self.expression(assignee, OBJECT);
+                self.out.push_str(" = ");
+                self.expression(value, ASSIGNMENT);
+            }
+            Ast::CompoundAssignmentExpr {
+                assignee,
+                operator,
+                value,
+                ..
+            } => {
+                self.expression(assignee, OBJECT);
+                self.out.push_str(&format!(" {}= ", operator));
+                self.expression(value, ASSIGNMENT);
+            }
+            Ast::MemberExpr {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let guarded = self.guarded(object);
+                match (computed, &**property) {
+                    (false, Ast::Identifier(name, _)) => {
+                        self.out.push_str(if guarded { "?." } else { "." });
+                        self.out.push_str(name);
+                    }
+                    _ => {
+                        self.out.push_str(if guarded { "?.[" } else { "[" });
+                        self.expression(property, ASSIGNMENT);
+                        self.out.push(']');
+                    }
+                }
+            }
+            Ast::CallExpr { caller, args, .. } => {
+                if self.guarded(caller) {
+                    self.out.push_str("?.");
+                }
+                self.arguments(args);
+            }
+            Ast::OptionalChain { expression } => self.node(expression),
+            Ast::NullGuard { argument } => self.expression(argument, MEMBER),
+            Ast::FunctionDeclaration { name, .. } => {
+                match name {
+                    Some(name) => self.out.push_str(&format!("fn {}", name)),
+                    None => self.out.push_str("fn "),
+                }
+                self.function(ast);
+            }
+            Ast::ClassDeclaration {
+                name,
+                parent,
+                fields,
+                methods,
+                ..
+            } => self.class(name, parent.as_deref(), fields, methods),
+            Ast::EnumDeclaration { name, variants, .. } => {
+                self.out.push_str(&format!("enum {} ", name));
+                self.list(
+                    ("{", "}"),
+                    true,
+                    true,
+                    variants,
+                    |printer, (name, fields)| {
+                        printer.out.push_str(name);
+                        if !fields.is_empty() {
+                            printer.out.push_str(&format!("({})", fields.join(", ")));
+                        }
+                    },
+                );
+            }
+            Ast::MatchExpr { subject, arms } => {
+                self.out.push_str("match ");
+                self.expression(subject, ASSIGNMENT);
+                self.out.push(' ');
+                self.arms(arms);
+            }
+            Ast::TryStatement {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => {
+                self.out.push_str("try ");
+                self.node(block);
+                if let Some(handler) = handler {
+                    match param {
+                        Some((param, _)) => self.out.push_str(&format!(" catch ({}) ", param)),
+                        None => self.out.push_str(" catch "),
+                    }
+                    self.node(handler);
+                }
+                if let Some(finalizer) = finalizer {
+                    self.out.push_str(" finally ");
+                    self.node(finalizer);
+                }
+            }
+            Ast::PropertyLiteral { key, value } => {
+                self.out.push_str(&property_key(key));
+                if let Some(value) = value {
+                    self.out.push_str(": ");
+                    self.expression(value, ASSIGNMENT);
+                }
+            }
+            statement => self.statement(statement, false),
+        }
+    }
+
+    // Prints the object of a member access or the callee of a call, returning whether it
+    // is reached through `?.`.
+    fn guarded(&mut self, object: &Ast) -> bool {
+        match object {
+            Ast::NullGuard { argument } => {
+                self.expression(argument, MEMBER);
+                true
+            }
+            object => {
+                self.expression(object, MEMBER);
+                false
+            }
+        }
+    }
+
+    // Prints `items` between `brackets` on one line if they fit, otherwise one per line.
+    // `padded` lists put spaces inside their brackets when on one line.
+    fn list<T>(
+        &mut self,
+        brackets: (&str, &str),
+        padded: bool,
+        trailing_comma: bool,
+        items: &[T],
+        print: impl Fn(&mut Printer, &T),
+    ) {
+        let (open, close) = brackets;
+        if items.is_empty() {
+            self.empty(brackets);
+            return;
+        }
+        let flat = self.flat(|printer| {
+            printer.out.push_str(open);
+            if padded {
+                printer.out.push(' ');
+            }
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    printer.out.push_str(", ");
+                }
+                print(printer, item);
+            }
+            if padded {
+                printer.out.push(' ');
+            }
+            printer.out.push_str(close);
+        });
+        if let Some(flat) = flat {
+            let start = self.out.len();
+            let forced = !self.flat
+                && self
+                    .broken_lists
+                    .range(start..start + flat.len())
+                    .next()
+                    .is_some();
+            if self.flat || (!forced && self.column() + flat.len() <= MAX_WIDTH) {
+                self.out.push_str(&flat);
+                return;
+            }
+        }
+        if self.flat {
+            self.broken = true;
+            return;
+        }
+        self.out.push_str(open);
+        self.depth += 1;
+        for (index, item) in items.iter().enumerate() {
+            self.newline();
+            print(self, item);
+            if trailing_comma || index + 1 < items.len() {
+                self.out.push(',');
+            }
+        }
+        self.depth -= 1;
+        self.newline();
+        self.out.push_str(close);
+    }
+
+    // Empty brackets, kept apart on two lines when a comment sits between them.
+    fn empty(&mut self, brackets: (&str, &str)) {
+        let (open, close) = brackets;
+        let broken = !self.flat && self.broken_lists.contains(&self.out.len());
+        self.out.push_str(open);
+        if broken {
+            self.newline();
+        }
+        self.out.push_str(close);
+    }
+
+    // Call arguments. A trailing function, object or array that needs several lines keeps
+    // the call open on the first line: `items.map(fn (item) {`.
+    fn arguments(&mut self, args: &[Ast]) {
+        let print = |printer: &mut Printer, arg: &Ast| printer.expression(arg, ASSIGNMENT);
+        let huggable = matches!(
+            args.last(),
+            Some(
+                Ast::FunctionDeclaration { .. }
+                    | Ast::ObjectLiteral { .. }
+                    | Ast::ArrayLiteral { .. }
+                    | Ast::MatchExpr { .. }
+            )
+        );
+        if !self.flat && huggable {
+            let (last, leading) = args.split_last().unwrap();
+            let all_fit = self.flat(|printer| {
+                printer.list(("(", ")"), false, false, args, print);
+            });
+            let leading = self.flat(|printer| {
+                for arg in leading {
+                    print(printer, arg);
+                    printer.out.push_str(", ");
+                }
+            });
+            if let (None, Some(leading)) = (all_fit, leading) {
+                if self.column() + leading.len() < MAX_WIDTH {
+                    self.out.push('(');
+                    self.out.push_str(&leading);
+                    print(self, last);
+                    self.out.push(')');
+                    return;
+                }
+            }
+        }
+        // Call arguments cannot end with a comma.
+        self.list(("(", ")"), false, false, args, print);
+    }
+
+    // The parameters, return type and body of a function declaration.
+    fn function(&mut self, function: &Ast) {
+        let Ast::FunctionDeclaration {
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+            ..
+        } = function
+        else {
+            return;
+        };
+        let parameters: Vec<(&Pattern, &Option<Annotation>)> =
+            parameters.iter().zip(parameter_types).collect();
+        self.list(
+            ("(", ")"),
+            false,
+            true,
+            &parameters,
+            |printer, parameter| printer.parameter(parameter.0, parameter.1.as_ref()),
+        );
+        if let Some(return_type) = return_type {
+            self.out.push_str(&format!(": {}", return_type));
+        }
+        self.out.push(' ');
+        self.block(body, true);
+    }
+
+    // The annotation of a parameter goes before its default value.
+    fn parameter(&mut self, pattern: &Pattern, annotation: Option<&Annotation>) {
+        let Some(annotation) = annotation else {
+            return self.pattern(pattern);
+        };
+        self.pattern(pattern.without_default());
+        self.out.push_str(&format!(": {}", annotation));
+        if let Pattern::Default { default, .. } = pattern {
+            self.out.push_str(" = ");
+            self.expression(default, OBJECT);
+        }
+    }
+
+    fn property(&mut self, property: &Property) {
+        match property {
+            Property::Named(key, None) => self.out.push_str(key),
+            Property::Named(key, Some(value)) => {
+                self.out.push_str(&property_key(key));
+                self.out.push_str(": ");
+                self.expression(value, ASSIGNMENT);
+            }
+            Property::Computed(key, value) => {
+                self.out.push('[');
+                self.expression(key, ASSIGNMENT);
+                self.out.push_str("]: ");
+                self.expression(value, ASSIGNMENT);
+            }
+            Property::Method(key, function) => {
+                self.out.push_str(key);
+                self.function(function);
+            }
+            Property::Spread(source) => {
+                self.out.push_str("...");
+                self.expression(source, ASSIGNMENT);
+            }
+        }
+    }
+
+    fn class(
+        &mut self,
+        name: &str,
+        parent: Option<&Ast>,
+        fields: &[(String, Option<Ast>)],
+        methods: &[Ast],
+    ) {
+        self.out.push_str(&format!("class {} ", name));
+        if let Some(parent) = parent {
+            self.out.push_str("extends ");
+            // The parent is parsed as a member expression, which cannot contain calls.
+            match is_member_path(parent) {
+                true => self.node(parent),
+                false => self.expression(parent, u8::MAX),
+            }
+            self.out.push(' ');
+        }
+        if fields.is_empty() && methods.is_empty() {
+            self.empty(("{", "}"));
+            return;
+        }
+        if self.flat {
+            self.broken = true;
+            return;
+        }
+        let order = self.class_members.pop_front().unwrap_or_default();
+        let (mut fields, mut methods) = (fields.iter(), methods.iter());
+        // Members in source order, then any the order does not account for.
+        let mut members: Vec<Member> = order
+            .into_iter()
+            .filter_map(|is_method| match is_method {
+                true => methods.next().map(Member::Method),
+                false => fields.next().map(Member::Field),
+            })
+            .collect();
+        members.extend(fields.map(Member::Field));
+        members.extend(methods.map(Member::Method));
+        self.out.push('{');
+        self.depth += 1;
+        for (index, member) in members.iter().enumerate() {
+            // Methods are set apart from the members around them by an empty line.
+            let is_method = |member: &Member| matches!(member, Member::Method(_));
+            if index > 0 && (is_method(member) || is_method(&members[index - 1])) {
+                self.out.push('\n');
+            }
+            self.newline();
+            match member {
+                Member::Field((field, value)) => {
+                    self.out.push_str(field);
+                    if let Some(value) = value {
+                        self.out.push_str(" = ");
+                        self.expression(value, ASSIGNMENT);
+                    }
+                    self.out.push(';');
+                }
+                Member::Method(method @ Ast::FunctionDeclaration { name, .. }) => {
+                    self.out.push_str(name.as_deref().unwrap_or_default());
+                    self.function(method);
+                }
+                Member::Method(_) => {}
+            }
+        }
+        self.depth -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn arms(&mut self, arms: &[MatchArm]) {
+        if arms.is_empty() {
+            self.empty(("{", "}"));
+            return;
+        }
+        if self.flat {
+            self.broken = true;
+            return;
+        }
+        self.out.push('{');
+        self.depth += 1;
+        for arm in arms {
+            self.newline();
+            self.pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                self.out.push_str(" if ");
+                self.expression(guard, ASSIGNMENT);
+            }
+            self.out.push_str(" => ");
+            self.expression(&arm.body, ASSIGNMENT);
+            self.out.push(',');
+        }
+        self.depth -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard => self.out.push('_'),
+            Pattern::Literal(literal) => self.node(literal),
+            Pattern::Binding(name, _) => self.out.push_str(name),
+            Pattern::Object { properties, rest } => {
+                let mut parts: Vec<String> = properties
+                    .iter()
+                    .map(|(key, pattern)| {
+                        let mut printer = Printer::default();
+                        match pattern.without_default() {
+                            Pattern::Binding(name, _) if name == key => {
+                                printer.out.push_str(key);
+                                if let Pattern::Default { default, .. } = pattern {
+                                    printer.out.push_str(" = ");
+                                    printer.expression(default, OBJECT);
+                                }
+                            }
+                            _ => {
+                                printer.out.push_str(&format!("{}: ", key));
+                                printer.pattern(pattern);
+                            }
+                        }
+                        printer.out
+                    })
+                    .collect();
+                parts.extend(rest.iter().map(|rest| format!("...{}", rest)));
+                match parts.is_empty() {
+                    true => self.out.push_str("{}"),
+                    false => self.out.push_str(&format!("{{ {} }}", parts.join(", "))),
+                }
+            }
+            Pattern::Array { elements, rest } => {
+                self.out.push('[');
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(element);
+                }
+                if let Some(rest) = rest {
+                    if !elements.is_empty() {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&format!("...{}", rest));
+                }
+                self.out.push(']');
+            }
+            Pattern::Variant {
+                enumeration,
+                variant,
+                fields,
+            } => {
+                self.node(enumeration);
+                self.out.push_str(&format!(".{}", variant));
+                if !fields.is_empty() {
+                    self.out.push('(');
+                    for (index, field) in fields.iter().enumerate() {
+                        if index > 0 {
+                            self.out.push_str(", ");
+                        }
+                        self.pattern(field);
+                    }
+                    self.out.push(')');
+                }
+            }
+            Pattern::Default { pattern, default } => {
+                self.pattern(pattern);
+                self.out.push_str(" = ");
+                self.expression(default, OBJECT);
+            }
+        }
+    }
+}
+
+// Whether `ast` is a name or a chain of `.name` accesses on one.
+fn is_member_path(mut ast: &Ast) -> bool {
+    while let Ast::MemberExpr {
+        object,
+        computed: false,
+        ..
+    } = ast
+    {
+        ast = object;
+    }
+    matches!(ast, Ast::Identifier(..))
+}
+
+fn float_literal(num: f64) -> String {
+    let text = num.to_string();
+    match text.contains('.') {
+        true => text,
+        false => format!("{}.0", text),
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Escapes the text of a template literal; line breaks are kept as written.
+fn template_text(text: &str) -> String {
+    let mut escaped = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => escaped.push_str("\\`"),
+            '\\' => escaped.push_str("\\\\"),
+            '\r' => escaped.push_str("\\r"),
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Object keys that are not plain names stay quoted.
+fn property_key(key: &str) -> String {
+    match is_identifier(key) {
+        true => key.to_string(),
+        false => quote(key),
+    }
+}
+
+// Lexers keep formatting differences to punctuation, so tokens line up except for
+// parentheses, semicolons and commas the printer added or dropped.
+fn same_token(original: &Token, formatted: &Token) -> bool {
+    original.kind() == formatted.kind()
+        && (matches!(original, Token::Float(_) | Token::Template(_)) || original == formatted)
+}
+
+// How far ahead `align` looks for the token streams to come back in step.
+const LOOKAHEAD: usize = 16;
+
+// Pairs each original token with the formatted token it became, if any.
+fn align(original: &[(Token, Span)], formatted: &[(Token, Span)]) -> Vec<Option<usize>> {
+    let mut aligned = vec![None; original.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < original.len() && j < formatted.len() {
+        if same_token(&original[i].0, &formatted[j].0) {
+            aligned[i] = Some(j);
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // Skip the fewest tokens on either side that brings the streams back in step.
+        let (skip_original, skip_formatted) = (1..=LOOKAHEAD)
+            .find_map(|k| {
+                if original
+                    .get(i + k)
+                    .is_some_and(|(token, _)| same_token(token, &formatted[j].0))
+                {
+                    Some((k, 0))
+                } else if formatted
+                    .get(j + k)
+                    .is_some_and(|(token, _)| same_token(&original[i].0, token))
+                {
+                    Some((0, k))
+                } else {
+                    None
+                }
+            })
+            .unwrap_or((1, 1));
+        i += skip_original;
+        j += skip_formatted;
+    }
+    aligned
+}
+
+// The offset of the outermost bracket that opens and closes on the line of `anchor` around
+// it, leaving out those already in `excluded`. For a comment `after` the anchor, breaking
+// the list the anchor opens helps and breaking the one it closes doesn't; for a comment
+// before it, the other way around.
+fn enclosing_list(
+    tokens: &[(Token, Span)],
+    anchor: usize,
+    after: bool,
+    excluded: &BTreeSet<usize>,
+) -> Option<usize> {
+    let Span { line, start, .. } = tokens[anchor].1;
+    let mut open = vec![];
+    let mut outermost = None;
+    for (token, span) in tokens.iter().filter(|(_, span)| span.line == line) {
+        match token {
+            Token::OpenParen | Token::OpenBrace | Token::OpenBracket => open.push(span.start),
+            Token::CloseParen | Token::CloseBrace | Token::CloseBracket => {
+                if let Some(opened) = open.pop() {
+                    let encloses = match after {
+                        true => opened <= start && start < span.start,
+                        false => opened < start && start <= span.start,
+                    };
+                    if encloses
+                        && !excluded.contains(&opened)
+                        && outermost.is_none_or(|o| opened < o)
+                    {
+                        outermost = Some(opened);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    outermost
+}
+
+// What sits between two tokens besides spaces.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    // A comment following code on the same line.
+    Trailing(String),
+    // A comment on a line of its own.
+    Comment(String),
+    // One or more empty lines.
+    Blank,
+}
+
+// The comments and blank lines of a source, each attached to the token after it.
+struct Trivia {
+    before: Vec<Vec<Item>>,
+}
+
+impl Trivia {
+    fn collect(source: &str, tokens: &[(Token, Span)], comments: &[Comment]) -> Self {
+        let mut before = vec![vec![]; tokens.len()];
+        // Comments inside a template literal's expressions lie within its token.
+        let newlines = |from: usize, to: usize| {
+            source
+                .get(from..to)
+                .map_or(0, |gap| gap.matches('\n').count())
+        };
+        let mut comments = comments.iter().peekable();
+        let mut previous_end = 0;
+        for (index, (_, span)) in tokens.iter().enumerate() {
+            let items = &mut before[index];
+            while let Some(comment) = comments.next_if(|comment| comment.span.end <= span.start) {
+                let start = comment.span.start;
+                let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+                if index > 0 && !source[line_start..start].trim().is_empty() {
+                    items.push(Item::Trailing(comment.text.clone()));
+                } else {
+                    if newlines(previous_end, start) > 1 {
+                        items.push(Item::Blank);
+                    }
+                    items.push(Item::Comment(comment.text.clone()));
+                }
+                previous_end = comment.span.end;
+            }
+            if newlines(previous_end, span.start) > 1 {
+                items.push(Item::Blank);
+            }
+            previous_end = span.end;
+        }
+        Trivia { before }
+    }
+
+    // The offset in `code` of a list to break so that the first comment that would have to
+    // move can stay in place: the outermost list that contains the comment's token and was
+    // joined onto that token's line.
+    fn misplaced(
+        &self,
+        code: &str,
+        tokens: &[(Token, Span)],
+        formatted_tokens: &[(Token, Span)],
+        broken_lists: &BTreeSet<usize>,
+    ) -> Option<usize> {
+        let aligned = align(tokens, formatted_tokens);
+        let line_of = |token: usize| formatted_tokens[token].1.line;
+        let last_line_of = |token: usize| {
+            let span = formatted_tokens[token].1;
+            span.line + code[span.start..span.end].matches('\n').count()
+        };
+        let mut commented_lines = BTreeSet::new();
+        for (index, items) in self.before.iter().enumerate() {
+            for item in items {
+                let (anchor, after) = match item {
+                    Item::Trailing(_) => {
+                        let Some(previous) = (0..index).rev().find_map(|index| aligned[index])
+                        else {
+                            continue;
+                        };
+                        // Commas and semicolons the printer added may follow the token.
+                        let next = formatted_tokens[previous + 1..]
+                            .iter()
+                            .find(|(token, _)| !matches!(token, Token::Comma | Token::Semicolon));
+                        let line = last_line_of(previous);
+                        let ends_line = next
+                            .is_none_or(|(token, span)| *token == Token::Eof || span.line > line);
+                        if ends_line && commented_lines.insert(line) {
+                            continue;
+                        }
+                        (previous, true)
+                    }
+                    Item::Comment(_) => {
+                        let Some(target) = (index..tokens.len()).find_map(|index| aligned[index])
+                        else {
+                            continue;
+                        };
+                        let span = formatted_tokens[target].1;
+                        if target == 0 || line_of(target - 1) < span.line {
+                            continue;
+                        }
+                        (target, false)
+                    }
+                    Item::Blank => continue,
+                };
+                if let Some(list) = enclosing_list(formatted_tokens, anchor, after, broken_lists) {
+                    return Some(list);
+                }
+            }
+        }
+        None
+    }
+
+    // Lays the trivia out around `code`, the printed form of `tokens`. Comments inside a
+    // line that was joined move in front of it.
+    fn restore(
+        self,
+        code: &str,
+        tokens: &[(Token, Span)],
+        formatted_tokens: &[(Token, Span)],
+    ) -> String {
+        let lines: Vec<&str> = code.lines().collect();
+        let aligned = align(tokens, formatted_tokens);
+        let line_of = |token: usize| formatted_tokens[token].1.line - 1;
+        let last_line_of = |token: usize| {
+            let span = formatted_tokens[token].1;
+            span.line - 1 + code[span.start..span.end].matches('\n').count()
+        };
+        let starts_line = |token: usize| {
+            let span = formatted_tokens[token].1;
+            lines
+                .get(span.line - 1)
+                .is_none_or(|line| line[..span.column - 1].trim().is_empty())
+        };
+        // Indexed by formatted line; the last entry holds what follows the final line.
+        let mut before: Vec<Vec<Item>> = vec![vec![]; lines.len() + 1];
+        let mut trailing: Vec<Option<String>> = vec![None; lines.len()];
+        for (index, items) in self.before.into_iter().enumerate() {
+            if items.is_empty() {
+                continue;
+            }
+            let target = (index..tokens.len())
+                .find_map(|index| aligned[index])
+                .unwrap_or(formatted_tokens.len() - 1);
+            let target_line = line_of(target).min(lines.len());
+            let item_count = items.len();
+            for (position, item) in items.into_iter().enumerate() {
+                match item {
+                    Item::Trailing(text) => {
+                        let previous = (0..index).rev().find_map(|index| aligned[index]);
+                        match previous.map(last_line_of) {
+                            Some(line) if line < lines.len() && trailing[line].is_none() => {
+                                trailing[line] = Some(text)
+                            }
+                            _ => before[target_line].push(Item::Comment(text)),
+                        }
+                    }
+                    // A blank line right before a token only survives if the token still
+                    // starts a line.
+                    Item::Blank if position + 1 == item_count && !starts_line(target) => {}
+                    item => before[target_line].push(item),
+                }
+            }
+        }
+
+        let mut out: Vec<String> = vec![];
+        // Whether an empty line may follow what was written so far.
+        let mut blank_allowed = false;
+        for (index, items) in before.into_iter().enumerate() {
+            let line = lines.get(index).copied();
+            let indent = match line {
+                Some(line) => {
+                    let code = line.trim_start();
+                    let mut indent = line[..line.len() - code.len()].to_string();
+                    // Comments before a closing bracket belong to the lines above it.
+                    if code.starts_with(['}', ']', ')']) {
+                        indent.push_str(INDENT);
+                    }
+                    indent
+                }
+                None => String::new(),
+            };
+            let closes = line.is_some_and(|line| line.trim_start().starts_with(['}', ']', ')']));
+            for item in items {
+                match item {
+                    Item::Blank if blank_allowed && !closes => {
+                        out.push(String::new());
+                        blank_allowed = false;
+                    }
+                    Item::Blank => {}
+                    Item::Comment(text) | Item::Trailing(text) => {
+                        out.push(format!("{}{}", indent, text));
+                        blank_allowed = true;
+                    }
+                }
+            }
+            if let Some(line) = line {
+                match &trailing[index] {
+                    Some(comment) => out.push(format!("{} {}", line, comment)),
+                    None => out.push(line.to_string()),
+                }
+                blank_allowed = !line.is_empty() && !line.ends_with(['{', '[', '(']);
+            }
+        }
+        while out.last().is_some_and(|line| line.is_empty()) {
+            out.pop();
+        }
+        if out.is_empty() {
+            return String::new();
+        }
+        out.join("\n") + "\n"
+    }
+}
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
-use std::process::exit;
 
-use crate::frontend::token::Token;
+use crate::frontend::error::SyntaxError;
+use crate::frontend::parser::{MAX_NESTING_DEPTH, STACK_RED_ZONE, STACK_SEGMENT};
+use crate::frontend::span::Span;
+use crate::frontend::token::{Comment, TemplatePart, Token};
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -9,18 +11,82 @@ lazy_static! {
         let mut map = HashMap::new();
         map.insert("let", Token::Let);
         map.insert("const", Token::Const);
+        map.insert("try", Token::Try);
+        map.insert("catch", Token::Catch);
+        map.insert("finally", Token::Finally);
+        map.insert("throw", Token::Throw);
+        map.insert("fn", Token::Fn);
+        map.insert("return", Token::Return);
+        map.insert("class", Token::Class);
+        map.insert("extends", Token::Extends);
+        map.insert("enum", Token::Enum);
+        map.insert("match", Token::Match);
+        map.insert("if", Token::If);
+        map.insert("import", Token::Import);
+        map.insert("export", Token::Export);
         map
     };
 }
-pub fn tokenize(source: String) -> Vec<Token> {
-    let mut tokens: Vec<Token> = Vec::new();
-    if !source.is_ascii() {
-        panic!("language does not support non ancii characters in source code");
+pub fn tokenize(source: String) -> Result<Vec<(Token, Span)>, SyntaxError> {
+    Ok(tokenize_with_comments(source)?.0)
+}
+
+// Whether `name` lexes as one identifier, so it can be written unquoted as a property key.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains_key(name)
+}
+
+// The tokens of a source along with its comments in source order.
+type Commented = (Vec<(Token, Span)>, Vec<Comment>);
+
+// Like `tokenize`, also returning the comments.
+pub fn tokenize_with_comments(source: String) -> Result<Commented, SyntaxError> {
+    if let Some(index) = source.find(|c: char| !c.is_ascii()) {
+        return Err(error_at(
+            source.as_str(),
+            index,
+            "language does not support non ascii characters in source code",
+        ));
     }
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut comments = vec![];
+    let tokens = lex(
+        source.as_str(),
+        0,
+        source.len(),
+        &mut line,
+        &mut line_start,
+        &mut comments,
+    )?;
+    Ok((tokens, comments))
+}
+
+// Tokenizes `source[start..end]`, ending with `Eof`. Spans are positions in the whole
+// source, so expressions nested in template literals report where they really are.
+fn lex(
+    source: &str,
+    start: usize,
+    end: usize,
+    line: &mut usize,
+    line_start: &mut usize,
+    comments: &mut Vec<Comment>,
+) -> Result<Vec<(Token, Span)>, SyntaxError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
     let source_chars: &[u8] = source.as_bytes();
-    let mut index = 0;
-    while index < source_chars.len() {
+    let mut index = start;
+    while index < end {
         let char_at_index = source_chars[index] as char;
+        let start = index;
+        let start_line = *line;
+        let start_column = index - *line_start + 1;
+        let next_char = source_chars.get(index + 1).map(|c| *c as char);
         match char_at_index {
             '(' => tokens.push(Token::OpenParen),
             ')' => tokens.push(Token::CloseParen),
@@ -28,17 +94,124 @@ pub fn tokenize(source: String) -> Vec<Token> {
             '}' => tokens.push(Token::CloseBrace),
             '[' => tokens.push(Token::OpenBracket),
             ']' => tokens.push(Token::CloseBracket),
+            '-' | '+' if next_char == Some(char_at_index) => {
+                index += 1;
+                tokens.push(Token::UpdateOperator(format!("{0}{0}", char_at_index)));
+            }
+            '/' if next_char == Some('/') => {
+                let line_end = source[index..end]
+                    .find('\n')
+                    .map_or(end, |offset| index + offset);
+                comments.push(Comment {
+                    text: source[index..line_end].trim_end().to_string(),
+                    span: Span {
+                        start,
+                        end: line_end,
+                        line: start_line,
+                        column: start_column,
+                    },
+                });
+                index = line_end - 1;
+            }
+            '-' | '+' | '*' | '/' | '%' if next_char == Some('=') => {
+                index += 1;
+                tokens.push(Token::AssignmentOperator(format!("{}=", char_at_index)));
+            }
             '-' | '+' | '*' | '/' | '%' => {
-                tokens.push(Token::BinaryOperator(char_at_index.to_owned()))
+                tokens.push(Token::BinaryOperator(char_at_index.to_string()))
             }
-            '=' => tokens.push(Token::Equals),
+            '=' => match next_char {
+                Some('=') => {
+                    index += 1;
+                    tokens.push(Token::BinaryOperator("==".to_string()));
+                }
+                Some('>') => {
+                    index += 1;
+                    tokens.push(Token::Arrow);
+                }
+                _ => tokens.push(Token::Equals),
+            },
+            '!' => match next_char {
+                Some('=') => {
+                    index += 1;
+                    tokens.push(Token::BinaryOperator("!=".to_string()));
+                }
+                _ => tokens.push(Token::Bang),
+            },
+            '<' | '>' => match next_char {
+                Some('=') => {
+                    index += 1;
+                    tokens.push(Token::BinaryOperator(format!("{}=", char_at_index)));
+                }
+                _ => tokens.push(Token::BinaryOperator(char_at_index.to_string())),
+            },
+            '&' | '|' if next_char == Some(char_at_index) => {
+                index += 1;
+                tokens.push(Token::BinaryOperator(format!("{0}{0}", char_at_index)));
+            }
+            '|' => tokens.push(Token::Pipe),
             ';' => tokens.push(Token::Semicolon),
             ':' => tokens.push(Token::Colon),
             ',' => tokens.push(Token::Comma),
+            '.' if source.get(index..index + 3) == Some("...") => {
+                index += 2;
+                tokens.push(Token::Ellipsis);
+            }
             '.' => tokens.push(Token::Dot),
-            ' ' | '\n' | '\t' | '\r' => {}
+            '?' if source.get(index..index + 3) == Some("??=") => {
+                index += 2;
+                tokens.push(Token::AssignmentOperator("??=".to_string()));
+            }
+            '?' if next_char == Some('?') => {
+                index += 1;
+                tokens.push(Token::BinaryOperator("??".to_string()));
+            }
+            '?' if next_char == Some('.') => {
+                index += 1;
+                tokens.push(Token::QuestionDot);
+            }
+            '?' => tokens.push(Token::Question),
+            '"' => {
+                let mut str = String::new();
+                index += 1;
+                loop {
+                    if index >= source_chars.len() {
+                        return Err(error_at(source, start, "unterminated string literal"));
+                    }
+                    let next_char = source_chars[index] as char;
+                    match next_char {
+                        '"' => break,
+                        '\\' if index + 1 < source_chars.len() => {
+                            index += 1;
+                            match source_chars[index] as char {
+                                'n' => str.push('\n'),
+                                't' => str.push('\t'),
+                                'r' => str.push('\r'),
+                                escaped => str.push(escaped),
+                            }
+                        }
+                        '\n' => {
+                            str.push(next_char);
+                            *line += 1;
+                            *line_start = index + 1;
+                        }
+                        _ => str.push(next_char),
+                    }
+                    index += 1;
+                }
+                tokens.push(Token::String(str));
+            }
+            '`' => tokens.push(Token::Template(lex_template(
+                source, &mut index, line, line_start, comments,
+            )?)),
+            '\n' => {
+                *line += 1;
+                *line_start = index + 1;
+            }
+            ' ' | '\t' | '\r' => {}
             _ => {
                 if char_at_index.is_ascii_digit() {
+                    let start = index;
                     let mut num_str = String::new();
                     while index < source_chars.len() {
                         let next_char = source_chars[index] as char;
@@ -49,13 +222,30 @@ pub fn tokenize(source: String) -> Vec<Token> {
                             break;
                         }
                     }
-                    tokens.push(Token::Number(num_str.parse::<isize>().unwrap()));
+                    let has_fraction = source_chars.get(index) == Some(&b'.')
+                        && source_chars
+                            .get(index + 1)
+                            .is_some_and(|c| c.is_ascii_digit());
+                    if has_fraction {
+                        num_str.push('.');
+                        index += 1;
+                        while index < source_chars.len() && source_chars[index].is_ascii_digit() {
+                            num_str.push(source_chars[index] as char);
+                            index += 1;
+                        }
+                        tokens.push(Token::Float(num_str));
+                    } else {
+                        let Ok(num) = num_str.parse::<isize>() else {
+                            return Err(error_at(source, start, "integer literal out of range"));
+                        };
+                        tokens.push(Token::Number(num));
+                    }
                     index -= 1;
-                } else if char_at_index.is_alphabetic() {
+                } else if char_at_index.is_alphabetic() || char_at_index == '_' {
                     let mut str = String::new();
                     while index < source_chars.len() {
                         let next_char = source_chars[index] as char;
-                        if next_char.is_alphabetic() {
+                        if next_char.is_alphanumeric() || next_char == '_' {
                             str.push(next_char);
                             index += 1;
                         } else {
@@ -70,13 +260,162 @@ pub fn tokenize(source: String) -> Vec<Token> {
 
                     index -= 1;
                 } else {
-                    eprintln!("'{}' invalid character found", char_at_index);
-                    exit(2);
+                    return Err(error_at(
+                        source,
+                        index,
+                        format!("'{}' invalid character found", char_at_index).as_str(),
+                    ));
                 }
             }
         }
         index += 1;
+        if spans.len() < tokens.len() {
+            spans.push(Span {
+                start,
+                end: index,
+                line: start_line,
+                column: start_column,
+            });
+        }
     }
     tokens.push(Token::Eof);
-    tokens
+    spans.push(Span {
+        start: index,
+        end: index,
+        line: *line,
+        column: index - *line_start + 1,
+    });
+    Ok(tokens.into_iter().zip(spans).collect())
+}
+
+// Reads a template literal from its opening backtick, leaving `index` on the closing one.
+// Each `${...}` is tokenized in place as its own expression.
+fn lex_template(
+    source: &str,
+    index: &mut usize,
+    line: &mut usize,
+    line_start: &mut usize,
+    comments: &mut Vec<Comment>,
+) -> Result<Vec<TemplatePart>, SyntaxError> {
+    let source_chars = source.as_bytes();
+    let start = *index;
+    let mut parts = vec![];
+    let mut text = String::new();
+    *index += 1;
+    loop {
+        if *index >= source_chars.len() {
+            return Err(error_at(source, start, "unterminated template literal"));
+        }
+        match source_chars[*index] as char {
+            '`' => break,
+            '$' if source_chars.get(*index + 1) == Some(&b'{') => {
+                if !text.is_empty() {
+                    parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                }
+                let expression_start = *index + 2;
+                let expression_end = find_expression_end(source, expression_start, 0)?;
+                let tokens = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+                    lex(
+                        source,
+                        expression_start,
+                        expression_end,
+                        line,
+                        line_start,
+                        comments,
+                    )
+                })?;
+                parts.push(TemplatePart::Expression(tokens));
+                *index = expression_end;
+            }
+            '\\' if *index + 1 < source_chars.len() => {
+                *index += 1;
+                match source_chars[*index] as char {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'r' => text.push('\r'),
+                    escaped => text.push(escaped),
+                }
+            }
+            '\n' => {
+                text.push('\n');
+                *line += 1;
+                *line_start = *index + 1;
+            }
+            c => text.push(c),
+        }
+        *index += 1;
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    Ok(parts)
+}
+
+// The index of the `}` closing a template expression that starts at `index`, skipping
+// nested braces, strings and templates. `nesting` counts the templates this expression
+// is already inside of.
+fn find_expression_end(
+    source: &str,
+    mut index: usize,
+    nesting: usize,
+) -> Result<usize, SyntaxError> {
+    let source_chars = source.as_bytes();
+    let start = index;
+    let mut depth = 0;
+    loop {
+        match source_chars.get(index).map(|c| *c as char) {
+            None => return Err(error_at(source, start, "unterminated template expression")),
+            Some('{') => depth += 1,
+            Some('}') if depth == 0 => return Ok(index),
+            Some('}') => depth -= 1,
+            Some('"') => index = skip_quoted(source, index, nesting)?,
+            Some('`') if nesting >= MAX_NESTING_DEPTH => {
+                let message = format!(
+                    "templates nested more than {} levels deep",
+                    MAX_NESTING_DEPTH
+                );
+                return Err(error_at(source, index, &message));
+            }
+            Some('`') => {
+                index = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+                    skip_quoted(source, index, nesting + 1)
+                })?
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+// The index of the quote closing the string or template opened at `index`.
+fn skip_quoted(source: &str, mut index: usize, nesting: usize) -> Result<usize, SyntaxError> {
+    let source_chars = source.as_bytes();
+    let start = index;
+    let quote = source_chars[index];
+    index += 1;
+    loop {
+        match source_chars.get(index) {
+            None => return Err(error_at(source, start, "unterminated string literal")),
+            Some(c) if *c == quote => return Ok(index),
+            Some(b'\\') => index += 1,
+            Some(b'$') if quote == b'`' && source_chars.get(index + 1) == Some(&b'{') => {
+                index = find_expression_end(source, index + 2, nesting)?;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+// A syntax error pointing at the character at byte `index`.
+fn error_at(source: &str, index: usize, message: &str) -> SyntaxError {
+    let before = &source[..index];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let span = Span {
+        start: index,
+        end: index + 1,
+        line: before.matches('\n').count() + 1,
+        column: index - line_start + 1,
+    };
+    SyntaxError::new(message.to_string(), span)
 }
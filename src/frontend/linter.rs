@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::frontend::ast::Ast;
+use crate::frontend::resolver;
+use crate::frontend::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    UnusedVariable,
+    Shadowing,
+    ConstAssignment,
+    UnreachableCode,
+    SelfComparison,
+    AbsentField,
+    UndefinedVariable,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::Shadowing,
+        Rule::ConstAssignment,
+        Rule::UnreachableCode,
+        Rule::SelfComparison,
+        Rule::AbsentField,
+        Rule::UndefinedVariable,
+    ];
+
+    // The name used for the rule in diagnostics and config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::Shadowing => "shadowing",
+            Rule::ConstAssignment => "const-assignment",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::SelfComparison => "self-comparison",
+            Rule::AbsentField => "absent-field",
+            Rule::UndefinedVariable => "undefined-variable",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+// Which rules run. Every rule is on unless the config turns it off.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+impl LintConfig {
+    // Reads a config of `rule-name = on|off` lines; `#` starts a comment.
+    pub fn parse(text: &str) -> Result<LintConfig, String> {
+        let mut config = LintConfig::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected `rule = on|off`", index + 1));
+            };
+            let Some(rule) = Rule::from_name(name.trim()) else {
+                return Err(format!("line {}: unknown rule {}", index + 1, name.trim()));
+            };
+            match value.trim() {
+                "on" | "true" => config.disabled.remove(&rule),
+                "off" | "false" => config.disabled.insert(rule),
+                value => {
+                    return Err(format!(
+                        "line {}: expected on or off for {}, found {}",
+                        index + 1,
+                        rule.name(),
+                        value
+                    ))
+                }
+            };
+        }
+        Ok(config)
+    }
+
+    pub fn enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} [{}]", self.span, self.message, self.rule.name())
+    }
+}
+
+// Checks a parsed program. `globals` are the names the global scope starts with and
+// whether each is a constant. Diagnostics come back in source order.
+pub fn lint(
+    program: &Ast,
+    globals: &HashMap<String, bool>,
+    config: &LintConfig,
+) -> Vec<Diagnostic> {
+    resolver::resolve(program, globals)
+        .diagnostics
+        .into_iter()
+        .filter(|diagnostic| config.enabled(diagnostic.rule))
+        .collect()
+}
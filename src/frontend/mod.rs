@@ -1,4 +1,10 @@
 pub mod ast;
+pub mod checker;
+pub mod error;
+pub mod formatter;
 pub mod lexer;
+pub mod linter;
 pub mod parser;
-mod token;
\ No newline at end of file
+pub mod resolver;
+pub mod span;
+mod token;
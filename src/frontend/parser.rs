@@ -1,15 +1,58 @@
-use crate::frontend::ast::Ast;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::frontend::ast::{Annotation, Ast, MatchArm, Pattern, Property};
+use crate::frontend::error::SyntaxError;
+use crate::frontend::span::Span;
 use crate::frontend::token::TokenKind::{CloseBracket, CloseParen, OpenParen};
-use crate::frontend::token::{Token, TokenKind};
+use crate::frontend::token::{TemplatePart, Token, TokenKind};
+
+// How deeply blocks, expressions, patterns and types may nest unless `Limits` say
+// otherwise. Deeper source is a syntax error rather than a parser, or later an evaluator,
+// running out of stack.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+// Operator, member access and call chains don't count as nesting: `1 + 1 + 1` is flat
+// however long it is. Its syntax tree still gets as deep as the chain is long, so chains
+// are bounded separately, by how deep the trees they build may get.
+pub const MAX_CHAIN_DEPTH: usize = 10_000;
+
+// Each nesting level recurses through several parse functions, so a level of nesting can
+// take tens of kilobytes of stack in debug builds. When fewer than `STACK_RED_ZONE` bytes
+// are left, parsing goes on in a new `STACK_SEGMENT` on the heap. The lexer does the same
+// for templates nested in templates.
+pub(crate) const STACK_RED_ZONE: usize = 256 * 1024;
+pub(crate) const STACK_SEGMENT: usize = 2 * 1024 * 1024;
+
+// The clock is read once per this many tokens eaten while parsing against a deadline.
+const TOKENS_PER_CLOCK_CHECK: usize = 1024;
 
 #[derive(Debug)]
 struct Parser {
-    tokens: Vec<Token>,
+    tokens: VecDeque<(Token, Span)>,
+    depth: usize,
+    max_depth: usize,
+    // Chain links enclosing the current position, counted on top of the depth of the
+    // operand each chain started with, and the most reached so far.
+    chained: usize,
+    reached: usize,
+    eaten: usize,
+    deadline: Option<Instant>,
+    timed_out: bool,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens }
+    fn new(tokens: Vec<(Token, Span)>, deadline: Option<Instant>, max_depth: usize) -> Self {
+        Parser {
+            tokens: tokens.into(),
+            depth: 0,
+            max_depth,
+            chained: 0,
+            reached: 0,
+            eaten: 0,
+            deadline,
+            timed_out: false,
+        }
     }
     // Orders Of Precedence
     // Assignment
@@ -19,93 +62,877 @@ impl Parser {
     // Call
     // Member
     // PrimaryExpr
-    fn parse(&mut self) -> Ast {
+    fn parse(&mut self) -> Result<Ast, SyntaxError> {
         let mut statements = vec![];
         while self.not_eof() {
-            statements.push(self.parse_statement())
+            match self.at() {
+                Some(Token::Export) => statements.push(self.parse_export_declaration()?),
+                _ => statements.push(self.parse_statement()?),
+            }
         }
         // let program = Program { statements };
-        Ast::Program { statements }
+        Ok(Ast::Program { statements })
     }
 
     fn not_eof(&self) -> bool {
-        !matches!(self.tokens.first(), Some(Token::Eof))
+        !matches!(self.at(), Some(Token::Eof))
     }
 
-    fn parse_statement(&mut self) -> Ast {
+    fn parse_statement(&mut self) -> Result<Ast, SyntaxError> {
         match self.at() {
             Some(Token::Let | Token::Const) => self.parse_variable_declaration(),
-            _ => self.parse_expression(),
+            Some(Token::Try) => self.parse_try_statement(),
+            Some(Token::Throw) => self.parse_throw_statement(),
+            Some(Token::Return) => self.parse_return_statement(),
+            Some(Token::Class) => self.parse_class_declaration(),
+            Some(Token::Enum) => self.parse_enum_declaration(),
+            Some(Token::Import) => self.parse_import_declaration(),
+            Some(Token::Export) => {
+                self.error("export is only allowed at the top level of a module".to_string())
+            }
+            Some(Token::Fn) if matches!(self.tokens.get(1), Some((Token::Identifier(_), _))) => {
+                self.parse_function_declaration()
+            }
+            _ => {
+                let expression = self.parse_expression()?;
+                if let Some(Token::Semicolon) = self.at() {
+                    self.eat();
+                }
+                Ok(expression)
+            }
+        }
+    }
+
+    fn parse_import_declaration(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        self.eat();
+        self.expect(
+            TokenKind::OpenBrace,
+            "expected opening brace for import names",
+        )?;
+        let mut names = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+            let name = self.expect_identifier("expected name to import")?;
+            let mut alias = name.clone();
+            if let Some(Token::Identifier(keyword)) = self.at() {
+                if keyword == "as" {
+                    self.eat();
+                    alias = self.expect_identifier("expected name after as")?;
+                }
+            }
+            names.push((name, alias));
+            if !matches!(self.at(), Some(Token::CloseBrace)) {
+                self.expect(TokenKind::Comma, "expected comma between import names")?;
+            }
         }
+        self.expect(
+            TokenKind::CloseBrace,
+            "expected closing brace for import names",
+        )?;
+        match self.at() {
+            Some(Token::Identifier(keyword)) if keyword == "from" => self.eat(),
+            token => {
+                return self.error(format!(
+                    "expected from after import names, found {}",
+                    token.unwrap_or(&Token::Eof)
+                ))
+            }
+        };
+        let source = match self.expect(TokenKind::String, "expected module path string")? {
+            Token::String(source) => source,
+            _ => panic!("never executed branch. module path expected"),
+        };
+        self.expect(TokenKind::Semicolon, "expected semicolon")?;
+        Ok(Ast::ImportDeclaration {
+            names,
+            source,
+            span,
+        })
     }
 
-    fn parse_expression(&mut self) -> Ast {
-        self.parse_assignment_expression()
+    fn parse_export_declaration(&mut self) -> Result<Ast, SyntaxError> {
+        self.eat();
+        let declaration = match self.at() {
+            Some(Token::Let | Token::Const) => self.parse_variable_declaration()?,
+            Some(Token::Class) => self.parse_class_declaration()?,
+            Some(Token::Enum) => self.parse_enum_declaration()?,
+            Some(Token::Fn) => self.parse_function_declaration()?,
+            token => {
+                return self.error(format!(
+                    "expected declaration after export, found {}",
+                    token.unwrap_or(&Token::Eof)
+                ))
+            }
+        };
+        Ok(Ast::ExportDeclaration {
+            declaration: Box::new(declaration),
+        })
     }
 
-    fn parse_assignment_expression(&mut self) -> Ast {
-        let left = self.parse_object_expression();
-        match self.at() {
+    fn parse_block_statement(&mut self) -> Result<Ast, SyntaxError> {
+        self.expect(TokenKind::OpenBrace, "expected opening brace for block")?;
+        let mut statements = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+            statements.push(self.nested(Self::parse_statement)?);
+        }
+        self.expect(TokenKind::CloseBrace, "expected closing brace for block")?;
+        Ok(Ast::BlockStatement { statements })
+    }
+
+    fn parse_try_statement(&mut self) -> Result<Ast, SyntaxError> {
+        self.eat();
+        let block = self.parse_block_statement()?;
+        let mut param = None;
+        let mut handler = None;
+        if let Some(Token::Catch) = self.at() {
+            self.eat();
+            if let Some(Token::OpenParen) = self.at() {
+                self.eat();
+                let span = self.span();
+                let name = self.expect_identifier("expected catch parameter name")?;
+                param = Some((name, span));
+                self.expect(
+                    CloseParen,
+                    "expected close parentheses after catch parameter",
+                )?;
+            }
+            handler = Some(Box::new(self.parse_block_statement()?));
+        }
+        let mut finalizer = None;
+        if let Some(Token::Finally) = self.at() {
+            self.eat();
+            finalizer = Some(Box::new(self.parse_block_statement()?));
+        }
+        if handler.is_none() && finalizer.is_none() {
+            return self.error("try statement requires a catch or finally block".to_string());
+        }
+        Ok(Ast::TryStatement {
+            block: Box::new(block),
+            param,
+            handler,
+            finalizer,
+        })
+    }
+
+    fn parse_function_declaration(&mut self) -> Result<Ast, SyntaxError> {
+        let mut span = self.span();
+        self.expect(TokenKind::Fn, "expected fn keyword")?;
+        let name = match self.at() {
+            Some(Token::Identifier(name)) => {
+                let name = name.to_string();
+                span = self.span();
+                self.eat();
+                Some(name)
+            }
+            _ => None,
+        };
+        self.parse_function(name, span)
+    }
+
+    // Parses the parameter list and body shared by functions and class methods.
+    fn parse_function(&mut self, name: Option<String>, span: Span) -> Result<Ast, SyntaxError> {
+        self.expect(
+            OpenParen,
+            "expected open parentheses before function parameters",
+        )?;
+        let mut parameters = vec![];
+        let mut parameter_types = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseParen)) {
+            let pattern = self.parse_pattern()?;
+            parameter_types.push(self.parse_annotation()?);
+            parameters.push(self.parse_default(pattern)?);
+            if !matches!(self.at(), Some(Token::CloseParen)) {
+                self.expect(
+                    TokenKind::Comma,
+                    "expected comma between function parameters",
+                )?;
+            }
+        }
+        self.expect(
+            CloseParen,
+            "expected close parentheses after function parameters",
+        )?;
+        let return_type = self.parse_annotation()?;
+        let body = match self.parse_block_statement()? {
+            Ast::BlockStatement { statements } => statements,
+            _ => panic!("never executed branch. function body expected"),
+        };
+        Ok(Ast::FunctionDeclaration {
+            name,
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+            span,
+        })
+    }
+
+    fn parse_class_declaration(&mut self) -> Result<Ast, SyntaxError> {
+        self.eat();
+        let span = self.span();
+        let name = self.expect_identifier("expected class name")?;
+        let mut parent = None;
+        if let Some(Token::Extends) = self.at() {
+            self.eat();
+            parent = Some(Box::new(self.parse_member_expression()?));
+        }
+        self.expect(
+            TokenKind::OpenBrace,
+            "expected opening brace for class body",
+        )?;
+        let mut fields = vec![];
+        let mut methods = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+            let member_span = self.span();
+            let member = self.expect_identifier("expected class member name")?;
+            match self.at() {
+                Some(Token::OpenParen) => {
+                    methods.push(self.parse_function(Some(member), member_span)?)
+                }
+                Some(Token::Equals) => {
+                    self.eat();
+                    let value = self.parse_expression()?;
+                    self.expect(TokenKind::Semicolon, "expected semicolon after class field")?;
+                    fields.push((member, Some(value)));
+                }
+                _ => {
+                    self.expect(TokenKind::Semicolon, "expected semicolon after class field")?;
+                    fields.push((member, None));
+                }
+            }
+        }
+        self.expect(
+            TokenKind::CloseBrace,
+            "expected closing brace for class body",
+        )?;
+        Ok(Ast::ClassDeclaration {
+            name,
+            parent,
+            fields,
+            methods,
+            span,
+        })
+    }
+
+    fn parse_enum_declaration(&mut self) -> Result<Ast, SyntaxError> {
+        self.eat();
+        let span = self.span();
+        let name = self.expect_identifier("expected enum name")?;
+        self.expect(TokenKind::OpenBrace, "expected opening brace for enum body")?;
+        let mut variants = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+            let variant = self.expect_identifier("expected enum variant name")?;
+            let mut fields = vec![];
+            if let Some(Token::OpenParen) = self.at() {
+                self.eat();
+                while self.not_eof() && !matches!(self.at(), Some(Token::CloseParen)) {
+                    fields.push(self.expect_identifier("expected enum variant field name")?);
+                    if !matches!(self.at(), Some(Token::CloseParen)) {
+                        self.expect(TokenKind::Comma, "expected comma between variant fields")?;
+                    }
+                }
+                self.expect(
+                    CloseParen,
+                    "expected close parentheses after variant fields",
+                )?;
+            }
+            variants.push((variant, fields));
+            if !matches!(self.at(), Some(Token::CloseBrace)) {
+                self.expect(TokenKind::Comma, "expected comma between enum variants")?;
+            }
+        }
+        self.expect(
+            TokenKind::CloseBrace,
+            "expected closing brace for enum body",
+        )?;
+        Ok(Ast::EnumDeclaration {
+            name,
+            variants,
+            span,
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Ast, SyntaxError> {
+        self.eat();
+        let subject = self.parse_expression()?;
+        self.expect(
+            TokenKind::OpenBrace,
+            "expected opening brace for match arms",
+        )?;
+        let mut arms = vec![];
+        while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+            let pattern = self.parse_pattern()?;
+            let mut guard = None;
+            if let Some(Token::If) = self.at() {
+                self.eat();
+                guard = Some(self.parse_expression()?);
+            }
+            self.expect(TokenKind::Arrow, "expected => after match pattern")?;
+            let body = self.parse_expression()?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            if !matches!(self.at(), Some(Token::CloseBrace)) {
+                self.expect(TokenKind::Comma, "expected comma between match arms")?;
+            }
+        }
+        self.expect(
+            TokenKind::CloseBrace,
+            "expected closing brace for match arms",
+        )?;
+        Ok(Ast::MatchExpr {
+            subject: Box::new(subject),
+            arms,
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, SyntaxError> {
+        let span = self.span();
+        let pattern = match self.eat() {
+            Token::Number(num) => Pattern::Literal(Ast::NumericLiteral(num)),
+            Token::Float(num) => Pattern::Literal(Ast::FloatLiteral(num.parse().unwrap())),
+            Token::BinaryOperator(op) if op == "-" => match self.eat() {
+                Token::Number(num) => Pattern::Literal(Ast::NumericLiteral(-num)),
+                Token::Float(num) => {
+                    Pattern::Literal(Ast::FloatLiteral(-num.parse::<f64>().unwrap()))
+                }
+                token => {
+                    return Err(SyntaxError::new(
+                        format!("expected number after - in pattern, found {}", token),
+                        span,
+                    ))
+                }
+            },
+            Token::String(str) => Pattern::Literal(Ast::StringLiteral(str)),
+            Token::Identifier(name) if name == "_" => Pattern::Wildcard,
+            Token::Identifier(name) if matches!(name.as_str(), "true" | "false" | "null") => {
+                Pattern::Literal(Ast::Identifier(name, span))
+            }
+            Token::Identifier(name) if matches!(self.at(), Some(Token::Dot)) => {
+                // `a.b.Variant(...)`: everything before the last name locates the enum.
+                let mut enumeration = Ast::Identifier(name, span);
+                let mut dot = self.span();
+                self.eat();
+                let mut variant_span = self.span();
+                let mut variant = self.expect_identifier("expected enum variant in pattern")?;
+                let chained = self.chained;
+                while let Some(Token::Dot) = self.at() {
+                    self.link()?;
+                    enumeration = Ast::MemberExpr {
+                        object: Box::new(enumeration),
+                        property: Box::new(Ast::Identifier(variant, variant_span)),
+                        computed: false,
+                        span: dot,
+                    };
+                    dot = self.span();
+                    self.eat();
+                    variant_span = self.span();
+                    variant = self.expect_identifier("expected enum variant in pattern")?;
+                }
+                self.chained = chained;
+                let mut fields = vec![];
+                if let Some(Token::OpenParen) = self.at() {
+                    self.eat();
+                    while self.not_eof() && !matches!(self.at(), Some(Token::CloseParen)) {
+                        fields.push(self.nested(Self::parse_pattern)?);
+                        if !matches!(self.at(), Some(Token::CloseParen)) {
+                            self.expect(TokenKind::Comma, "expected comma between patterns")?;
+                        }
+                    }
+                    self.expect(
+                        CloseParen,
+                        "expected close parentheses after variant patterns",
+                    )?;
+                }
+                Pattern::Variant {
+                    enumeration: Box::new(enumeration),
+                    variant,
+                    fields,
+                }
+            }
+            Token::Identifier(name) => Pattern::Binding(name, span),
+            Token::OpenBrace => {
+                let mut properties = vec![];
+                let mut rest = None;
+                while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+                    if let Some(Token::Ellipsis) = self.at() {
+                        self.eat();
+                        rest = Some(self.expect_identifier("expected name after ... in pattern")?);
+                    } else {
+                        // `{ key = default }` needs the default parsed after the shorthand key.
+                        let (key, pattern) =
+                            if matches!(self.tokens.get(1), Some((Token::Equals, _))) {
+                                let key_span = self.span();
+                                let key = self.expect_identifier("object pattern key expected")?;
+                                let binding = Pattern::Binding(key.clone(), key_span);
+                                (key, self.parse_default(binding)?)
+                            } else {
+                                let key_span = self.span();
+                                let (key, pattern) = self.parse_property(
+                                    "object pattern key expected",
+                                    Self::parse_binding_pattern,
+                                )?;
+                                let pattern = pattern
+                                    .unwrap_or_else(|| Pattern::Binding(key.clone(), key_span));
+                                (key, pattern)
+                            };
+                        properties.push((key, pattern));
+                    }
+                    if !matches!(self.at(), Some(Token::CloseBrace)) {
+                        self.expect(TokenKind::Comma, "expected comma between pattern keys")?;
+                    }
+                }
+                self.expect(
+                    TokenKind::CloseBrace,
+                    "expected closing brace for object pattern",
+                )?;
+                Pattern::Object { properties, rest }
+            }
+            Token::OpenBracket => {
+                let mut elements = vec![];
+                let mut rest = None;
+                while self.not_eof() && !matches!(self.at(), Some(Token::CloseBracket)) {
+                    if let Some(Token::Ellipsis) = self.at() {
+                        self.eat();
+                        rest = Some(self.expect_identifier("expected name after ... in pattern")?);
+                    } else {
+                        elements.push(self.parse_binding_pattern()?);
+                    }
+                    if !matches!(self.at(), Some(Token::CloseBracket)) {
+                        self.expect(TokenKind::Comma, "expected comma between array patterns")?;
+                    }
+                }
+                self.expect(CloseBracket, "expected closing bracket for array pattern")?;
+                Pattern::Array { elements, rest }
+            }
+            token => {
+                return Err(SyntaxError::new(
+                    format!("unexpected {} in pattern", token),
+                    span,
+                ))
+            }
+        };
+        Ok(pattern)
+    }
+
+    // A pattern that may be followed by `= default`, as in declarations and parameters.
+    fn parse_binding_pattern(&mut self) -> Result<Pattern, SyntaxError> {
+        let pattern = self.nested(Self::parse_pattern)?;
+        self.parse_default(pattern)
+    }
+
+    fn parse_default(&mut self, pattern: Pattern) -> Result<Pattern, SyntaxError> {
+        if !matches!(self.at(), Some(Token::Equals)) {
+            return Ok(pattern);
+        }
+        self.eat();
+        Ok(Pattern::Default {
+            pattern: Box::new(pattern),
+            default: Box::new(self.nested(Self::parse_object_expression)?),
+        })
+    }
+
+    // `: type` after a name or parameter list, when there is one.
+    fn parse_annotation(&mut self) -> Result<Option<Annotation>, SyntaxError> {
+        if !matches!(self.at(), Some(Token::Colon)) {
+            return Ok(None);
+        }
+        self.eat();
+        self.parse_type().map(Some)
+    }
+
+    // `member | member | ...`
+    fn parse_type(&mut self) -> Result<Annotation, SyntaxError> {
+        let mut members = vec![self.parse_type_member()?];
+        while let Some(Token::Pipe) = self.at() {
+            self.eat();
+            members.push(self.parse_type_member()?);
+        }
+        Ok(match members.len() {
+            1 => members.pop().unwrap(),
+            _ => Annotation::Union(members),
+        })
+    }
+
+    // A name, object type or parenthesized type, followed by any number of `[]` and `?`.
+    fn parse_type_member(&mut self) -> Result<Annotation, SyntaxError> {
+        let span = self.span();
+        let mut annotation = match self.eat() {
+            Token::Identifier(name) => Annotation::Named(name, span),
+            Token::OpenBrace => {
+                let mut fields = vec![];
+                while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
+                    let key = self.expect_identifier("expected field name in object type")?;
+                    self.expect(TokenKind::Colon, "expected colon after field name")?;
+                    fields.push((key, self.nested(Self::parse_type)?));
+                    if !matches!(self.at(), Some(Token::CloseBrace)) {
+                        self.expect(TokenKind::Comma, "expected comma between object fields")?;
+                    }
+                }
+                self.expect(
+                    TokenKind::CloseBrace,
+                    "expected closing brace for object type",
+                )?;
+                Annotation::Object(fields, span)
+            }
+            Token::OpenParen => {
+                let annotation = self.nested(Self::parse_type)?;
+                self.expect(CloseParen, "expected close parentheses after type")?;
+                annotation
+            }
+            token => {
+                return Err(SyntaxError::new(
+                    format!("expected type, found {}", token),
+                    span,
+                ))
+            }
+        };
+        let depth = self.depth;
+        loop {
+            match (self.at(), self.tokens.get(1).map(|(token, _)| token)) {
+                (Some(Token::OpenBracket), Some(Token::CloseBracket)) => {
+                    self.deepen()?;
+                    self.eat();
+                    self.eat();
+                    annotation = Annotation::Array(Box::new(annotation));
+                }
+                (Some(Token::Question), _) => {
+                    self.deepen()?;
+                    self.eat();
+                    annotation = Annotation::Nullable(Box::new(annotation));
+                }
+                _ => {
+                    self.depth = depth;
+                    return Ok(annotation);
+                }
+            }
+        }
+    }
+
+    fn expect_identifier(&mut self, message: &str) -> Result<String, SyntaxError> {
+        Ok(match self.expect(TokenKind::Identifier, message)? {
+            Token::Identifier(name) => name,
+            _ => panic!("never executed branch. {}", message),
+        })
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        self.eat();
+        let mut argument = None;
+        if !matches!(self.at(), Some(Token::Semicolon)) {
+            argument = Some(Box::new(self.parse_expression()?));
+        }
+        self.expect(
+            TokenKind::Semicolon,
+            "expected semicolon after return statement",
+        )?;
+        Ok(Ast::ReturnStatement { argument, span })
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        self.eat();
+        let argument = self.parse_expression()?;
+        self.expect(
+            TokenKind::Semicolon,
+            "expected semicolon after throw statement",
+        )?;
+        Ok(Ast::ThrowStatement {
+            argument: Box::new(argument),
+            span,
+        })
+    }
+
+    fn parse_expression(&mut self) -> Result<Ast, SyntaxError> {
+        self.nested(Self::parse_assignment_expression)
+    }
+
+    fn parse_assignment_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let left = self.parse_object_expression()?;
+        if let Some(Token::Equals | Token::AssignmentOperator(_)) = self.at() {
+            check_assignee(&left, self.span())?;
+        }
+        Ok(match self.at() {
             Some(Token::Equals) => {
                 self.eat();
-                let value = self.parse_assignment_expression();
-                self.expect(TokenKind::Semicolon, "expected semicolon");
+                let value = self.parse_expression()?;
                 Ast::AssignmentExpr {
                     assignee: Box::new(left),
                     value: Box::new(value),
+                    span,
+                }
+            }
+            Some(Token::AssignmentOperator(operator)) => {
+                let operator = operator.trim_end_matches('=').to_string();
+                self.eat();
+                let value = self.parse_expression()?;
+                Ast::CompoundAssignmentExpr {
+                    assignee: Box::new(left),
+                    operator,
+                    value: Box::new(value),
+                    span,
                 }
             }
             _ => left,
+        })
+    }
+
+    fn parse_logical_or_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut left, chained) = self.chain(Self::parse_logical_and_expression)?;
+        while let Some(operator) = self.at_operator(&["||", "??"]) {
+            self.link()?;
+            self.eat();
+            let right = self.parse_logical_and_expression()?;
+            left = Ast::BinaryExpr {
+                left: Box::new(left),
+                right: Box::new(right),
+                operator,
+                span,
+            }
         }
+        self.chained = chained;
+        Ok(left)
     }
 
-    fn parse_additive_expression(&mut self) -> Ast {
-        let mut left = self.parse_multiplicative_expression();
-        while let Some(Token::BinaryOperator(op @ ('+' | '-'))) = self.at() {
-            let operator = *op;
+    fn parse_logical_and_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut left, chained) = self.chain(Self::parse_comparison_expression)?;
+        while let Some(operator) = self.at_operator(&["&&"]) {
+            self.link()?;
             self.eat();
-            let right = self.parse_multiplicative_expression();
+            let right = self.parse_comparison_expression()?;
             left = Ast::BinaryExpr {
                 left: Box::new(left),
                 right: Box::new(right),
                 operator,
+                span,
             }
         }
+        self.chained = chained;
+        Ok(left)
+    }
 
-        left
+    fn parse_comparison_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut left, chained) = self.chain(Self::parse_additive_expression)?;
+        while let Some(operator) = self.at_operator(&["==", "!=", "<", ">", "<=", ">="]) {
+            self.link()?;
+            self.eat();
+            let right = self.parse_additive_expression()?;
+            left = Ast::BinaryExpr {
+                left: Box::new(left),
+                right: Box::new(right),
+                operator,
+                span,
+            }
+        }
+        self.chained = chained;
+        Ok(left)
     }
 
-    fn parse_multiplicative_expression(&mut self) -> Ast {
-        let mut left = self.parse_member_call_expression();
-        while let Some(Token::BinaryOperator(op @ ('*' | '%' | '/'))) = self.at() {
-            let operator = *op;
+    fn parse_additive_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut left, chained) = self.chain(Self::parse_multiplicative_expression)?;
+        while let Some(operator) = self.at_operator(&["+", "-"]) {
+            self.link()?;
             self.eat();
-            let right = self.parse_member_call_expression();
+            let right = self.parse_multiplicative_expression()?;
             left = Ast::BinaryExpr {
                 left: Box::new(left),
                 right: Box::new(right),
                 operator,
+                span,
             }
         }
+        self.chained = chained;
+        Ok(left)
+    }
 
-        left
+    fn parse_multiplicative_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut left, chained) = self.chain(Self::parse_unary_expression)?;
+        while let Some(operator) = self.at_operator(&["*", "%", "/"]) {
+            self.link()?;
+            self.eat();
+            let right = self.parse_unary_expression()?;
+            left = Ast::BinaryExpr {
+                left: Box::new(left),
+                right: Box::new(right),
+                operator,
+                span,
+            }
+        }
+        self.chained = chained;
+        Ok(left)
+    }
+
+    fn parse_unary_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        if let Some(Token::UpdateOperator(op)) = self.at() {
+            let operator = op.clone();
+            self.eat();
+            let argument = self.nested(Self::parse_unary_expression)?;
+            check_assignee(&argument, span)?;
+            return Ok(Ast::UpdateExpr {
+                operator,
+                argument: Box::new(argument),
+                prefix: true,
+                span,
+            });
+        }
+        // `!`s and `-`s in a row chain like any other operators.
+        let chained = self.chained;
+        let mut operators = vec![];
+        loop {
+            let operator = match self.at() {
+                Some(Token::Bang) => "!".to_string(),
+                Some(Token::BinaryOperator(op)) if op == "-" => op.clone(),
+                _ => break,
+            };
+            self.link()?;
+            self.eat();
+            operators.push(operator);
+        }
+        if operators.is_empty() {
+            return self.parse_postfix_expression();
+        }
+        let mut argument = self.nested(Self::parse_unary_expression)?;
+        self.chained = chained;
+        for operator in operators.into_iter().rev() {
+            argument = Ast::UnaryExpr {
+                operator,
+                argument: Box::new(argument),
+            };
+        }
+        Ok(argument)
+    }
+
+    fn parse_postfix_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let argument = self.parse_member_call_expression()?;
+        Ok(match self.at() {
+            Some(Token::UpdateOperator(op)) => {
+                let operator = op.clone();
+                check_assignee(&argument, self.span())?;
+                self.eat();
+                Ast::UpdateExpr {
+                    operator,
+                    argument: Box::new(argument),
+                    prefix: false,
+                    span,
+                }
+            }
+            _ => argument,
+        })
+    }
+
+    // Returns the current binary operator if it is one of `operators`.
+    fn at_operator(&self, operators: &[&str]) -> Option<String> {
+        match self.at() {
+            Some(Token::BinaryOperator(op)) if operators.contains(&op.as_str()) => Some(op.clone()),
+            _ => None,
+        }
     }
 
     fn at(&self) -> Option<&Token> {
-        self.tokens.first()
+        self.tokens.front().map(|(token, _)| token)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .front()
+            .map(|(_, span)| *span)
+            .unwrap_or_default()
     }
 
+    // The trailing `Eof` is never removed, so reading past the end keeps returning it.
+    // Once the deadline has passed, the rest of the tokens are dropped so parsing winds
+    // down at once.
     fn eat(&mut self) -> Token {
-        self.tokens.remove(0)
+        let token = match self.tokens.len() {
+            1 => self.tokens[0].0.clone(),
+            _ => self.tokens.pop_front().unwrap().0,
+        };
+        self.eaten += 1;
+        if self.eaten.is_multiple_of(TOKENS_PER_CLOCK_CHECK)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() > deadline)
+        {
+            self.timed_out = true;
+            let eof = self.tokens.len() - 1;
+            self.tokens.drain(..eof);
+        }
+        token
     }
 
-    fn parse_primary_expression(&mut self) -> Ast {
-        println!("{:#?}", self.at());
-        match self.at() {
+    // Parses one level deeper into the source's nesting.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, SyntaxError>,
+    ) -> Result<T, SyntaxError> {
+        self.deepen()?;
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || parse(self));
+        self.depth -= 1;
+        result
+    }
+
+    // Goes one level deeper, as each `[]` or `?` after a type does. The caller restores the
+    // depth afterwards.
+    fn deepen(&mut self) -> Result<(), SyntaxError> {
+        if self.depth >= self.max_depth {
+            return self.error(format!(
+                "code nested more than {} levels deep",
+                self.max_depth
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // Parses the first operand of a chain, returning it with the chain count to restore
+    // when the chain ends. Each link of the chain deepens the tree under it, so links are
+    // counted on top of how deep that operand's own chains went.
+    fn chain<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, SyntaxError>,
+    ) -> Result<(T, usize), SyntaxError> {
+        let chained = self.chained;
+        let reached = std::mem::replace(&mut self.reached, chained);
+        let operand = parse(self)?;
+        self.chained = self.reached;
+        self.reached = self.reached.max(reached);
+        Ok((operand, chained))
+    }
+
+    // Adds a link to the current chain.
+    fn link(&mut self) -> Result<(), SyntaxError> {
+        if self.chained >= MAX_CHAIN_DEPTH {
+            return self.error(format!(
+                "expression chained more than {} levels deep",
+                MAX_CHAIN_DEPTH
+            ));
+        }
+        self.chained += 1;
+        self.reached = self.reached.max(self.chained);
+        Ok(())
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, SyntaxError> {
+        Err(SyntaxError::new(message, self.span()))
+    }
+
+    fn parse_primary_expression(&mut self) -> Result<Ast, SyntaxError> {
+        Ok(match self.at() {
             Some(Token::OpenParen) => {
                 self.eat();
-                let expression = self.parse_expression();
-                self.expect(CloseParen, "unexpected token found inside parentheses expression. expected close parentheses");
+                let expression = self.parse_expression()?;
+                self.expect(CloseParen, "expected close parentheses after expression")?;
                 expression
             }
             Some(Token::Number(num)) => {
@@ -113,171 +940,373 @@ impl Parser {
                 self.eat();
                 Ast::NumericLiteral(num)
             }
+            Some(Token::Float(num)) => {
+                let num = num.parse().unwrap();
+                self.eat();
+                Ast::FloatLiteral(num)
+            }
+            Some(Token::Fn) => self.parse_function_declaration()?,
+            Some(Token::Match) => self.parse_match_expression()?,
+            Some(Token::OpenBracket) => {
+                self.eat();
+                let mut elements = vec![];
+                while self.not_eof() && !matches!(self.at(), Some(Token::CloseBracket)) {
+                    elements.push(self.parse_element()?);
+                    if !matches!(self.at(), Some(Token::CloseBracket)) {
+                        self.expect(TokenKind::Comma, "expected comma between array elements")?;
+                    }
+                }
+                self.expect(CloseBracket, "expected closing bracket for array literal")?;
+                Ast::ArrayLiteral { elements }
+            }
+            Some(Token::String(str)) => {
+                let str = str.to_string();
+                self.eat();
+                Ast::StringLiteral(str)
+            }
+            Some(Token::Template(_)) => {
+                let Token::Template(parts) = self.eat() else {
+                    unreachable!("the current token is a template")
+                };
+                let parts = parts
+                    .into_iter()
+                    .map(|part| match part {
+                        TemplatePart::Text(text) => Ok(Ast::StringLiteral(text)),
+                        TemplatePart::Expression(tokens) => {
+                            let mut parser = Parser::new(tokens, self.deadline, self.max_depth);
+                            parser.depth = self.depth;
+                            parser.chained = self.chained;
+                            parser.reached = self.chained;
+                            let expression = parser.parse_expression()?;
+                            self.reached = self.reached.max(parser.reached);
+                            parser.expect(
+                                TokenKind::Eof,
+                                "expected } after template literal expression",
+                            )?;
+                            Ok(expression)
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ast::TemplateLiteral { parts }
+            }
             Some(Token::Identifier(id)) => {
                 let variable_name = id.to_string();
+                let span = self.span();
                 self.eat();
-                Ast::Identifier(variable_name)
-            }
-            _ => {
-                panic!("Unexpected token {:?}", self.at())
+                Ast::Identifier(variable_name, span)
             }
-        }
+            token => return self.error(format!("unexpected {}", token.unwrap_or(&Token::Eof))),
+        })
     }
 
-    fn expect(&mut self, expected_token_kind: TokenKind, message: &str) -> Token {
+    fn expect(
+        &mut self,
+        expected_token_kind: TokenKind,
+        message: &str,
+    ) -> Result<Token, SyntaxError> {
+        let span = self.span();
         let token = self.eat();
         if token.kind() != expected_token_kind {
-            panic!("Unexpected token found '{:?}'. {}", token, message)
+            return Err(SyntaxError::new(
+                format!("unexpected {}, {}", token, message),
+                span,
+            ));
         }
-        token
+        Ok(token)
     }
 
-    fn parse_variable_declaration(&mut self) -> Ast {
+    fn parse_variable_declaration(&mut self) -> Result<Ast, SyntaxError> {
         let is_const = matches!(self.eat(), Token::Const);
-        let identifier = self.expect(TokenKind::Identifier, "expected identifier");
-        match (identifier, self.at()) {
+        if let Some(Token::OpenBrace | Token::OpenBracket) = self.at() {
+            return self.parse_destructuring_declaration(is_const);
+        }
+        let span = self.span();
+        let identifier = self.expect(TokenKind::Identifier, "expected identifier")?;
+        let annotation = self.parse_annotation()?;
+        Ok(match (identifier, self.at()) {
             (Token::Identifier(variable), Some(Token::Semicolon)) => {
                 if is_const {
-                    panic!("must assign value to constant expression. No value provided");
+                    return self.error(
+                        "must assign value to constant expression. No value provided".to_string(),
+                    );
                 }
                 self.eat();
                 Ast::VariableDeclaration {
                     constant: is_const,
-                    value: Box::new(Ast::Identifier("null".to_string())),
+                    value: Box::new(Ast::Identifier("null".to_string(), span)),
                     identifier: variable,
+                    annotation,
+                    span,
                 }
             }
             (Token::Identifier(variable), Some(_)) => {
-                self.expect(TokenKind::Equals, "expected equals");
+                self.expect(TokenKind::Equals, "expected equals")?;
                 let statement = Ast::VariableDeclaration {
                     constant: is_const,
-                    value: Box::new(self.parse_expression()),
+                    value: Box::new(self.parse_expression()?),
                     identifier: variable,
+                    annotation,
+                    span,
                 };
-                self.expect(TokenKind::Semicolon, "expected semicolon");
+                self.expect(TokenKind::Semicolon, "expected semicolon")?;
                 statement
             }
+            _ => return self.error("unexpected error".to_string()),
+        })
+    }
+
+    fn parse_destructuring_declaration(&mut self, is_const: bool) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let pattern = self.parse_pattern()?;
+        self.expect(
+            TokenKind::Equals,
+            "destructuring declaration must be initialised",
+        )?;
+        let statement = Ast::DestructuringDeclaration {
+            constant: is_const,
+            pattern: Box::new(pattern),
+            value: Box::new(self.parse_expression()?),
+            span,
+        };
+        self.expect(TokenKind::Semicolon, "expected semicolon")?;
+        Ok(statement)
+    }
+
+    // Parses `key` or `key: value`, the shape shared by object literals and object
+    // patterns. The shorthand form `key` returns no value.
+    fn parse_property<T>(
+        &mut self,
+        message: &str,
+        parse_value: impl FnOnce(&mut Self) -> Result<T, SyntaxError>,
+    ) -> Result<(String, Option<T>), SyntaxError> {
+        let key = self.expect_identifier(message)?;
+        Ok(match self.at() {
+            // Allows short hand key pair : {key} {key,}
+            Some(Token::Comma | Token::CloseBrace) => (key, None),
+            // Allows key value pair : {key: value} {key: value,}
             _ => {
-                panic!("unexpected error");
+                self.expect(TokenKind::Colon, "expected colon after object key")?;
+                (key, Some(parse_value(self)?))
             }
-        }
+        })
     }
 
-    fn parse_object_expression(&mut self) -> Ast {
+    fn parse_object_expression(&mut self) -> Result<Ast, SyntaxError> {
         if !matches!(self.at(), Some(Token::OpenBrace)) {
-            return self.parse_additive_expression();
+            return self.parse_logical_or_expression();
         }
         self.eat();
-        let mut properties: Vec<(String, Option<Box<Ast>>)> = Vec::new();
+        let mut properties: Vec<Property> = Vec::new();
         while self.not_eof() && !matches!(self.at(), Some(Token::CloseBrace)) {
-            let key = self.expect(TokenKind::Identifier, "object literal key expected");
-            match (key, self.at()) {
-                (Token::Identifier(var), Some(Token::Comma)) => {
-                    // Allows short hand key pair : {key,}
-                    self.eat();
-                    properties.push((var, None));
-                    continue;
-                }
-                (Token::Identifier(var), Some(Token::CloseBrace)) => {
-                    // Allows short hand key pair : {key}
-                    properties.push((var, None));
-                    continue;
-                }
-                (Token::Identifier(var), Some(_)) => {
-                    // Allows short hand key pair : {key: value} {key: value,}
-                    self.expect(TokenKind::Colon, "colon expected after object literal key");
-                    let expression = self.parse_expression();
-                    properties.push((var, Some(Box::new(expression))));
-                    if !matches!(self.at(), Some(Token::CloseBrace)) {
-                        self.expect(
-                            TokenKind::Comma,
-                            "Expected comma or closing bracket following a property",
-                        );
-                    }
-                }
-                _ => {
-                    panic!("never executed branch. object literal key expected");
-                }
+            properties.push(self.parse_object_property()?);
+            if !matches!(self.at(), Some(Token::CloseBrace)) {
+                self.expect(
+                    TokenKind::Comma,
+                    "expected comma or closing bracket after a property",
+                )?;
             }
         }
         self.expect(
             TokenKind::CloseBrace,
             "expected closing brace for object literal",
-        );
-        Ast::ObjectLiteral { properties }
+        )?;
+        Ok(Ast::ObjectLiteral { properties })
     }
 
-    fn parse_member_call_expression(&mut self) -> Ast {
-        let member = self.parse_member_expression();
+    fn parse_object_property(&mut self) -> Result<Property, SyntaxError> {
+        Ok(match self.at() {
+            Some(Token::Ellipsis) => {
+                self.eat();
+                Property::Spread(self.parse_expression()?)
+            }
+            Some(Token::OpenBracket) => {
+                self.eat();
+                let key = self.parse_expression()?;
+                self.expect(CloseBracket, "expected closing bracket after computed key")?;
+                self.expect(TokenKind::Colon, "expected colon after object key")?;
+                Property::Computed(key, self.parse_expression()?)
+            }
+            Some(Token::String(key)) => {
+                let key = key.to_string();
+                self.eat();
+                self.expect(TokenKind::Colon, "expected colon after object key")?;
+                Property::Named(key, Some(Box::new(self.parse_expression()?)))
+            }
+            Some(Token::Identifier(key))
+                if matches!(self.tokens.get(1), Some((Token::OpenParen, _))) =>
+            {
+                let key = key.to_string();
+                let span = self.span();
+                self.eat();
+                Property::Method(key, self.parse_function(None, span)?)
+            }
+            _ => {
+                let (key, value) = self
+                    .parse_property("object literal key expected", |parser| {
+                        Ok(Box::new(parser.parse_expression()?))
+                    })?;
+                Property::Named(key, value)
+            }
+        })
+    }
+
+    fn parse_member_call_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let span = self.span();
+        let (mut member, chained) = self.chain(Self::parse_primary_expression)?;
+        member = self.parse_member_access(member)?;
         if let Some(Token::OpenParen) = self.at() {
-            return self.parse_call_expression(member);
+            member = self.parse_call_expression(member, span)?;
         }
+        self.chained = chained;
+        if has_null_guard(&member) {
+            return Ok(Ast::OptionalChain {
+                expression: Box::new(member),
+            });
+        }
+        Ok(member)
+    }
+
+    fn parse_member_expression(&mut self) -> Result<Ast, SyntaxError> {
+        let (object, chained) = self.chain(Self::parse_primary_expression)?;
+        let member = self.parse_member_access(object);
+        self.chained = chained;
         member
     }
 
-    fn parse_member_expression(&mut self) -> Ast {
-        let mut object = self.parse_primary_expression();
-        while let Some(Token::Dot | Token::OpenBracket) = self.at() {
-            let operator = self.eat();
+    // Links the member accesses following `object` onto the chain it started; the caller
+    // ends the chain.
+    fn parse_member_access(&mut self, mut object: Ast) -> Result<Ast, SyntaxError> {
+        while let Some(Token::Dot | Token::OpenBracket | Token::QuestionDot) = self.at() {
+            self.link()?;
+            let mut operator = self.eat();
+            if operator == Token::QuestionDot {
+                object = Ast::NullGuard {
+                    argument: Box::new(object),
+                };
+                match self.at() {
+                    // `f?.()` is finished by the caller, which parses the arguments.
+                    Some(Token::OpenParen) => break,
+                    Some(Token::OpenBracket) => operator = self.eat(),
+                    _ => operator = Token::Dot,
+                }
+            }
 
+            let span = self.span();
             let property: Ast;
             let computed: bool;
 
             if operator == Token::Dot {
                 computed = false;
-                property = self.parse_primary_expression();
-                if !matches!(property, Ast::Identifier(_)) {
-                    panic!("Cannot use dot operator without right hand side being a identifier");
+                property = self.parse_primary_expression()?;
+                if !matches!(property, Ast::Identifier(..)) {
+                    return self.error(
+                        "Cannot use dot operator without right hand side being a identifier"
+                            .to_string(),
+                    );
                 }
             } else {
                 computed = true;
-                property = self.parse_expression();
-                self.expect(CloseBracket, "Missing closing bracket in computed value.");
+                property = self.parse_expression()?;
+                self.expect(CloseBracket, "expected closing bracket after computed key")?;
             }
 
             object = Ast::MemberExpr {
                 object: Box::new(object),
                 property: Box::new(property),
                 computed,
+                span,
             }
         }
-        object
+        Ok(object)
     }
 
-    fn parse_call_expression(&mut self, caller: Ast) -> Ast {
-        let mut call_expr = Ast::CallExpr {
-            caller: Box::new(caller),
-            args: self.parse_args(),
-        };
-        println!("{:#?}", call_expr);
-        if let Some(Token::OpenParen) = self.at() {
-            call_expr = self.parse_call_expression(call_expr);
+    fn parse_call_expression(&mut self, mut caller: Ast, span: Span) -> Result<Ast, SyntaxError> {
+        // Allows chaining after a call: `make().field`, `make()[key]()`
+        while let Some(Token::OpenParen) = self.at() {
+            self.link()?;
+            caller = Ast::CallExpr {
+                caller: Box::new(caller),
+                args: self.parse_args()?,
+                span,
+            };
+            caller = self.parse_member_access(caller)?;
         }
-        call_expr
+        Ok(caller)
     }
 
-    fn parse_args(&mut self) -> Vec<Ast> {
-        self.expect(OpenParen, "expect open params before params");
+    fn parse_args(&mut self) -> Result<Vec<Ast>, SyntaxError> {
+        self.expect(OpenParen, "expected open parentheses before params")?;
         let mut args: Vec<Ast> = vec![];
         if !matches!(self.at(), Some(Token::CloseParen)) {
-            args = self.parse_args_list();
+            args = self.parse_args_list()?;
         }
-        self.expect(CloseParen, "expect close parentheses following arguments");
-        args
+        self.expect(CloseParen, "expected close parentheses after arguments")?;
+        Ok(args)
     }
 
-    fn parse_args_list(&mut self) -> Vec<Ast> {
+    fn parse_args_list(&mut self) -> Result<Vec<Ast>, SyntaxError> {
         let mut args: Vec<Ast> = vec![];
-        args.push(self.parse_assignment_expression());
+        args.push(self.parse_element()?);
         while let Some(Token::Comma) = self.at() {
             self.eat();
-            args.push(self.parse_assignment_expression());
+            args.push(self.parse_element()?);
+        }
+        Ok(args)
+    }
+
+    // An array element or call argument, either of which may be spread: `...values`.
+    fn parse_element(&mut self) -> Result<Ast, SyntaxError> {
+        if !matches!(self.at(), Some(Token::Ellipsis)) {
+            return self.parse_expression();
+        }
+        self.eat();
+        Ok(Ast::SpreadElement {
+            argument: Box::new(self.parse_expression()?),
+        })
+    }
+}
+
+// Whether `?.` appears along the object/caller spine of an access chain.
+fn has_null_guard(mut ast: &Ast) -> bool {
+    loop {
+        ast = match ast {
+            Ast::NullGuard { .. } => return true,
+            Ast::MemberExpr { object, .. } => object,
+            Ast::CallExpr { caller, .. } => caller,
+            _ => return false,
         }
-        args
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Ast {
-    let mut parser = Parser::new(tokens);
-    parser.parse()
+// `a?.b = 1` and `a?.b++` are rejected, as there may be nothing to assign to.
+fn check_assignee(assignee: &Ast, span: Span) -> Result<(), SyntaxError> {
+    match assignee {
+        Ast::OptionalChain { .. } => Err(SyntaxError::new(
+            "cannot assign to an optional chain".to_string(),
+            span,
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn parse(tokens: Vec<(Token, Span)>) -> Result<Ast, SyntaxError> {
+    parse_until(tokens, None, MAX_NESTING_DEPTH)
+}
+
+// Like `parse`, allowing code to nest `max_depth` levels deep and giving up with a syntax
+// error once `deadline` has passed.
+pub fn parse_until(
+    tokens: Vec<(Token, Span)>,
+    deadline: Option<Instant>,
+    max_depth: usize,
+) -> Result<Ast, SyntaxError> {
+    let mut parser = Parser::new(tokens, deadline, max_depth);
+    let result = parser.parse();
+    match parser.timed_out {
+        true => Err(SyntaxError::new(
+            "parsing took too long".to_string(),
+            parser.span(),
+        )),
+        false => result,
+    }
 }
@@ -0,0 +1,982 @@
+use std::collections::HashMap;
+
+use crate::frontend::ast::{Ast, Pattern, Property};
+use crate::frontend::linter::{Diagnostic, Rule};
+use crate::frontend::parser::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::frontend::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Variable,
+    Constant,
+    Function,
+    Parameter,
+    Class,
+    Enum,
+    Import,
+}
+
+// A name the program declares.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    // Where the name is written.
+    pub span: Span,
+    // The kind of value it is declared with, as `RuntimeVal::type_name` names it, when
+    // that can be told without running the program.
+    pub value: Option<&'static str>,
+    // The parameters, when it is declared with a function.
+    pub parameters: Option<Vec<String>>,
+    // The keys, when it is declared with an object literal.
+    pub fields: Vec<String>,
+    pub scope: usize,
+}
+
+// A use of a name; `declaration` is none for names of the global scope.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    pub declaration: Option<usize>,
+}
+
+// The source a scope covers, from the first to the last name or node seen in it.
+#[derive(Debug, Clone)]
+pub struct ScopeRange {
+    pub parent: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+}
+
+// What the names of a program refer to, and the problems found on the way. Scope 0 is the
+// top level of the program.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
+    pub scopes: Vec<ScopeRange>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolution {
+    // The declaration of the name at byte `offset`, written there or referred to.
+    pub fn declaration_at(&self, offset: usize) -> Option<usize> {
+        let declared = self
+            .declarations
+            .iter()
+            .position(|declaration| contains(declaration.span, offset));
+        declared.or_else(|| self.reference_at(offset)?.declaration)
+    }
+
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|reference| contains(reference.span, offset))
+    }
+
+    // Where the declaration is referred to, in source order.
+    pub fn references_to(&self, declaration: usize) -> Vec<Span> {
+        let mut spans: Vec<Span> = self
+            .references
+            .iter()
+            .filter(|reference| reference.declaration == Some(declaration))
+            .map(|reference| reference.span)
+            .collect();
+        spans.sort_by_key(|span| span.start);
+        spans.dedup();
+        spans
+    }
+
+    // The declarations that can be named at byte `offset`, innermost first. Functions and
+    // classes count from the start of their scope, since bodies run after they are set up.
+    pub fn visible_at(&self, offset: usize) -> Vec<&Declaration> {
+        let innermost = self
+            .scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, scope)| scope.start <= offset && offset <= scope.end)
+            .min_by_key(|(id, scope)| (scope.end - scope.start, usize::MAX - id))
+            .map_or(0, |(id, _)| id);
+        let mut visible: Vec<&Declaration> = vec![];
+        let mut scope = Some(innermost);
+        while let Some(id) = scope {
+            for declaration in self.declarations.iter().filter(|d| d.scope == id) {
+                let hoisted = matches!(
+                    declaration.kind,
+                    DeclarationKind::Function | DeclarationKind::Class
+                );
+                let taken = visible.iter().any(|seen| seen.name == declaration.name);
+                if (hoisted || declaration.span.start <= offset) && !taken {
+                    visible.push(declaration);
+                }
+            }
+            scope = self.scopes[id].parent;
+        }
+        visible
+    }
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+// Resolves the names of a parsed program. `globals` are the names the global scope starts
+// with and whether each is a constant. Diagnostics come back in source order.
+pub fn resolve(program: &Ast, globals: &HashMap<String, bool>) -> Resolution {
+    let mut resolver = Resolver {
+        globals,
+        scopes: vec![],
+        resolution: Resolution::default(),
+    };
+    resolver.push_scope();
+    if let Ast::Program { statements } = program {
+        resolver.statements(statements);
+    }
+    resolver.pop_scope();
+    let mut resolution = resolver.resolution;
+    resolution
+        .diagnostics
+        .sort_by_key(|diagnostic| diagnostic.span.start);
+    resolution
+}
+
+// A name declared in a scope.
+struct Binding {
+    name: String,
+    // Where a `let` or `const` declared it; other declarations have no span.
+    span: Option<Span>,
+    constant: bool,
+    used: bool,
+    // Only `let` and `const` declarations that are not exported are reported when unused.
+    check_unused: bool,
+    // The fields of the object literal the variable holds, while nothing could have
+    // changed them, and the reads of other fields.
+    fields: Option<Vec<String>>,
+    absent_reads: Vec<(String, Span)>,
+    // None for `this`, `super` and rest names, whose place is not recorded.
+    declaration: Option<usize>,
+}
+
+impl Binding {
+    fn new(name: &str, constant: bool, declaration: Option<usize>) -> Self {
+        Binding {
+            name: name.to_string(),
+            span: None,
+            constant,
+            used: false,
+            check_unused: false,
+            fields: None,
+            absent_reads: vec![],
+            declaration,
+        }
+    }
+}
+
+// Code that runs after the scope it is written in has been set up, like function bodies,
+// so it is resolved once every declaration of that scope is known.
+enum Deferred<'a> {
+    Function {
+        parameters: &'a [Pattern],
+        body: &'a [Ast],
+        method: bool,
+    },
+    Class {
+        extends: bool,
+        fields: &'a [(String, Option<Ast>)],
+        methods: &'a [Ast],
+    },
+}
+
+struct Scope<'a> {
+    id: usize,
+    bindings: Vec<Binding>,
+    deferred: Vec<Deferred<'a>>,
+}
+
+struct Resolver<'a> {
+    globals: &'a HashMap<String, bool>,
+    scopes: Vec<Scope<'a>>,
+    resolution: Resolution,
+}
+
+impl<'a> Resolver<'a> {
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        self.resolution.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            span,
+        });
+    }
+
+    fn push_scope(&mut self) {
+        let id = self.resolution.scopes.len();
+        self.resolution.scopes.push(ScopeRange {
+            parent: self.scopes.last().map(|scope| scope.id),
+            start: usize::MAX,
+            end: 0,
+        });
+        self.scopes.push(Scope {
+            id,
+            bindings: vec![],
+            deferred: vec![],
+        });
+    }
+
+    // Resolves what was deferred in the innermost scope, then reports its unused variables
+    // and absent fields.
+    fn pop_scope(&mut self) {
+        loop {
+            let deferred = std::mem::take(&mut self.scopes.last_mut().unwrap().deferred);
+            if deferred.is_empty() {
+                break;
+            }
+            for deferred in deferred {
+                self.deferred(deferred);
+            }
+        }
+        let scope = self.scopes.pop().unwrap();
+        let range = self.resolution.scopes[scope.id].clone();
+        if let (Some(parent), true) = (range.parent, range.start <= range.end) {
+            let parent = &mut self.resolution.scopes[parent];
+            parent.start = parent.start.min(range.start);
+            parent.end = parent.end.max(range.end);
+        }
+        for binding in scope.bindings {
+            let Some(span) = binding.span else {
+                continue;
+            };
+            if binding.check_unused && !binding.used && !binding.name.starts_with('_') {
+                self.report(
+                    Rule::UnusedVariable,
+                    span,
+                    format!("{} is declared but never used", binding.name),
+                );
+            }
+            if binding.fields.is_some() {
+                for (field, span) in binding.absent_reads {
+                    self.report(
+                        Rule::AbsentField,
+                        span,
+                        format!("{} has no field {}", binding.name, field),
+                    );
+                }
+            }
+        }
+    }
+
+    // Widens the innermost scope to cover `span`.
+    fn touch(&mut self, span: Span) {
+        let range = &mut self.resolution.scopes[self.scopes.last().unwrap().id];
+        range.start = range.start.min(span.start);
+        range.end = range.end.max(span.end);
+    }
+
+    fn resolve(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.bindings.iter_mut().rev())
+            .find(|binding| binding.name == name)
+    }
+
+    // Resolves a name read or written at `span`, reporting it when nothing declares it.
+    fn reference(&mut self, name: &str, span: Span) -> Option<&mut Binding> {
+        self.touch(span);
+        let declaration = match self.resolve(name).map(|binding| binding.declaration) {
+            Some(Some(declaration)) => Some(declaration),
+            Some(None) => return self.resolve(name),
+            None if self.globals.contains_key(name) => None,
+            None if matches!(name, "null" | "true" | "false") => return None,
+            None => {
+                self.report(
+                    Rule::UndefinedVariable,
+                    span,
+                    format!("{} is not defined", name),
+                );
+                return None;
+            }
+        };
+        self.resolution.references.push(Reference {
+            name: name.to_string(),
+            span,
+            declaration,
+        });
+        self.resolve(name)
+    }
+
+    // Records a declaration in the innermost scope, returning its index.
+    fn declaration(&mut self, name: &str, kind: DeclarationKind, span: Span) -> usize {
+        self.touch(span);
+        self.resolution.declarations.push(Declaration {
+            name: name.to_string(),
+            kind,
+            span,
+            value: None,
+            parameters: None,
+            fields: vec![],
+            scope: self.scopes.last().unwrap().id,
+        });
+        self.resolution.declarations.len() - 1
+    }
+
+    // Declares a name that is never reported as unused, such as a function or parameter.
+    fn declare(&mut self, name: &str, constant: bool, declaration: Option<usize>) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope
+            .bindings
+            .push(Binding::new(name, constant, declaration));
+    }
+
+    // Declares a `let` or `const` variable written at `at`, reporting names that are
+    // already taken at `span`.
+    fn declare_variable(
+        &mut self,
+        name: &str,
+        constant: bool,
+        exported: bool,
+        span: Span,
+        at: Span,
+    ) -> usize {
+        let depth = self.scopes.len();
+        let current = &self.scopes[depth - 1];
+        let redeclared = current.bindings.iter().any(|binding| binding.name == name)
+            || (depth == 1 && self.globals.contains_key(name));
+        let shadowed = self.scopes[..depth - 1]
+            .iter()
+            .flat_map(|scope| scope.bindings.iter())
+            .rev()
+            .find(|binding| binding.name == name)
+            .map(|binding| binding.span);
+        match (redeclared, shadowed) {
+            (true, _) => self.report(
+                Rule::Shadowing,
+                span,
+                format!("{} is already declared in this scope", name),
+            ),
+            (false, Some(Some(outer))) => self.report(
+                Rule::Shadowing,
+                span,
+                format!("{} shadows the declaration at {}", name, outer),
+            ),
+            (false, Some(None)) => self.report(
+                Rule::Shadowing,
+                span,
+                format!("{} shadows a declaration in an enclosing scope", name),
+            ),
+            (false, None) => {}
+        }
+        let kind = match constant {
+            true => DeclarationKind::Constant,
+            false => DeclarationKind::Variable,
+        };
+        let declaration = self.declaration(name, kind, at);
+        let scope = self.scopes.last_mut().unwrap();
+        scope.bindings.push(Binding {
+            span: Some(span),
+            check_unused: !exported,
+            ..Binding::new(name, constant, Some(declaration))
+        });
+        declaration
+    }
+
+    // Declares the names a pattern binds, as parameters or match bindings.
+    fn declare_pattern(&mut self, pattern: &'a Pattern, kind: DeclarationKind) {
+        for (name, span) in bindings(pattern) {
+            let declaration = span.map(|span| self.declaration(&name, kind, span));
+            self.declare(&name, false, declaration);
+        }
+    }
+
+    fn statements(&mut self, statements: &'a [Ast]) {
+        for (index, statement) in statements.iter().enumerate() {
+            self.statement(statement, false);
+            // The last statement's value is the result, which uses what it declares.
+            if index + 1 == statements.len() {
+                let names = match statement {
+                    Ast::VariableDeclaration { identifier, .. } => vec![identifier.clone()],
+                    Ast::DestructuringDeclaration { pattern, .. } => pattern.bound_names(),
+                    _ => vec![],
+                };
+                for name in names {
+                    if let Some(binding) = self.resolve(&name) {
+                        binding.used = true;
+                    }
+                }
+            }
+            let span = match statement {
+                Ast::ReturnStatement { span, .. } => Some((*span, "return")),
+                Ast::ThrowStatement { span, .. } => Some((*span, "throw")),
+                _ => None,
+            };
+            if let (Some((span, keyword)), true) = (span, index + 1 < statements.len()) {
+                self.report(
+                    Rule::UnreachableCode,
+                    span,
+                    format!("code after this {} is unreachable", keyword),
+                );
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Ast, exported: bool) {
+        if let Some(span) = statement.span() {
+            self.touch(span);
+        }
+        match statement {
+            Ast::VariableDeclaration {
+                constant,
+                identifier,
+                value,
+                span,
+                ..
+            } => {
+                self.expression(value);
+                let declaration =
+                    self.declare_variable(identifier, *constant, exported, *span, *span);
+                self.describe(declaration, value);
+                if !exported {
+                    let fields = object_fields(value);
+                    self.resolve(identifier).unwrap().fields = fields;
+                }
+            }
+            Ast::DestructuringDeclaration {
+                constant,
+                pattern,
+                value,
+                span,
+            } => {
+                self.expression(value);
+                self.pattern(pattern);
+                for (name, at) in bindings(pattern) {
+                    self.declare_variable(&name, *constant, exported, *span, at.unwrap_or(*span));
+                }
+            }
+            Ast::FunctionDeclaration {
+                name: Some(name),
+                parameters,
+                body,
+                span,
+                ..
+            } => {
+                let declaration = self.declaration(name, DeclarationKind::Function, *span);
+                self.describe(declaration, statement);
+                self.declare(name, false, Some(declaration));
+                self.defer(Deferred::Function {
+                    parameters,
+                    body,
+                    method: false,
+                });
+            }
+            Ast::ClassDeclaration {
+                name,
+                parent,
+                fields,
+                methods,
+                span,
+            } => {
+                if let Some(parent) = parent {
+                    self.expression(parent);
+                }
+                let declaration = self.declaration(name, DeclarationKind::Class, *span);
+                self.describe(declaration, statement);
+                self.declare(name, false, Some(declaration));
+                self.defer(Deferred::Class {
+                    extends: parent.is_some(),
+                    fields,
+                    methods,
+                });
+            }
+            Ast::EnumDeclaration { name, span, .. } => {
+                let declaration = self.declaration(name, DeclarationKind::Enum, *span);
+                self.describe(declaration, statement);
+                self.declare(name, true, Some(declaration));
+            }
+            Ast::ImportDeclaration { names, span, .. } => {
+                for (_, alias) in names {
+                    let declaration = self.declaration(alias, DeclarationKind::Import, *span);
+                    self.declare(alias, true, Some(declaration));
+                }
+            }
+            Ast::ExportDeclaration { declaration } => self.statement(declaration, true),
+            Ast::Program { statements } => self.statements(statements),
+            Ast::BlockStatement { statements } => {
+                self.push_scope();
+                self.statements(statements);
+                self.pop_scope();
+            }
+            Ast::TryStatement {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => {
+                self.statement(block, false);
+                if let Some(handler) = handler {
+                    self.push_scope();
+                    if let Some((param, span)) = param {
+                        let declaration =
+                            self.declaration(param, DeclarationKind::Parameter, *span);
+                        self.declare(param, false, Some(declaration));
+                    }
+                    self.statement(handler, false);
+                    self.pop_scope();
+                }
+                if let Some(finalizer) = finalizer {
+                    self.statement(finalizer, false);
+                }
+            }
+            Ast::ReturnStatement { argument, .. } => {
+                if let Some(argument) = argument {
+                    self.expression(argument);
+                }
+            }
+            Ast::ThrowStatement { argument, .. } => self.expression(argument),
+            expression => self.expression(expression),
+        }
+    }
+
+    // Notes what a declaration's value shows about it.
+    fn describe(&mut self, declaration: usize, value: &Ast) {
+        let kind = match value {
+            Ast::Identifier(name, _) => self
+                .resolve(name)
+                .and_then(|binding| binding.declaration)
+                .and_then(|declaration| self.resolution.declarations[declaration].value),
+            value => value_kind(value),
+        };
+        let declaration = &mut self.resolution.declarations[declaration];
+        declaration.value = kind;
+        match value {
+            Ast::FunctionDeclaration { parameters, .. } => {
+                let parameters = parameters.iter().map(|p| p.to_string()).collect();
+                declaration.parameters = Some(parameters);
+            }
+            Ast::ObjectLiteral { properties } => {
+                declaration.fields = properties
+                    .iter()
+                    .filter_map(|property| match property {
+                        Property::Named(key, _) | Property::Method(key, _) => Some(key.clone()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    fn defer(&mut self, deferred: Deferred<'a>) {
+        self.scopes.last_mut().unwrap().deferred.push(deferred);
+    }
+
+    fn deferred(&mut self, deferred: Deferred<'a>) {
+        match deferred {
+            Deferred::Function {
+                parameters,
+                body,
+                method,
+            } => self.function(parameters, body, method),
+            Deferred::Class {
+                extends,
+                fields,
+                methods,
+            } => {
+                // Methods and field values see `super` when the class extends another.
+                self.push_scope();
+                if extends {
+                    self.declare("super", true, None);
+                }
+                for (_, value) in fields {
+                    if let Some(value) = value {
+                        self.push_scope();
+                        self.declare("this", true, None);
+                        self.expression(value);
+                        self.pop_scope();
+                    }
+                }
+                for method in methods {
+                    if let Ast::FunctionDeclaration {
+                        parameters, body, ..
+                    } = method
+                    {
+                        self.function(parameters, body, true);
+                    }
+                }
+                self.pop_scope();
+            }
+        }
+    }
+
+    // Parameters and the body of a function share one scope, as when it is called.
+    fn function(&mut self, parameters: &'a [Pattern], body: &'a [Ast], method: bool) {
+        self.push_scope();
+        if method {
+            self.declare("this", true, None);
+        }
+        for parameter in parameters {
+            self.pattern(parameter);
+            self.declare_pattern(parameter, DeclarationKind::Parameter);
+        }
+        self.statements(body);
+        self.pop_scope();
+    }
+
+    // The expressions inside a pattern: default values and the enums of variants.
+    fn pattern(&mut self, pattern: &'a Pattern) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Binding(..) => {}
+            Pattern::Object { properties, .. } => {
+                for (_, pattern) in properties {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Array { elements, .. } => {
+                for pattern in elements {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Variant {
+                enumeration,
+                fields,
+                ..
+            } => {
+                self.expression(enumeration);
+                for pattern in fields {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Default { pattern, default } => {
+                self.pattern(pattern);
+                self.expression(default);
+            }
+        }
+    }
+
+    // Operator and member chains make trees as deep as they are long, so resolving grows
+    // the stack on the heap like parsing does.
+    fn expression(&mut self, ast: &'a Ast) {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.resolve_expression(ast)
+        })
+    }
+
+    fn resolve_expression(&mut self, ast: &'a Ast) {
+        if let Some(span) = ast.span() {
+            self.touch(span);
+        }
+        match ast {
+            Ast::Identifier(name, span) => {
+                if let Some(binding) = self.reference(name, *span) {
+                    binding.used = true;
+                    // The object may be changed wherever it is passed to.
+                    binding.fields = None;
+                }
+            }
+            Ast::BinaryExpr {
+                left,
+                right,
+                operator,
+                span,
+            } => {
+                let comparison = matches!(operator.as_str(), "==" | "!=" | "<" | ">" | "<=" | ">=");
+                if let (true, Some(left), Some(right)) = (comparison, path(left), path(right)) {
+                    if left == right {
+                        self.report(
+                            Rule::SelfComparison,
+                            *span,
+                            format!("{} is compared with itself", left),
+                        );
+                    }
+                }
+                self.expression(left);
+                self.expression(right);
+            }
+            Ast::UnaryExpr { argument, .. } => self.expression(argument),
+            Ast::AssignmentExpr {
+                assignee,
+                value,
+                span,
+            } => {
+                self.expression(value);
+                self.assignee(assignee, *span, false);
+            }
+            Ast::CompoundAssignmentExpr {
+                assignee,
+                value,
+                span,
+                ..
+            } => {
+                self.expression(value);
+                self.assignee(assignee, *span, true);
+            }
+            Ast::UpdateExpr { argument, span, .. } => self.assignee(argument, *span, true),
+            Ast::MemberExpr {
+                object,
+                property,
+                computed,
+                span,
+            } => match (object.as_ref(), property.as_ref(), computed) {
+                (Ast::Identifier(name, at), Ast::Identifier(field, _), false) => {
+                    if let Some(binding) = self.reference(name, *at) {
+                        binding.used = true;
+                        if let Some(fields) = &binding.fields {
+                            if !fields.contains(field) {
+                                binding.absent_reads.push((field.clone(), *span));
+                            }
+                        }
+                    }
+                }
+                (Ast::Identifier(name, at), property, true) => {
+                    if let Some(binding) = self.reference(name, *at) {
+                        binding.used = true;
+                    }
+                    self.expression(property);
+                }
+                (object, property, computed) => {
+                    self.expression(object);
+                    if *computed {
+                        self.expression(property);
+                    }
+                }
+            },
+            Ast::CallExpr { caller, args, .. } => {
+                self.expression(caller);
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Ast::FunctionDeclaration {
+                name: None,
+                parameters,
+                body,
+                ..
+            } => self.defer(Deferred::Function {
+                parameters,
+                body,
+                method: false,
+            }),
+            Ast::MatchExpr { subject, arms } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.push_scope();
+                    self.pattern(&arm.pattern);
+                    self.declare_pattern(&arm.pattern, DeclarationKind::Variable);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            Ast::TemplateLiteral { parts } => {
+                for part in parts {
+                    self.expression(part);
+                }
+            }
+            Ast::ObjectLiteral { properties } => {
+                for property in properties {
+                    match property {
+                        Property::Named(key, None) => {
+                            if let Some(binding) = self.resolve(key) {
+                                binding.used = true;
+                                binding.fields = None;
+                            }
+                        }
+                        Property::Named(_, Some(value)) => self.expression(value),
+                        Property::Computed(key, value) => {
+                            self.expression(key);
+                            self.expression(value);
+                        }
+                        // Methods of object literals see `this`, the object they are read from.
+                        Property::Method(_, function) => {
+                            if let Ast::FunctionDeclaration {
+                                parameters, body, ..
+                            } = function
+                            {
+                                self.defer(Deferred::Function {
+                                    parameters,
+                                    body,
+                                    method: true,
+                                });
+                            }
+                        }
+                        Property::Spread(source) => self.expression(source),
+                    }
+                }
+            }
+            Ast::ArrayLiteral { elements } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            Ast::PropertyLiteral { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Ast::OptionalChain { expression } => self.expression(expression),
+            Ast::NullGuard { argument } | Ast::SpreadElement { argument } => {
+                self.expression(argument)
+            }
+            Ast::NumericLiteral(_) | Ast::FloatLiteral(_) | Ast::StringLiteral(_) => {}
+            statement => self.statement(statement, false),
+        }
+    }
+
+    // The target of `=`, `op=`, `++` or `--`; `reads` when the old value is used too.
+    fn assignee(&mut self, assignee: &'a Ast, span: Span, reads: bool) {
+        match assignee {
+            Ast::Identifier(name, at) => {
+                let constant = match self.reference(name, *at) {
+                    Some(binding) => {
+                        binding.used |= reads;
+                        binding.fields = None;
+                        binding.constant
+                    }
+                    None => self.globals.get(name).copied().unwrap_or_default(),
+                };
+                if constant {
+                    self.report(
+                        Rule::ConstAssignment,
+                        span,
+                        format!("cannot assign to {} because it is a constant", name),
+                    );
+                }
+            }
+            Ast::MemberExpr {
+                object,
+                property,
+                computed,
+                ..
+            } => match (object.as_ref(), property.as_ref(), computed) {
+                // Assigning a field the object already has leaves its fields as they are.
+                (Ast::Identifier(name, at), Ast::Identifier(field, _), false) => {
+                    if let Some(binding) = self.reference(name, *at) {
+                        binding.used = true;
+                        if binding
+                            .fields
+                            .as_ref()
+                            .is_some_and(|fields| !fields.contains(field))
+                        {
+                            binding.fields = None;
+                        }
+                    }
+                }
+                (object, property, computed) => {
+                    self.expression(object);
+                    if *computed {
+                        self.expression(property);
+                    }
+                }
+            },
+            assignee => self.expression(assignee),
+        }
+    }
+}
+
+// The names a pattern binds, with where each is written; rest names have no span.
+fn bindings(pattern: &Pattern) -> Vec<(String, Option<Span>)> {
+    match pattern {
+        Pattern::Wildcard | Pattern::Literal(_) => vec![],
+        Pattern::Binding(name, span) => vec![(name.clone(), Some(*span))],
+        Pattern::Object { properties, rest } => properties
+            .iter()
+            .flat_map(|(_, pattern)| bindings(pattern))
+            .chain(rest.iter().map(|rest| (rest.clone(), None)))
+            .collect(),
+        Pattern::Array { elements, rest } => elements
+            .iter()
+            .flat_map(bindings)
+            .chain(rest.iter().map(|rest| (rest.clone(), None)))
+            .collect(),
+        Pattern::Variant { fields, .. } => fields.iter().flat_map(bindings).collect(),
+        Pattern::Default { pattern, .. } => bindings(pattern),
+    }
+}
+
+// The kind of value an expression evaluates to, as `RuntimeVal::type_name` names it, when
+// the expression alone shows it.
+pub fn value_kind(value: &Ast) -> Option<&'static str> {
+    match value {
+        Ast::NumericLiteral(_) => Some("int"),
+        Ast::FloatLiteral(_) => Some("float"),
+        Ast::StringLiteral(_) | Ast::TemplateLiteral { .. } => Some("string"),
+        Ast::Identifier(name, _) => match name.as_str() {
+            "true" | "false" => Some("bool"),
+            "null" => Some("null"),
+            _ => None,
+        },
+        Ast::ObjectLiteral { .. } => Some("object"),
+        Ast::ArrayLiteral { .. } => Some("array"),
+        Ast::FunctionDeclaration { .. } => Some("function"),
+        Ast::ClassDeclaration { .. } => Some("class"),
+        Ast::EnumDeclaration { .. } => Some("enum"),
+        Ast::UnaryExpr { operator, argument } => match operator.as_str() {
+            "!" => Some("bool"),
+            _ => value_kind(argument).filter(|kind| matches!(*kind, "int" | "float")),
+        },
+        Ast::BinaryExpr {
+            left,
+            right,
+            operator,
+            ..
+        } => match operator.as_str() {
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => Some("bool"),
+            "+" | "-" | "*" | "/" | "%" => match (value_kind(left)?, value_kind(right)?) {
+                ("string", _) | (_, "string") if operator == "+" => Some("string"),
+                ("int", "int") => Some("int"),
+                ("int" | "float", "int" | "float") => Some("float"),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The fields of an object literal with fixed keys and no methods, which only change when
+// the object itself is assigned to.
+fn object_fields(value: &Ast) -> Option<Vec<String>> {
+    let Ast::ObjectLiteral { properties } = value else {
+        return None;
+    };
+    properties
+        .iter()
+        .map(|property| match property {
+            Property::Named(_, Some(value))
+                if matches!(**value, Ast::FunctionDeclaration { .. }) =>
+            {
+                None
+            }
+            Property::Named(key, _) => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// `a`, `a.b` or `a.b.c` as written, for expressions that read a variable or its fields.
+fn path(mut ast: &Ast) -> Option<String> {
+    let mut names = vec![];
+    while let Ast::MemberExpr {
+        object,
+        property,
+        computed: false,
+        ..
+    } = ast
+    {
+        let Ast::Identifier(property, _) = property.as_ref() else {
+            return None;
+        };
+        names.push(property.as_str());
+        ast = object;
+    }
+    match ast {
+        Ast::Identifier(name, _) if matches!(name.as_str(), "null" | "true" | "false") => None,
+        Ast::Identifier(name, _) => {
+            names.push(name);
+            names.reverse();
+            Some(names.join("."))
+        }
+        _ => None,
+    }
+}
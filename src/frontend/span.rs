@@ -0,0 +1,17 @@
+use std::fmt::{Display, Formatter};
+
+// Location of a token or node in the source. `start`/`end` are byte offsets,
+// `line`/`column` are 1-based and refer to `start`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
@@ -1,3 +1,7 @@
+use std::fmt::{Display, Formatter};
+
+use crate::frontend::span::Span;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(crate) enum TokenKind {
     OpenParen,
@@ -9,14 +13,38 @@ pub(crate) enum TokenKind {
     Equals,
     BinaryOperator,
     Number,
+    Float,
+    Template,
+    String,
     Identifier,
     Let,
     Const,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Fn,
+    Return,
+    Class,
+    Extends,
+    Enum,
+    Match,
+    If,
+    Import,
+    Export,
     Eof,
     Semicolon,
     Colon,
     Comma,
     Dot,
+    Ellipsis,
+    Arrow,
+    Bang,
+    QuestionDot,
+    Question,
+    Pipe,
+    AssignmentOperator,
+    UpdateOperator,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -28,16 +56,60 @@ pub enum Token {
     OpenBracket,
     CloseBracket,
     Equals,
-    BinaryOperator(char),
+    BinaryOperator(String),
     Number(isize),
+    // Kept as written so tokens stay `Eq`; parsed into an `f64` by the parser.
+    Float(String),
+    // A template literal: `text ${expression} text`.
+    Template(Vec<TemplatePart>),
+    String(String),
     Identifier(String),
     Let,
     Const,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Fn,
+    Return,
+    Class,
+    Extends,
+    Enum,
+    Match,
+    If,
+    Import,
+    Export,
     Eof,
     Semicolon,
     Colon,
     Comma,
     Dot,
+    Ellipsis,
+    Arrow,
+    Bang,
+    QuestionDot,
+    // `?` and `|` in type annotations: `int?`, `int | string`
+    Question,
+    Pipe,
+    // `+=`, `-=`, `*=`, `/=`, `%=` and `??=`
+    AssignmentOperator(String),
+    // `++` and `--`
+    UpdateOperator(String),
+}
+
+// A `// ...` line comment, without its line break. The parser never sees comments; tools
+// that reprint source, like the formatter, get them from `tokenize_with_comments`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TemplatePart {
+    Text(String),
+    // The tokens between `${` and `}`, ending with `Eof`.
+    Expression(Vec<(Token, Span)>),
 }
 
 impl Token {
@@ -48,9 +120,25 @@ impl Token {
             Token::Equals => TokenKind::Equals,
             Token::BinaryOperator(_) => TokenKind::BinaryOperator,
             Token::Number(_) => TokenKind::Number,
+            Token::Float(_) => TokenKind::Float,
+            Token::Template(_) => TokenKind::Template,
+            Token::String(_) => TokenKind::String,
             Token::Identifier(_) => TokenKind::Identifier,
             Token::Let => TokenKind::Let,
             Token::Const => TokenKind::Const,
+            Token::Try => TokenKind::Try,
+            Token::Catch => TokenKind::Catch,
+            Token::Finally => TokenKind::Finally,
+            Token::Throw => TokenKind::Throw,
+            Token::Fn => TokenKind::Fn,
+            Token::Return => TokenKind::Return,
+            Token::Class => TokenKind::Class,
+            Token::Extends => TokenKind::Extends,
+            Token::Enum => TokenKind::Enum,
+            Token::Match => TokenKind::Match,
+            Token::If => TokenKind::If,
+            Token::Import => TokenKind::Import,
+            Token::Export => TokenKind::Export,
             Token::Eof => TokenKind::Eof,
             Token::Semicolon => TokenKind::Semicolon,
             Token::OpenBrace => TokenKind::OpenBrace,
@@ -60,6 +148,64 @@ impl Token {
             Token::OpenBracket => TokenKind::OpenBracket,
             Token::CloseBracket => TokenKind::CloseBracket,
             Token::Dot => TokenKind::Dot,
+            Token::Ellipsis => TokenKind::Ellipsis,
+            Token::Arrow => TokenKind::Arrow,
+            Token::Bang => TokenKind::Bang,
+            Token::QuestionDot => TokenKind::QuestionDot,
+            Token::Question => TokenKind::Question,
+            Token::Pipe => TokenKind::Pipe,
+            Token::AssignmentOperator(_) => TokenKind::AssignmentOperator,
+            Token::UpdateOperator(_) => TokenKind::UpdateOperator,
         }
     }
 }
+
+// How error messages name a token: ``unexpected `}` ``, `unexpected end of input`.
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Token::Eof => return write!(f, "end of input"),
+            Token::Number(num) => return write!(f, "number {}", num),
+            Token::Float(num) => return write!(f, "number {}", num),
+            Token::String(str) => return write!(f, "string {:?}", str),
+            Token::Template(_) => return write!(f, "template literal"),
+            Token::Identifier(name)
+            | Token::BinaryOperator(name)
+            | Token::AssignmentOperator(name)
+            | Token::UpdateOperator(name) => name.as_str(),
+            Token::OpenParen => "(",
+            Token::CloseParen => ")",
+            Token::OpenBrace => "{",
+            Token::CloseBrace => "}",
+            Token::OpenBracket => "[",
+            Token::CloseBracket => "]",
+            Token::Equals => "=",
+            Token::Let => "let",
+            Token::Const => "const",
+            Token::Try => "try",
+            Token::Catch => "catch",
+            Token::Finally => "finally",
+            Token::Throw => "throw",
+            Token::Fn => "fn",
+            Token::Return => "return",
+            Token::Class => "class",
+            Token::Extends => "extends",
+            Token::Enum => "enum",
+            Token::Match => "match",
+            Token::If => "if",
+            Token::Import => "import",
+            Token::Export => "export",
+            Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::Ellipsis => "...",
+            Token::Arrow => "=>",
+            Token::Bang => "!",
+            Token::QuestionDot => "?.",
+            Token::Question => "?",
+            Token::Pipe => "|",
+        };
+        write!(f, "`{}`", text)
+    }
+}
@@ -0,0 +1,21 @@
+// v-lang as a library. `Interpreter` is the entry point for embedding the language in a
+// Rust program; the `v-lang` binary is a repl and file runner built on the same modules,
+// and the `v-lang-lsp` binary serves editors through `lsp::serve`.
+pub mod commands;
+pub mod embed;
+pub mod frontend;
+pub mod lsp;
+pub mod repl;
+#[cfg(test)]
+mod repl_test;
+pub mod runtime;
+
+// Lets the code generated by the derive macros name this crate from inside it.
+extern crate self as v_lang;
+
+pub use embed::Interpreter;
+pub use runtime::convert::{ConversionError, EnumValue, FromValue, IntoValue};
+pub use v_lang_derive::{FromValue, IntoValue};
+
+pub type Value = runtime::types::RuntimeVal;
+pub type Error = runtime::error::RuntimeError;
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::frontend::ast::Ast;
+use crate::frontend::error::{SyntaxError, TypeError};
+use crate::frontend::resolver::{self, Resolution};
+use crate::frontend::{checker, lexer, parser};
+
+// An open file and what is known about it.
+pub struct Document {
+    pub text: String,
+    // Why the text does not parse, when it does not.
+    pub error: Option<SyntaxError>,
+    // The latest text that parsed, which keeps answering queries while the text is being
+    // edited into something that parses again.
+    pub analysis: Option<Analysis>,
+}
+
+pub struct Analysis {
+    pub text: String,
+    pub program: Ast,
+    pub resolution: Resolution,
+    pub type_errors: Vec<TypeError>,
+}
+
+impl Document {
+    pub fn new(text: String, globals: &HashMap<String, bool>) -> Self {
+        let mut document = Document {
+            text: String::new(),
+            error: None,
+            analysis: None,
+        };
+        document.update(text, globals);
+        document
+    }
+
+    pub fn update(&mut self, text: String, globals: &HashMap<String, bool>) {
+        match lexer::tokenize(text.clone()).and_then(parser::parse) {
+            Ok(program) => {
+                let mut resolution = resolver::resolve(&program, globals);
+                widen_scopes(&text, &mut resolution);
+                let type_errors = checker::check(&program);
+                self.error = None;
+                self.analysis = Some(Analysis {
+                    text: text.clone(),
+                    program,
+                    resolution,
+                    type_errors,
+                });
+            }
+            Err(error) => self.error = Some(error),
+        }
+        self.text = text;
+    }
+
+    // The analysis of the text as it is now.
+    pub fn current(&self) -> Option<&Analysis> {
+        self.analysis
+            .as_ref()
+            .filter(|analysis| analysis.text == self.text)
+    }
+}
+
+// Scope ranges end at the last name or node seen in them. Each is widened to the `}` that
+// closes its block, so that positions on blank lines of a body are inside it.
+fn widen_scopes(text: &str, resolution: &mut Resolution) {
+    for scope in resolution.scopes.iter_mut().skip(1) {
+        if scope.start > scope.end {
+            continue;
+        }
+        if let Some(end) = block_end(text, scope.start) {
+            scope.end = scope.end.max(end);
+        }
+    }
+}
+
+// The offset after the `}` closing the block that `offset` is in. A parameter list counts
+// as part of the body that follows it.
+fn block_end(text: &str, offset: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut index = offset;
+    while index < bytes.len() {
+        match bytes[index] {
+            quote @ (b'"' | b'`') => {
+                index += 1;
+                while index < bytes.len() && bytes[index] != quote {
+                    index += 1 + usize::from(bytes[index] == b'\\');
+                }
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                index = text[index..]
+                    .find('\n')
+                    .map_or(text.len(), |end| index + end);
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' if depth == 0 => {
+                let body = text[index + 1..].trim_start();
+                if !body.starts_with('{') {
+                    return None;
+                }
+                index = text.len() - body.len();
+            }
+            b'}' if depth == 0 => return Some(index + 1),
+            b']' if depth == 0 => return None,
+            b')' | b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+// The byte offset of an LSP position: a 0-based line and a column in UTF-16 code units.
+// Positions past the end of a line or of the text are clamped to it.
+pub fn offset(text: &str, line: usize, character: usize) -> usize {
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+    let line_text = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (index, c) in line_text.char_indices() {
+        if units >= character {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line_text.len()
+}
+
+// The LSP position of a byte offset, as `(line, character)`.
+pub fn position(text: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let character = before[line_start..].encode_utf16().count();
+    (before.matches('\n').count(), character)
+}
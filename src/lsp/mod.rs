@@ -0,0 +1,621 @@
+// A language server for `.v` files, speaking the Language Server Protocol over stdio from
+// the `v-lang-lsp` binary. Documents are synced whole; each change is parsed and resolved again
+// to publish diagnostics and answer definition, references, hover, document symbol,
+// completion and formatting requests.
+mod document;
+mod protocol;
+
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use crate::commands;
+use crate::frontend::ast::Ast;
+use crate::frontend::formatter;
+use crate::frontend::lexer::KEYWORDS;
+use crate::frontend::linter::{LintConfig, Rule};
+use crate::frontend::resolver::{Declaration, DeclarationKind};
+use crate::frontend::span::Span;
+use crate::lsp::document::Document;
+use crate::lsp::protocol::{
+    get, get_bool, get_number, get_string, number, object, string, INVALID_PARAMS, INVALID_REQUEST,
+    METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use crate::runtime::environment::{self, Environment};
+use crate::runtime::native::json;
+use crate::runtime::types::RuntimeVal;
+
+// LSP enumerations, by the numbers the protocol uses.
+const SEVERITY_ERROR: usize = 1;
+const SEVERITY_WARNING: usize = 2;
+const SYMBOL_CLASS: usize = 5;
+const SYMBOL_METHOD: usize = 6;
+const SYMBOL_ENUM: usize = 10;
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+const SYMBOL_CONSTANT: usize = 14;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_FIELD: usize = 5;
+const COMPLETION_VARIABLE: usize = 6;
+const COMPLETION_CLASS: usize = 7;
+const COMPLETION_MODULE: usize = 9;
+const COMPLETION_ENUM: usize = 13;
+const COMPLETION_KEYWORD: usize = 14;
+const COMPLETION_CONSTANT: usize = 21;
+const REQUEST_FAILED: isize = -32803;
+
+// Serves one client until it sends `exit` or closes the stream. The status is 0 when the
+// client shut the server down first, as the protocol asks, and 1 otherwise.
+pub fn serve(mut reader: impl BufRead, writer: impl Write) -> i32 {
+    let mut server = Server::new(writer);
+    loop {
+        let handled = match protocol::read_message(&mut reader) {
+            Ok(Some(content)) => server.receive(&content),
+            Ok(None) => return 1,
+            Err(error) => Err(error),
+        };
+        match handled {
+            Ok(Some(status)) => return status,
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("lsp: {}", error);
+                return 1;
+            }
+        }
+    }
+}
+
+// A failed request: a JSON-RPC error code and message.
+type Failure = (isize, String);
+
+struct Server<W: Write> {
+    writer: W,
+    env: Environment,
+    globals: HashMap<String, bool>,
+    config: LintConfig,
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(writer: W) -> Self {
+        let env = environment::global_env();
+        let globals = env.globals();
+        Server {
+            writer,
+            env,
+            globals,
+            config: LintConfig::default(),
+            documents: HashMap::new(),
+            shut_down: false,
+        }
+    }
+
+    // Handles one message, returning the exit status once the client asks to exit.
+    fn receive(&mut self, content: &str) -> io::Result<Option<i32>> {
+        let message = match json::from_json(content) {
+            Ok(message) => message,
+            Err(error) => {
+                self.respond(RuntimeVal::Null, Err((PARSE_ERROR, error)))?;
+                return Ok(None);
+            }
+        };
+        let params = get(&message, "params").unwrap_or(RuntimeVal::Null);
+        match (get_string(&message, "method"), get(&message, "id")) {
+            (Some(method), _) if method == "exit" => return Ok(Some(i32::from(!self.shut_down))),
+            (Some(method), Some(id)) => {
+                let result = match self.shut_down {
+                    true => Err((INVALID_REQUEST, "the server is shut down".to_string())),
+                    false => self.request(&method, &params),
+                };
+                self.respond(id, result)?;
+            }
+            (Some(method), None) => self.notification(&method, &params)?,
+            // Responses to requests; the server never sends any.
+            (None, _) => {}
+        }
+        Ok(None)
+    }
+
+    fn request(&mut self, method: &str, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(RuntimeVal::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/formatting" => self.formatting(params),
+            method => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &RuntimeVal) -> io::Result<()> {
+        let Some(uri) = get_string(params, "textDocument.uri") else {
+            return Ok(());
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = get_string(params, "textDocument.text").unwrap_or_default();
+                let document = Document::new(text, &self.globals);
+                self.documents.insert(uri.clone(), document);
+                self.publish_diagnostics(&uri)
+            }
+            // Changes carry the whole text, as the server asks for in `initialize`.
+            "textDocument/didChange" => {
+                let changes = get(params, "contentChanges").unwrap_or(RuntimeVal::Null);
+                let text = protocol::elements(&changes)
+                    .last()
+                    .and_then(|change| get_string(change, "text"));
+                match (text, self.documents.get_mut(&uri)) {
+                    (Some(text), Some(document)) => document.update(text, &self.globals),
+                    _ => return Ok(()),
+                }
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish(&uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn send(&mut self, message: RuntimeVal) -> io::Result<()> {
+        protocol::write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, id: RuntimeVal, result: Result<RuntimeVal, Failure>) -> io::Result<()> {
+        let outcome = match result {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                object([
+                    ("code", RuntimeVal::Number(code)),
+                    ("message", string(message)),
+                ]),
+            ),
+        };
+        self.send(object([("jsonrpc", string("2.0")), ("id", id), outcome]))
+    }
+
+    // Lint rules are read from `.vlint` in the workspace root, or the working directory
+    // when the client names no root.
+    fn initialize(&mut self, params: &RuntimeVal) -> RuntimeVal {
+        let root = get_string(params, "rootUri")
+            .and_then(|uri| uri_path(&uri))
+            .unwrap_or_default();
+        let path = root.join(commands::LINT_CONFIG);
+        if path.is_file() {
+            match commands::lint_config(Some(path)) {
+                Ok(config) => self.config = config,
+                Err(error) => eprintln!("lsp: {}", error),
+            }
+        }
+        let completion = object([("triggerCharacters", RuntimeVal::array(vec![string(".")]))]);
+        object([
+            (
+                "capabilities",
+                object([
+                    ("textDocumentSync", number(1)),
+                    ("definitionProvider", RuntimeVal::Bool(true)),
+                    ("referencesProvider", RuntimeVal::Bool(true)),
+                    ("hoverProvider", RuntimeVal::Bool(true)),
+                    ("documentSymbolProvider", RuntimeVal::Bool(true)),
+                    ("completionProvider", completion),
+                    ("documentFormattingProvider", RuntimeVal::Bool(true)),
+                ]),
+            ),
+            ("serverInfo", object([("name", string("v-lang"))])),
+        ])
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let document = &self.documents[uri];
+        let text = document.text.as_str();
+        let diagnostics = match (&document.error, document.current()) {
+            (Some(error), _) => vec![diagnostic(
+                text,
+                error.span,
+                SEVERITY_ERROR,
+                &error.message,
+                "syntax",
+            )],
+            (None, Some(analysis)) => {
+                let lints = analysis
+                    .resolution
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| self.config.enabled(diagnostic.rule))
+                    .map(|diagnostic| {
+                        // Rules for code that fails when it runs are errors.
+                        let severity = match diagnostic.rule {
+                            Rule::UndefinedVariable | Rule::ConstAssignment => SEVERITY_ERROR,
+                            _ => SEVERITY_WARNING,
+                        };
+                        let message = diagnostic.message.as_str();
+                        let code = diagnostic.rule.name();
+                        self::diagnostic(text, diagnostic.span, severity, message, code)
+                    });
+                let type_errors = analysis.type_errors.iter().map(|error| {
+                    self::diagnostic(text, error.span, SEVERITY_ERROR, &error.message, "type")
+                });
+                lints.chain(type_errors).collect()
+            }
+            (None, None) => vec![],
+        };
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<RuntimeVal>) -> io::Result<()> {
+        self.send(object([
+            ("jsonrpc", string("2.0")),
+            ("method", string("textDocument/publishDiagnostics")),
+            (
+                "params",
+                object([
+                    ("uri", string(uri)),
+                    ("diagnostics", RuntimeVal::array(diagnostics)),
+                ]),
+            ),
+        ]))
+    }
+
+    // The document a request is about and the byte offset of its position.
+    fn locate(&self, params: &RuntimeVal) -> Result<(String, &Document, usize), Failure> {
+        let uri = get_string(params, "textDocument.uri")
+            .ok_or((INVALID_PARAMS, "expected textDocument.uri".to_string()))?;
+        let document = self
+            .documents
+            .get(&uri)
+            .ok_or((INVALID_PARAMS, format!("{} is not open", uri)))?;
+        let line = get_number(params, "position.line").unwrap_or_default();
+        let character = get_number(params, "position.character").unwrap_or_default();
+        let offset = document::offset(&document.text, line, character);
+        Ok((uri, document, offset))
+    }
+
+    fn definition(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (uri, document, offset) = self.locate(params)?;
+        let Some(analysis) = document.current() else {
+            return Ok(RuntimeVal::Null);
+        };
+        let resolution = &analysis.resolution;
+        Ok(match resolution.declaration_at(offset) {
+            Some(declaration) => {
+                let span = resolution.declarations[declaration].span;
+                location(&uri, &document.text, span)
+            }
+            None => RuntimeVal::Null,
+        })
+    }
+
+    fn references(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (uri, document, offset) = self.locate(params)?;
+        let Some(analysis) = document.current() else {
+            return Ok(RuntimeVal::Null);
+        };
+        let resolution = &analysis.resolution;
+        let Some(declaration) = resolution.declaration_at(offset) else {
+            return Ok(RuntimeVal::array(vec![]));
+        };
+        let mut spans = resolution.references_to(declaration);
+        if get_bool(params, "context.includeDeclaration").unwrap_or_default() {
+            spans.insert(0, resolution.declarations[declaration].span);
+        }
+        let locations = spans
+            .into_iter()
+            .map(|span| location(&uri, &document.text, span))
+            .collect();
+        Ok(RuntimeVal::array(locations))
+    }
+
+    fn hover(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (_, document, offset) = self.locate(params)?;
+        let Some(analysis) = document.current() else {
+            return Ok(RuntimeVal::Null);
+        };
+        let resolution = &analysis.resolution;
+        let (text, span) = match (
+            resolution.declaration_at(offset),
+            resolution.reference_at(offset),
+        ) {
+            (Some(declaration), reference) => {
+                let declaration = &resolution.declarations[declaration];
+                let span = reference.map_or(declaration.span, |reference| reference.span);
+                (describe(declaration), span)
+            }
+            (None, Some(reference)) => {
+                let name = reference.name.clone();
+                let span = reference.span;
+                let value = self.env.clone().lookup_variable(&name);
+                let kind = value.map_or("unknown", |value| value.type_name());
+                (format!("(global) {}: {}", name, kind), span)
+            }
+            (None, None) => return Ok(RuntimeVal::Null),
+        };
+        Ok(object([
+            (
+                "contents",
+                object([
+                    ("kind", string("markdown")),
+                    ("value", string(format!("```v\n{}\n```", text))),
+                ]),
+            ),
+            ("range", range(&document.text, span)),
+        ]))
+    }
+
+    fn document_symbols(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (_, document, _) = self.locate(params)?;
+        let Some(analysis) = document.current() else {
+            return Ok(RuntimeVal::Null);
+        };
+        let Ast::Program { statements } = &analysis.program else {
+            return Ok(RuntimeVal::array(vec![]));
+        };
+        let text = document.text.as_str();
+        let symbols = statements
+            .iter()
+            .flat_map(|statement| symbols(text, statement))
+            .collect();
+        Ok(RuntimeVal::array(symbols))
+    }
+
+    // Offers the keys of an object after `name.`, and otherwise the names in scope and the
+    // keywords. While the text does not parse, the last text that did is used.
+    fn completion(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (_, document, offset) = self.locate(params)?;
+        let before = &document.text[..offset];
+        let word = before.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+        let analysis = document.analysis.as_ref();
+        let visible = analysis.map_or(vec![], |analysis| {
+            let offset = offset.min(analysis.text.len());
+            analysis.resolution.visible_at(offset)
+        });
+        let mut items = vec![];
+        if let Some(object) = word.strip_suffix('.') {
+            let name_start = object
+                .trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                .len();
+            let name = &object[name_start..];
+            let fields = match visible.iter().find(|declaration| declaration.name == name) {
+                Some(declaration) => declaration.fields.clone(),
+                None => match self.env.clone().lookup_variable(name) {
+                    Ok(RuntimeVal::Object(object)) => {
+                        object.borrow().properties.keys().cloned().collect()
+                    }
+                    _ => vec![],
+                },
+            };
+            for field in fields {
+                items.push(completion_item(field, COMPLETION_FIELD, None));
+            }
+            return Ok(RuntimeVal::array(items));
+        }
+        for declaration in &visible {
+            let kind = match declaration.kind {
+                DeclarationKind::Variable | DeclarationKind::Parameter => COMPLETION_VARIABLE,
+                DeclarationKind::Constant => COMPLETION_CONSTANT,
+                DeclarationKind::Function => COMPLETION_FUNCTION,
+                DeclarationKind::Class => COMPLETION_CLASS,
+                DeclarationKind::Enum => COMPLETION_ENUM,
+                DeclarationKind::Import => COMPLETION_MODULE,
+            };
+            let detail = Some(describe(declaration));
+            items.push(completion_item(declaration.name.clone(), kind, detail));
+        }
+        let mut globals: Vec<&String> = self.globals.keys().collect();
+        globals.sort();
+        for name in globals {
+            if visible.iter().any(|declaration| &declaration.name == name) {
+                continue;
+            }
+            let value = self.env.clone().lookup_variable(name);
+            let kind = match &value {
+                Ok(RuntimeVal::Function(_) | RuntimeVal::NativeFunction(_)) => COMPLETION_FUNCTION,
+                Ok(RuntimeVal::Object(_)) => COMPLETION_MODULE,
+                _ => COMPLETION_VARIABLE,
+            };
+            let detail = value.ok().map(|value| value.type_name().to_string());
+            items.push(completion_item(name.clone(), kind, detail));
+        }
+        let mut keywords: Vec<&&str> = KEYWORDS.keys().collect();
+        keywords.sort();
+        for keyword in keywords {
+            items.push(completion_item(
+                keyword.to_string(),
+                COMPLETION_KEYWORD,
+                None,
+            ));
+        }
+        Ok(RuntimeVal::array(items))
+    }
+
+    // Replaces the whole text with its formatted version.
+    fn formatting(&self, params: &RuntimeVal) -> Result<RuntimeVal, Failure> {
+        let (_, document, _) = self.locate(params)?;
+        let text = document.text.as_str();
+        let formatted = formatter::format(text).map_err(|error| {
+            let message = format!("cannot format: {}", error);
+            (REQUEST_FAILED, message)
+        })?;
+        if formatted == text {
+            return Ok(RuntimeVal::array(vec![]));
+        }
+        let whole = Span {
+            start: 0,
+            end: text.len(),
+            ..Span::default()
+        };
+        let edit = object([
+            ("range", range(text, whole)),
+            ("newText", string(formatted)),
+        ]);
+        Ok(RuntimeVal::array(vec![edit]))
+    }
+}
+
+// How a declaration reads in hovers and completions, with the kind of value it holds when
+// that is known.
+fn describe(declaration: &Declaration) -> String {
+    let name = declaration.name.as_str();
+    let typed = |keyword: &str| match declaration.value {
+        Some(kind) => format!("{} {}: {}", keyword, name, kind),
+        None => format!("{} {}", keyword, name),
+    };
+    match declaration.kind {
+        DeclarationKind::Variable => typed("let"),
+        DeclarationKind::Constant => typed("const"),
+        DeclarationKind::Parameter => format!("(parameter) {}", name),
+        DeclarationKind::Function => {
+            let parameters = declaration.parameters.clone().unwrap_or_default();
+            format!("fn {}({})", name, parameters.join(", "))
+        }
+        DeclarationKind::Class => format!("class {}", name),
+        DeclarationKind::Enum => format!("enum {}", name),
+        DeclarationKind::Import => format!("import {}", name),
+    }
+}
+
+// The outline entries of a top level statement.
+fn symbols(text: &str, statement: &Ast) -> Vec<RuntimeVal> {
+    match statement {
+        Ast::ExportDeclaration { declaration } => symbols(text, declaration),
+        Ast::VariableDeclaration {
+            constant,
+            identifier,
+            span,
+            ..
+        } => {
+            let kind = match constant {
+                true => SYMBOL_CONSTANT,
+                false => SYMBOL_VARIABLE,
+            };
+            vec![symbol(text, identifier, kind, *span, vec![])]
+        }
+        Ast::DestructuringDeclaration {
+            constant,
+            pattern,
+            span,
+            ..
+        } => {
+            let kind = match constant {
+                true => SYMBOL_CONSTANT,
+                false => SYMBOL_VARIABLE,
+            };
+            let names = pattern.bound_names();
+            names
+                .iter()
+                .map(|name| symbol(text, name, kind, *span, vec![]))
+                .collect()
+        }
+        Ast::FunctionDeclaration {
+            name: Some(name),
+            span,
+            ..
+        } => vec![symbol(text, name, SYMBOL_FUNCTION, *span, vec![])],
+        Ast::ClassDeclaration {
+            name,
+            methods,
+            span,
+            ..
+        } => {
+            let methods = methods
+                .iter()
+                .filter_map(|method| match method {
+                    Ast::FunctionDeclaration {
+                        name: Some(name),
+                        span,
+                        ..
+                    } => Some(symbol(text, name, SYMBOL_METHOD, *span, vec![])),
+                    _ => None,
+                })
+                .collect();
+            vec![symbol(text, name, SYMBOL_CLASS, *span, methods)]
+        }
+        Ast::EnumDeclaration { name, span, .. } => {
+            vec![symbol(text, name, SYMBOL_ENUM, *span, vec![])]
+        }
+        _ => vec![],
+    }
+}
+
+fn symbol(
+    text: &str,
+    name: &str,
+    kind: usize,
+    span: Span,
+    children: Vec<RuntimeVal>,
+) -> RuntimeVal {
+    object([
+        ("name", string(name)),
+        ("kind", number(kind)),
+        ("range", range(text, span)),
+        ("selectionRange", range(text, span)),
+        ("children", RuntimeVal::array(children)),
+    ])
+}
+
+fn completion_item(label: String, kind: usize, detail: Option<String>) -> RuntimeVal {
+    match detail {
+        Some(detail) => object([
+            ("label", string(label)),
+            ("kind", number(kind)),
+            ("detail", string(detail)),
+        ]),
+        None => object([("label", string(label)), ("kind", number(kind))]),
+    }
+}
+
+// `code` is the lint rule, `syntax` for text that does not parse or `type` for type errors.
+fn diagnostic(text: &str, span: Span, severity: usize, message: &str, code: &str) -> RuntimeVal {
+    object([
+        ("range", range(text, span)),
+        ("severity", number(severity)),
+        ("code", string(code)),
+        ("source", string("v-lang")),
+        ("message", string(message)),
+    ])
+}
+
+fn location(uri: &str, text: &str, span: Span) -> RuntimeVal {
+    object([("uri", string(uri)), ("range", range(text, span))])
+}
+
+fn range(text: &str, span: Span) -> RuntimeVal {
+    let position = |offset: usize| {
+        let (line, character) = document::position(text, offset);
+        object([("line", number(line)), ("character", number(character))])
+    };
+    object([("start", position(span.start)), ("end", position(span.end))])
+}
+
+// The path of a `file://` URI, decoding `%XX` escapes.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = vec![];
+    let mut index = 0;
+    while index < encoded.len() {
+        let escaped = encoded
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (encoded[index], escaped) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use indexmap::IndexMap;
+
+use crate::runtime::native::json;
+use crate::runtime::types::RuntimeVal;
+
+// JSON-RPC error codes.
+pub const PARSE_ERROR: isize = -32700;
+pub const INVALID_REQUEST: isize = -32600;
+pub const METHOD_NOT_FOUND: isize = -32601;
+pub const INVALID_PARAMS: isize = -32602;
+
+// Reads the content of the next message, or none once the stream ends. Messages are a
+// `Content-Length` header and a blank line followed by that many bytes of JSON.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &RuntimeVal) -> io::Result<()> {
+    let content = json::to_json(message).map_err(io::Error::other)?;
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+// A JSON object with the given fields, in order.
+pub fn object<const N: usize>(fields: [(&str, RuntimeVal); N]) -> RuntimeVal {
+    let fields = fields
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value));
+    RuntimeVal::object(IndexMap::from_iter(fields))
+}
+
+pub fn string(str: impl Into<String>) -> RuntimeVal {
+    RuntimeVal::String(str.into())
+}
+
+pub fn number(num: usize) -> RuntimeVal {
+    RuntimeVal::Number(num as isize)
+}
+
+// The value at a dotted path of object keys, such as `textDocument.uri`.
+pub fn get(value: &RuntimeVal, path: &str) -> Option<RuntimeVal> {
+    let mut value = value.clone();
+    for key in path.split('.') {
+        let next = match &value {
+            RuntimeVal::Object(object) => object.borrow().properties.get(key)?.clone(),
+            _ => return None,
+        };
+        value = next;
+    }
+    Some(value)
+}
+
+pub fn get_string(value: &RuntimeVal, path: &str) -> Option<String> {
+    match get(value, path)? {
+        RuntimeVal::String(str) => Some(str),
+        _ => None,
+    }
+}
+
+pub fn get_number(value: &RuntimeVal, path: &str) -> Option<usize> {
+    match get(value, path)? {
+        RuntimeVal::Number(num) => usize::try_from(num).ok(),
+        _ => None,
+    }
+}
+
+pub fn get_bool(value: &RuntimeVal, path: &str) -> Option<bool> {
+    match get(value, path)? {
+        RuntimeVal::Bool(b_val) => Some(b_val),
+        _ => None,
+    }
+}
+
+pub fn elements(value: &RuntimeVal) -> Vec<RuntimeVal> {
+    match value {
+        RuntimeVal::Array(elements) => elements.borrow().clone(),
+        _ => vec![],
+    }
+}
@@ -1,11 +1,5 @@
-mod frontend;
-mod repl;
-#[cfg(test)]
-mod repl_test;
-mod runtime;
+use v_lang::repl;
 
 fn main() {
-    // repl_test::test_file::test_with_file()
     repl::run()
 }
-
@@ -1,38 +1,144 @@
+use std::fs;
 use std::io;
 use std::io::{stdout, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+use std::time::Duration;
 
+use crate::commands;
+use crate::frontend::error::SyntaxError;
 use crate::frontend::{lexer, parser};
-use crate::runtime::environment::Environment;
+use crate::runtime::environment::{Environment, MissingField};
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::limits::Limits;
 use crate::runtime::types::RuntimeVal;
-use crate::runtime::{environment, interpreter};
+use crate::runtime::{environment, interpreter, module};
 
 fn flush_to_std_out() {
     stdout().flush().unwrap();
 }
 
-fn read_str() -> String {
+// Returns `None` once stdin is closed.
+fn read_str() -> Option<String> {
     print!("> ");
     flush_to_std_out();
     let mut input = String::new();
-    io::stdin()
+    let read = io::stdin()
         .read_line(&mut input)
         .expect("Didn't receive input");
-    input
+    if read == 0 {
+        return None;
+    }
+    Some(input)
 }
 
+// `v-lang path/to/file.v args...` runs a file, whose script sees `args`; without arguments
+// an interactive repl starts. `v-lang fmt ...` formats, `v-lang lint ...` lints and
+// `v-lang check ...` type checks source files instead.
 pub fn run() {
-    print!("\nRepl 1.0.0\n");
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("fmt") => exit(commands::fmt(args.skip(1).collect())),
+        Some("lint") => exit(commands::lint(args.skip(1).collect())),
+        Some("check") => exit(commands::check(args.skip(1).collect())),
+        _ => {}
+    }
     let mut environment = environment::global_env();
-    loop {
-        let val = execute(&mut environment, read_str());
-        println!("{}", val);
+    if let Some(max_depth) = setting("VLANG_MAX_CALL_DEPTH") {
+        if let Err(error) = environment.set_max_call_depth(max_depth) {
+            eprintln!("VLANG_MAX_CALL_DEPTH: {}", error);
+            exit(1);
+        }
+    }
+    if let Ok("null") = std::env::var("VLANG_MISSING_FIELD").as_deref() {
+        environment.set_missing_field(MissingField::Null);
+    }
+    environment.set_limits(Limits {
+        max_steps: setting("VLANG_MAX_STEPS"),
+        max_call_depth: None,
+        max_nesting_depth: setting("VLANG_MAX_NESTING_DEPTH"),
+        max_source_depth: setting("VLANG_MAX_SOURCE_DEPTH"),
+        max_heap_bytes: setting("VLANG_MAX_HEAP_BYTES"),
+        timeout: setting("VLANG_TIMEOUT_MS").map(Duration::from_millis),
+    });
+    if let Some(path) = args.next() {
+        environment.set_args(args.collect());
+        return run_file(&mut environment, PathBuf::from(path));
+    }
+    print!("\nRepl 1.0.0\n");
+    while let Some(source) = read_str() {
+        environment.reset_usage();
+        match execute(&mut environment, source) {
+            Ok(val) => println!("{}", val),
+            Err(RuntimeError::Exit(code)) => exit(code),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+    println!();
+}
+
+// A numeric setting read from an environment variable; unset or invalid values are ignored.
+fn setting<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+fn run_file(environment: &mut Environment, path: PathBuf) {
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("cannot read {}: {}", path.display(), error);
+            exit(1);
+        }
+    };
+    environment.set_module_path(fs::canonicalize(&path).unwrap_or(path));
+    match module::run_entry(environment, source) {
+        Ok(_) => {}
+        Err(RuntimeError::Exit(code)) => exit(code),
+        Err(error) => {
+            eprintln!("{}", error);
+            exit(1);
+        }
+    }
+}
+
+// Runs `source` in `environment`. Syntax errors are raised as catchable SyntaxErrors
+// located in the environment's module. The time limit covers tokenizing and parsing too.
+pub fn execute(environment: &mut Environment, source: String) -> Result<RuntimeVal, RuntimeError> {
+    let tokens = lexer::tokenize(source).map_err(|error| syntax_error(environment, error));
+    environment.check_time()?;
+    let max_depth = environment.limits().max_source_depth;
+    let node = parser::parse_until(
+        tokens?,
+        environment.deadline(),
+        max_depth.unwrap_or(parser::MAX_NESTING_DEPTH),
+    )
+    .map_err(|error| syntax_error(environment, error));
+    environment.check_time()?;
+    interpreter::evaluate(environment, node?).map_err(|error| unhandled(environment, error))
+}
+
+// Control flow that escaped everything that could handle it, as the error it stands for.
+fn unhandled(environment: &Environment, error: RuntimeError) -> RuntimeError {
+    match error {
+        RuntimeError::Return(_) => environment.error(
+            ErrorKind::Syntax,
+            "return statement outside of function".to_string(),
+        ),
+        RuntimeError::ShortCircuit => environment.error(
+            ErrorKind::Type,
+            "?. outside of an optional chain".to_string(),
+        ),
+        error => error,
     }
 }
 
-pub fn execute(environment: &mut Environment, source: String) -> RuntimeVal {
-    let tokens = lexer::tokenize(source);
-    // println!("{:#?}", tokens);
-    let node = parser::parse(tokens);
-    println!("{:#?}", node);
-    interpreter::evaluate(environment, node)
+fn syntax_error(environment: &Environment, error: SyntaxError) -> RuntimeError {
+    let location = environment.module().location(error.span);
+    environment.error(
+        ErrorKind::Syntax,
+        format!("{} at {}", error.message, location),
+    )
 }
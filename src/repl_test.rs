@@ -1,15 +1,28 @@
+use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::error::Error;
 use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use crate::repl;
+use crate::frontend::linter::LintConfig;
+use crate::frontend::{checker, formatter, lexer, linter, parser};
+use crate::runtime::capabilities::{Allow, Capabilities};
 use crate::runtime::environment;
+use crate::runtime::environment::MissingField;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::limits::{Limit, Limits};
+use crate::runtime::module;
+use crate::runtime::native::native_function;
 use crate::runtime::types::RuntimeVal;
+use crate::{lsp, repl};
+use crate::{EnumValue, FromValue, Interpreter, IntoValue};
 
 #[test]
 fn test() {
     let string = "let x = 10 * ( 10 /10 ) - 1;x".to_string();
     let mut env = environment::global_env();
-    let runtime_val = repl::execute(&mut env, string);
+    let runtime_val = repl::execute(&mut env, string).unwrap();
     assert_eq!(runtime_val, RuntimeVal::Number(9))
 }
 
@@ -17,17 +30,2402 @@ fn test() {
 fn test_with_file() {
     let string = fs::read_to_string("src/test.v").unwrap();
     let mut env = environment::global_env();
-    let runtime_val = repl::execute(&mut env, string);
-    let properties_map = HashMap::from([
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    let properties_map = IndexMap::from([
         ("x".to_string(), RuntimeVal::Number(10)),
         ("y".to_string(), RuntimeVal::Number(32)),
         ("foo".to_string(), RuntimeVal::Number(100)),
         (
             "complex".to_string(),
-            RuntimeVal::Object(HashMap::from([("bar".to_string(), RuntimeVal::Bool(true))])),
+            RuntimeVal::object(IndexMap::from([(
+                "bar".to_string(),
+                RuntimeVal::Bool(true),
+            )])),
         ),
     ]);
-    assert_eq!(runtime_val, RuntimeVal::Object(properties_map));
+    assert_eq!(runtime_val, RuntimeVal::object(properties_map));
+}
+
+#[test]
+fn test_try_catch_recovers_from_missing_field() {
+    let string = r#"
+        const data = { name: "x" };
+        let result = 0;
+        try {
+            result = data.count;
+        } catch (e) {
+            result = e.kind;
+        }
+        result
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val,
+        RuntimeVal::String("ReferenceError".to_string())
+    );
+}
+
+#[test]
+fn test_finally_runs_after_throw() {
+    let string = r#"
+        let cleaned = false;
+        let caught = null;
+        try {
+            try {
+                throw "boom";
+            } finally {
+                cleaned = true;
+            }
+        } catch (e) {
+            caught = e;
+        }
+        { cleaned, caught }
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    let properties_map = IndexMap::from([
+        ("cleaned".to_string(), RuntimeVal::Bool(true)),
+        ("caught".to_string(), RuntimeVal::String("boom".to_string())),
+    ]);
+    assert_eq!(runtime_val, RuntimeVal::object(properties_map));
+}
+
+#[test]
+fn test_uncaught_runtime_errors() {
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, "10 / (5 - 5)".to_string()).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught ArithmeticError: division by zero"));
+    let error = repl::execute(&mut env, "missing".to_string()).unwrap_err();
+    assert!(error.to_string().starts_with("Uncaught ReferenceError"));
+    let error = repl::execute(&mut env, "null = 1;".to_string()).unwrap_err();
+    assert!(error.to_string().starts_with("Uncaught TypeError"));
+}
+
+#[test]
+fn test_function_calls_and_closures() {
+    let string = r#"
+        let base = 10;
+        fn add(a, b) {
+            return a + b + base;
+        }
+        const twice = fn (f, x) { f(f(x, 1), 1) };
+        twice(add, 1)
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val, RuntimeVal::Number(23));
+}
+
+#[test]
+fn test_stack_trace_lists_call_frames() {
+    let string = r#"
+        fn inner() { missing }
+        fn outer() { inner() }
+        outer()
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught ReferenceError: missing variable is not defined\n    at inner (called at 3:22)\n    at outer (called at 4:9)\n    at <main>"
+    );
+}
+
+#[test]
+fn test_recursion_limit_raises_stack_overflow() {
+    let mut env = environment::global_env();
+    env.set_max_call_depth(50).unwrap();
+    let string = r#"
+        fn forever(n) { forever(n + 1) }
+        let kind = null;
+        try { forever(0); } catch (e) { kind = e.kind; }
+        kind
+    "#
+    .to_string();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val,
+        RuntimeVal::String("StackOverflowError".to_string())
+    );
+}
+
+#[test]
+fn test_default_recursion_limit_fits_in_rust_stack() {
+    let mut env = environment::global_env();
+    let string = "fn forever(n) { forever(n + 1) } forever(0)".to_string();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error.to_string().starts_with("Uncaught StackOverflowError"));
+    // Each call here nests a dozen expressions deep.
+    let string = "fn d(n) { return { a: { b: { c: [[[`${d(n + 1)}`]]] } } }; } d(0);".to_string();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error.to_string().starts_with("Uncaught StackOverflowError"));
+}
+
+#[test]
+fn test_stack_trace_collapses_repeated_frames() {
+    let mut interpreter = Interpreter::new();
+    let error = interpreter
+        .eval("fn forever(n) { forever(n + 1) } forever(0)")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught StackOverflowError: stack overflow: maximum call depth of 150 exceeded calling forever
+    at forever (called at 1:17)
+    ... previous frame repeated 148 more times
+    at forever (called at 1:34)
+    at <main>"
+    );
+    // A frame repeated once is shown twice, which is no longer than the count.
+    let error = interpreter
+        .eval("fn twice(n) { match n { 2 => missing, _ => twice(n + 1) } } twice(0)")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught ReferenceError: missing variable is not defined
+    at twice (called at 1:44)
+    at twice (called at 1:44)
+    at twice (called at 1:61)
+    at <main>"
+    );
+}
+
+#[test]
+fn test_max_call_depth_fits_in_rust_stack() {
+    let mut env = environment::global_env();
+    env.set_max_call_depth(environment::MAX_CALL_DEPTH).unwrap();
+    let string = "fn count(n) { return n + (count(n + 1) ?? 0); } count(0)".to_string();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught StackOverflowError: stack overflow: maximum call depth of 10000"));
+    assert_eq!(
+        env.set_max_call_depth(environment::MAX_CALL_DEPTH + 1),
+        Err("maximum call depth must be at most 10000, found 10001".to_string())
+    );
+}
+
+#[test]
+fn test_objects_keep_insertion_order_when_printed() {
+    let string = r#"
+        const inner = { c: true, text: "say \"hi\"" };
+        { z: 1, a: inner, m: {} }
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"{ z: 1, a: { c: true, text: "say \"hi\"" }, m: {} }"#
+    );
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        "{\n    z: 1,\n    a: {\n        c: true,\n        text: \"say \\\"hi\\\"\",\n    },\n    m: {},\n}"
+    );
+    // Keys are quoted only where the lexer would not read them as one identifier.
+    let keys = r#"{ is_file: true, _x2: 1, "let": 0, "a-b": 2, "2d": 3 }"#;
+    let runtime_val = repl::execute(&mut env, keys.to_string()).unwrap();
+    assert_eq!(runtime_val.to_string(), keys);
+}
+
+#[test]
+fn test_large_objects_print_over_several_lines() {
+    let string = r#"
+        { description: "a fairly long string value", other: "another long string value", n: 1 }
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        "{\n    description: \"a fairly long string value\",\n    other: \"another long string value\",\n    n: 1,\n}"
+    );
+}
+
+#[test]
+fn test_class_methods_bind_this() {
+    let string = r#"
+        class Point {
+            x = 0;
+            y = 0;
+            constructor(x, y) {
+                this.x = x;
+                this.y = y;
+            }
+            move(dx, dy) {
+                this.x = this.x + dx;
+                this.y = this.y + dy;
+                return this;
+            }
+        }
+        let p = Point(1, 2);
+        p.move(1, 2);
+        p
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val.to_string(), "Point { x: 2, y: 4 }");
+}
+
+#[test]
+fn test_cyclic_objects_print_and_compare() {
+    let string = r#"
+        let o = { name: "o" };
+        o.me = o;
+        let list = [1];
+        list[0] = list;
+        let twin = { name: "o" };
+        twin.me = twin;
+        let outer = { name: "o", me: twin };
+        let other = { name: "p", me: o };
+        [o == o, o == twin, o == outer, o == other]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val.to_string(), "[true, true, true, false]");
+    let printed = repl::execute(&mut env, "[o, list]".to_string()).unwrap();
+    assert_eq!(
+        printed.to_string(),
+        r#"[{ name: "o", me: [Circular] }, [[Circular]]]"#
+    );
+    // The same object twice, side by side, is not a cycle.
+    let shared = repl::execute(&mut env, "let s = {}; [s, s]".to_string()).unwrap();
+    assert_eq!(shared.to_string(), "[{}, {}]");
+}
+
+#[test]
+fn test_class_inheritance_and_super() {
+    let string = r#"
+        class Shape {
+            name = "shape";
+            constructor(name) { this.name = name; }
+            describe() { "a " + this.name }
+        }
+        class Square extends Shape {
+            size;
+            constructor(size) {
+                super("square");
+                this.size = size;
+            }
+            describe() { super.describe() + " of size " + this.size }
+        }
+        Square(3).describe()
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val,
+        RuntimeVal::String("a square of size 3".to_string())
+    );
+}
+
+#[test]
+fn test_match_on_enum_variants_with_guards() {
+    let string = r#"
+        enum Shape { Circle(r), Rect(w, h), Empty }
+        fn describe(shape) {
+            match shape {
+                Shape.Circle(r) if r > 10 => "big circle",
+                Shape.Circle(r) => "circle " + r,
+                Shape.Rect(w, h) if w == h => "square",
+                Shape.Rect(w, h) => w * h,
+                Shape.Empty => "nothing",
+            }
+        }
+        [describe(Shape.Circle(20)), describe(Shape.Circle(2)), describe(Shape.Rect(3, 3)),
+            describe(Shape.Rect(2, 5)), describe(Shape.Empty), Shape.Rect(1, 2).h]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"["big circle", "circle 2", "square", 10, "nothing", 2]"#
+    );
+}
+
+#[test]
+fn test_match_literal_object_and_array_patterns() {
+    let string = r#"
+        fn classify(value) {
+            match value {
+                0 => "zero",
+                -1 => "minus one",
+                "text" => "string",
+                { kind: "user", name } => "user " + name,
+                [first, ...rest] => { first, rest },
+                _ => null,
+            }
+        }
+        [classify(0), classify(-1), classify("text"), classify({ kind: "user", name: "ann", id: 1 }),
+            classify([1, 2, 3]), classify(true)]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"["zero", "minus one", "string", "user ann", { first: 1, rest: [2, 3] }, null]"#
+    );
+}
+
+#[test]
+fn test_non_exhaustive_enum_match_is_an_error() {
+    let string = r#"
+        enum Light { Red, Green, Yellow }
+        match Light.Red {
+            Light.Red => "stop",
+            Light.Green => "go",
+        }
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error.to_string().starts_with(
+        "Uncaught MatchError: non-exhaustive match on enum Light: missing Light.Yellow"
+    ));
+}
+
+#[test]
+fn test_destructuring_declarations() {
+    let string = r#"
+        let user = { name: "ann", age: 30, role: "admin", team: "core" };
+        let { name, age: years, nickname = "none", ...others } = user;
+        const [first, second, third = 3, ...tail] = [1, 2];
+        let [head, ...rest] = [10, 20, 30];
+        let { point: { x, y = 0 } } = { point: { x: 5, y: null } };
+        [[name, years, nickname, others], [first, second, third, tail], [head, rest, x, y]]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    ["ann", 30, "none", { role: "admin", team: "core" }],
+    [1, 2, 3, []],
+    [10, [20, 30], 5, 0],
+]"#
+    );
+}
+
+#[test]
+fn test_destructuring_function_parameters() {
+    let string = r#"
+        fn describe({ name, tags: [tag, ...more] }, greeting = "hi " + name) {
+            [greeting, tag, more]
+        }
+        [describe({ name: "ann", tags: ["a", "b", "c"] }), describe({ name: "bob", tags: ["x"] }, "yo")]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[["hi ann", "a", ["b", "c"]], ["yo", "x", []]]"#
+    );
+}
+
+#[test]
+fn test_destructuring_mismatch_is_a_type_error() {
+    let string = r#"
+        let { missing } = { present: 1 };
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error.to_string().starts_with(
+        "Uncaught TypeError: cannot destructure { present: 1 } with pattern { missing }"
+    ));
+}
+
+#[test]
+fn test_object_spread_computed_and_quoted_keys() {
+    let string = r#"
+        let defaults = { host: "localhost", port: 80, debug: false };
+        let name = "level";
+        let config = {
+            ...defaults,
+            port: 8080,
+            [name + "s"]: 3,
+            "content-type": "json",
+            ...null,
+            double(x) { x * 2 },
+        };
+        [config, config.double(4), defaults.port]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    {
+        host: "localhost",
+        port: 8080,
+        debug: false,
+        levels: 3,
+        "content-type": "json",
+        double: fn double(x),
+    },
+    8,
+    80,
+]"#
+    );
+}
+
+#[test]
+fn test_object_literal_methods_bind_this() {
+    let string = r#"
+        let counter = {
+            count: 0,
+            increment(by = 1) {
+                this.count = this.count + by;
+                return this;
+            },
+        };
+        counter.increment().increment(2);
+        // Spreading copies the method, which then binds to the new object.
+        let copy = { ...counter, count: 10 };
+        copy.increment();
+        let o = { n: 1, f() { return this.n; } };
+        [counter.count, copy.count, o.f()]
+    "#;
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string.to_string()).unwrap();
+    assert_eq!(runtime_val.to_string(), "[3, 11, 1]");
+    assert_eq!(lint(string, &LintConfig::default()), Vec::<String>::new());
+}
+
+#[test]
+fn test_spread_in_arrays_and_call_arguments() {
+    let string = r#"
+        fn sum(a, b, c, d) { a + b + c + d }
+        let a = [1, 2];
+        let b = [3];
+        [[...a, ...b, 4], [...[]], sum(...a, ...b, 4), sum(0, ...[1, 2, 3])]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val.to_string(), "[[1, 2, 3, 4], [], 10, 6]");
+}
+
+#[test]
+fn test_spreading_a_non_array_is_a_type_error() {
+    let string = r#"
+        [...{ a: 1 }]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught TypeError: cannot spread { a: 1 }; only arrays can be spread"));
+}
+
+#[test]
+fn test_optional_chaining() {
+    let string = r#"
+        let user = { profile: { name: "ann", tags: ["a"] }, greet: fn() { "hi" }, nothing: null };
+        let key = "name";
+        [
+            user?.profile?.name,
+            user.nothing?.name.first.second,
+            user.profile?.[key],
+            user.nothing?.[key],
+            user.greet?.(),
+            user.nothing?.(),
+            user?.profile.tags?.[0],
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"["ann", null, "ann", null, "hi", null, "a"]"#
+    );
+}
+
+#[test]
+fn test_optional_chaining_finds_null_for_missing_fields() {
+    // Whatever the missing field policy, `?.` probes loosely structured data.
+    let string = r#"
+        let o = { a: 1, list: [] };
+        let caught = null;
+        try { o?.list.missing; } catch (e) { caught = e.kind; }
+        [o?.x, o?.x?.y, o?.["x"], o.list?.missing, caught]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[null, null, null, null, "ReferenceError"]"#
+    );
+    for (source, column) in [
+        ("a?.b = 5;", 6),
+        ("a?.b += 5;", 6),
+        ("a?.b++;", 5),
+        ("++a?.b;", 1),
+    ] {
+        let error = parser::parse(lexer::tokenize(source.to_string()).unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("cannot assign to an optional chain at 1:{}", column)
+        );
+    }
+}
+
+#[test]
+fn test_missing_field_policy() {
+    let string = r#"
+        let data = { a: { b: 1 } };
+        let caught = null;
+        try { data.missing; } catch (e) { caught = e.kind; }
+        caught
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val.to_string(), "ReferenceError");
+
+    env.set_missing_field(MissingField::Null);
+    let string = "[data.missing, data.missing.deeper, data.a.c, data.a.b]".to_string();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(runtime_val.to_string(), "[null, null, null, 1]");
+}
+
+#[test]
+fn test_compound_assignment_and_update_operators() {
+    let string = r#"
+        let total = 0;
+        let i = 0;
+        total += 30;
+        total -= 4;
+        total /= 2;
+        i++;
+        ++i;
+        let counts = { a: 1 };
+        counts.a *= 10;
+        counts.b ??= 7;
+        counts.a ??= 99;
+        let values = [10, 20];
+        let index = 0;
+        values[index++] -= 5;
+        values[index] %= 6;
+        let post = index--;
+        let pre = --index;
+        let fallback = null ?? "default";
+        [total, i, counts, values, post, pre, index, fallback]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[13, 2, { a: 10, b: 7 }, [5, 2], 1, -1, -1, "default"]"#
+    );
+}
+
+#[test]
+fn test_compound_assignment_respects_constants() {
+    let string = r#"
+        const limit = 3;
+        let results = [];
+        try { limit += 1; } catch (e) { results = [...results, e.kind]; }
+        try { limit++; } catch (e) { results = [...results, e.kind]; }
+        try { limit ??= 10; } catch (e) { results = [...results, e.kind]; }
+        [...results, limit]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"["TypeError", "TypeError", "TypeError", 3]"#
+    );
+}
+
+// Writes `files` into a fresh temporary directory and returns its path.
+fn module_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vlang-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    for (file, source) in files {
+        fs::write(dir.join(file), source).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_import_and_export_between_files() {
+    let dir = module_dir(
+        "modules",
+        &[
+            (
+                "lib/math.v",
+                r#"
+                import { counter } from "../state.v";
+                export fn square(x) { x * x }
+                export const { pi, e } = { pi: 3, e: 2 };
+                fn hidden() { 0 }
+                counter.loads += 1;
+            "#,
+            ),
+            ("state.v", "export let counter = { loads: 0 };"),
+            (
+                "main.v",
+                r#"
+                import { square, pi as PI } from "./lib/math.v";
+                import { square as sq } from "./lib/math.v";
+                import { counter } from "./state.v";
+            "#,
+            ),
+        ],
+    );
+    let mut env = environment::global_env();
+    env.set_module_path(dir.join("main.v"));
+    repl::execute(&mut env, fs::read_to_string(dir.join("main.v")).unwrap()).unwrap();
+    let runtime_val = repl::execute(&mut env, "[square(4), sq(5), PI, counter]".to_string());
+    assert_eq!(
+        runtime_val.unwrap().to_string(),
+        "[16, 25, 3, { loads: 1 }]"
+    );
+
+    let error = repl::execute(
+        &mut env,
+        r#"import { hidden } from "./lib/math.v";"#.to_string(),
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught ImportError: hidden is not exported by ./lib/math.v at "));
+}
+
+#[test]
+fn test_import_cycles_are_reported() {
+    let dir = module_dir(
+        "cycle",
+        &[
+            ("a.v", "import { b } from \"./b.v\";\nexport let a = 1;"),
+            (
+                "b.v",
+                "let x = 1;\n  import { a } from \"./a.v\";\nexport let b = 2;",
+            ),
+        ],
+    );
+    let mut env = environment::global_env();
+    env.set_module_path(dir.join("main.v"));
+    let error = repl::execute(&mut env, r#"import { a } from "./a.v";"#.to_string()).unwrap_err();
+    let message = error.to_string();
+    assert!(message.starts_with("Uncaught ImportError: import cycle detected at "));
+    assert!(message.contains("b.v:2:3: a.v -> b.v -> a.v"));
+}
+
+#[test]
+fn test_importing_the_entry_file_is_a_cycle() {
+    let dir = module_dir(
+        "entry-cycle",
+        &[("b.v", "import { a } from \"./a.v\";\nexport let b = 2;")],
+    );
+    let log = dir.join("log.txt");
+    let entry = format!(
+        "fs.append({:?}, \"a\");\nimport {{ b }} from \"./b.v\";\nexport let a = 1;",
+        log
+    );
+    fs::write(dir.join("a.v"), &entry).unwrap();
+    let mut env = environment::global_env();
+    env.set_module_path(dir.join("a.v"));
+    let error = module::run_entry(&mut env, entry).unwrap_err();
+    let message = error.to_string();
+    assert!(message.starts_with("Uncaught ImportError: import cycle detected at "));
+    assert!(
+        message.contains("b.v:1:1: a.v -> b.v -> a.v"),
+        "{}",
+        message
+    );
+    assert_eq!(fs::read_to_string(&log).unwrap(), "a");
+}
+
+#[test]
+fn test_capabilities_gate_io() {
+    let dir = module_dir(
+        "capabilities",
+        &[
+            ("lib/util.v", "export let answer = 42;"),
+            ("secret.v", "export let token = \"abc\";"),
+        ],
+    );
+    let capabilities = Capabilities {
+        read: Allow::Only(vec![dir.join("lib")]),
+        env_vars: Allow::Only(vec!["HOME".to_string()]),
+        ..Capabilities::none()
+    };
+    let mut env = environment::global_env_with(capabilities);
+    env.set_module_path(dir.join("main.v"));
+    let string = r#"
+        import { answer } from "./lib/util.v";
+        let denied = null;
+        try {
+            import { token } from "./lib/../secret.v";
+        } catch (e) {
+            denied = e.kind;
+        }
+        [answer, denied]
+    "#;
+    let runtime_val = repl::execute(&mut env, string.to_string()).unwrap();
+    assert_eq!(runtime_val.to_string(), "[42, \"PermissionError\"]");
+
+    assert!(env
+        .check_read(&dir.join("lib/new.v"), "cannot read")
+        .is_ok());
+    let error = env
+        .check_write(&dir.join("lib/new.v"), "cannot write")
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught PermissionError: permission denied: cannot write /"));
+    assert!(env.check_env_var("HOME").is_ok());
+    assert!(env.check_env_var("AWS_SECRET").is_err());
+    assert!(env.check_run("ls").is_err());
+
+    let mut interpreter = Interpreter::new();
+    let error = interpreter.eval("import { answer } from \"./x.v\";");
+    assert!(error.unwrap_err().to_string().contains("PermissionError"));
+}
+
+#[test]
+fn test_fs_module() {
+    let dir = module_dir("fs", &[("lib/a.txt", "alpha")]);
+    let string = format!(
+        r#"
+        let root = {:?};
+        fs.mkdir(root + "/out/nested", true);
+        fs.write_text(root + "/out/notes.txt", "one\n");
+        fs.append(root + "/out/notes.txt", "two\n");
+        let info = fs.stat(root + "/out/notes.txt");
+        let listing = fs.list_dir(root + "/out");
+        let missing = null;
+        try {{
+            fs.read_text(root + "/nope.txt");
+        }} catch (e) {{
+            missing = e.kind;
+        }}
+        fs.remove(root + "/out", true);
+        [
+            fs.read_text(root + "/lib/a.txt"),
+            listing,
+            [info.size, info.is_file, info.is_dir],
+            missing,
+            fs.exists(root + "/out"),
+        ]
+    "#,
+        dir.display().to_string()
+    );
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"["alpha", ["nested", "notes.txt"], [8, true, false], "IOError", false]"#
+    );
+
+    let mut interpreter = Interpreter::with_capabilities(Capabilities {
+        read: Allow::Only(vec![dir.clone()]),
+        ..Capabilities::none()
+    });
+    let string = format!(
+        r#"
+        let root = {:?};
+        let denied = null;
+        try {{ fs.write_text(root + "/lib/a.txt", "overwritten"); }} catch (e) {{ denied = e.message; }}
+        [fs.read_text(root + "/lib/a.txt"), denied]
+    "#,
+        dir.display().to_string()
+    );
+    let runtime_val = interpreter.eval(&string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        format!(
+            r#"["alpha", "permission denied: cannot write {}/lib/a.txt"]"#,
+            dir.display()
+        )
+    );
+}
+
+#[test]
+fn test_process_env_and_args() {
+    let dir = module_dir("args", &[("lib/cli.v", "export fn first() { args[0] }")]);
+    let mut env = environment::global_env();
+    env.set_module_path(dir.join("main.v"));
+    env.set_args(vec!["--verbose".to_string(), "input.txt".to_string()]);
+    let string = r#"
+        import { first } from "./lib/cli.v";
+        let result = process.run("sh", ["-c", "echo out; echo err >&2; exit 3"]);
+        [
+            [first(), args.len()],
+            [result.stdout, result.stderr, result.status],
+            [env.get("PATH") != null, env.get("VLANG_TEST_SURELY_UNSET")],
+        ]
+    "#
+    .to_string();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[["--verbose", 2], ["out\n", "err\n", 3], [true, null]]"#
+    );
+}
+
+#[test]
+fn test_exit_cannot_be_caught() {
+    let mut interpreter = Interpreter::new();
+    let string = r#"
+        let log = [];
+        let denied = [];
+        try { env.get("HOME"); } catch (e) { denied.push(e.kind); }
+        try { process.run("ls"); } catch (e) { denied.push(e.kind); }
+        try { stdin.read_line(); } catch (e) { denied.push(e.kind); }
+        try {
+            exit(3);
+        } catch (e) {
+            log.push("caught");
+        } finally {
+            log.push("finally");
+        }
+    "#;
+    let error = interpreter.eval(string).unwrap_err();
+    assert_eq!(error, RuntimeError::Exit(3));
+    assert_eq!(
+        interpreter.get_global("denied").unwrap().to_string(),
+        r#"["PermissionError", "PermissionError", "PermissionError"]"#
+    );
+    assert_eq!(
+        interpreter.get_global("log").unwrap().to_string(),
+        r#"["finally"]"#
+    );
+    assert_eq!(interpreter.get_global("args").unwrap().to_string(), "[]");
+}
+
+#[test]
+fn test_path_module() {
+    let string = r#"
+        [
+            path.join("a", "b/c", "d.txt"),
+            path.join("a", "/abs"),
+            [path.dirname("a/b/c.txt"), path.dirname("c.txt"), path.dirname("/")],
+            [path.basename("a/b/c.tar.gz"), path.extension("a/b/c.tar.gz"), path.extension("Makefile")],
+            [path.normalize("a/./b/../c/"), path.normalize("../a/.."), path.normalize("/../x"), path.normalize("a/..")],
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    "a/b/c/d.txt",
+    "/abs",
+    ["a/b", ".", "/"],
+    ["c.tar.gz", "gz", ""],
+    ["a/c", "..", "/x", "."],
+]"#
+    );
+}
+
+#[test]
+fn test_math_module() {
+    let string = r#"
+        [
+            [math.abs(-5), math.abs(-2.5), math.min(3, 1.5, 2), math.max(4, 9, -1)],
+            [math.pow(2, 10), math.pow(4, 0.5), math.sqrt(16), math.floor(2.7), math.ceil(2.1)],
+            [math.round(-2.5), math.gcd(48, -18), math.clamp(15, 0, 10), math.clamp(-1.5, -1, 1)],
+            [math.round(math.sin(math.PI / 2) * 100), math.atan2(0, 1), math.floor(math.E)],
+            [1.5 + 2, 7 / 2.0, 1 == 1.0],
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        "[
+    [5, 2.5, 1.5, 9],
+    [1024, 2.0, 4.0, 2, 3],
+    [-3, 6, 10, -1],
+    [100, 0.0, 2],
+    [3.5, 3.5, true],
+]"
+    );
+}
+
+#[test]
+fn test_math_argument_checking() {
+    let string = r#"
+        let errors = [];
+        try { math.abs(); } catch (e) { errors = [...errors, e.message]; }
+        try { math.sqrt("four"); } catch (e) { errors = [...errors, e.message]; }
+        try { math.sqrt(-4); } catch (e) { errors = [...errors, e.kind]; }
+        try { math.gcd(1.5, 3); } catch (e) { errors = [...errors, e.message]; }
+        try { math.max(); } catch (e) { errors = [...errors, e.message]; }
+        errors
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        r#"[
+    "math.abs expects 1 argument, got 0",
+    "math.sqrt expects a number, got four",
+    "RangeError",
+    "math.gcd expects an integer, got 1.5",
+    "math.max expects at least 1 argument, got 0",
+]"#
+    );
+}
+
+#[test]
+fn test_math_rejects_results_that_are_not_numbers() {
+    let mut interpreter = Interpreter::new();
+    for (source, expected) in [
+        ("math.pow(0, 0)", RuntimeVal::Number(1)),
+        ("math.pow(0, 0.5)", RuntimeVal::Float(0.0)),
+        ("math.pow(2, -1)", RuntimeVal::Float(0.5)),
+        ("math.pow(-8, 2.0)", RuntimeVal::Float(64.0)),
+        ("math.asin(1) * 2 == math.PI", RuntimeVal::Bool(true)),
+        ("math.acos(-1) == math.PI", RuntimeVal::Bool(true)),
+        ("math.acos(1)", RuntimeVal::Float(0.0)),
+        ("math.log(1)", RuntimeVal::Float(0.0)),
+        ("math.log(math.E)", RuntimeVal::Float(1.0)),
+    ] {
+        assert_eq!(interpreter.eval(source).unwrap(), expected, "{}", source);
+    }
+    for (source, message) in [
+        (
+            "math.pow(0, -1)",
+            "ArithmeticError: math.pow(0, -1) is infinite",
+        ),
+        (
+            "math.pow(0.0, -0.5)",
+            "ArithmeticError: math.pow(0.0, -0.5) is infinite",
+        ),
+        (
+            "math.pow(10.0, 400)",
+            "ArithmeticError: math.pow(10.0, 400) is infinite",
+        ),
+        (
+            "math.pow(-8, 1 / 3.0)",
+            "RangeError: math.pow(-8, 0.3333333333333333) is undefined",
+        ),
+        ("math.asin(2)", "RangeError: math.asin(2) is undefined"),
+        (
+            "math.asin(-1.5)",
+            "RangeError: math.asin(-1.5) is undefined",
+        ),
+        (
+            "math.acos(1.01)",
+            "RangeError: math.acos(1.01) is undefined",
+        ),
+        ("math.log(0)", "ArithmeticError: math.log(0) is infinite"),
+        ("math.log(-1)", "RangeError: math.log(-1) is undefined"),
+    ] {
+        let source = format!(
+            "try {{ {} }} catch (e) {{ e.kind + \": \" + e.message }}",
+            source
+        );
+        assert_eq!(
+            interpreter.eval(source.as_str()).unwrap(),
+            RuntimeVal::String(message.to_string()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_string_methods() {
+    let string = r#"
+        let line = "  2024-01-05 ERROR disk full  ";
+        let parts = line.trim().split(" ");
+        [
+            [line.len(), parts, "-".join(["a", 1, true]), "a b\tc".split()],
+            ["Hi".upper(), "Hi".lower(), "a-b-c".replace("-", "+"), "abc".chars()],
+            [parts[1].starts_with("ERR"), line.ends_with("x"), line.contains("disk")],
+            ["hello".slice(1, 3), "hello".slice(-3), "hello".index_of("l"), "hello"[4]],
+            ["7".pad_start(3, "0"), "ab".pad_end(4), "ab".repeat(3)],
+            [" 42 ".parse_int(), "ff".parse_int(16), "2.5".parse_float()],
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    [30, ["2024-01-05", "ERROR", "disk", "full"], "a-1-true", ["a", "b", "c"]],
+    ["HI", "hi", "a+b+c", ["a", "b", "c"]],
+    [true, false, true],
+    ["el", "llo", 2, "o"],
+    ["007", "ab  ", "ababab"],
+    [42, 255, 2.5],
+]"#
+    );
+}
+
+#[test]
+fn test_string_method_errors() {
+    let string = r#"
+        let errors = [];
+        try { "x".parse_int(); } catch (e) { errors = [...errors, e.kind + ": " + e.message]; }
+        try { "x".split(1); } catch (e) { errors = [...errors, e.message]; }
+        try { "x".len(1); } catch (e) { errors = [...errors, e.message]; }
+        try { "x".reverse(); } catch (e) { errors = [...errors, e.message]; }
+        errors
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        r#"[
+    "ValueError: cannot parse \"x\" as an integer",
+    "string.split expects a string, got 1",
+    "string.len expects 0 arguments, got 1",
+    "reverse method not found on string",
+]"#
+    );
+}
+
+#[test]
+fn test_array_methods_with_callbacks() {
+    let string = r#"
+        let numbers = [3, 1, 2];
+        let pushed = numbers.push(5, 4);
+        let popped = numbers.pop();
+        numbers.insert(0, 9);
+        let removed = numbers.remove(1);
+        let offset = 10;
+        [
+            [pushed, popped, removed, numbers, numbers.len()],
+            [numbers.map(fn(n) { n + offset }), numbers.filter(fn(n) { n > 2 })],
+            [numbers.reduce(fn(sum, n) { sum + n }), [].reduce(fn(a, b) { a + b }, 0)],
+            [numbers.find(fn(n) { n < 3 }), numbers.find(fn(n) { n > 100 })],
+            [numbers.any(fn(n) { n == 2 }), numbers.all(fn(n) { n > 1 }), [-1, -2].map(math.abs)],
+            [[3, 1, 2].sort(), ["b", "a"].sort(), [1, 3, 2].sort(fn(a, b) { b - a })],
+            [[1, 2].reverse(), [1, 2, 3].zip(["a", "b"]), ["x", "y"].enumerate()],
+            [[1, 2].flat_map(fn(n) { [n, n * 10] })],
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    [5, 4, 3, [9, 1, 2, 5], 4],
+    [[19, 11, 12, 15], [9, 5]],
+    [17, 0],
+    [1, null],
+    [true, false, [1, 2]],
+    [[1, 2, 3], ["a", "b"], [3, 2, 1]],
+    [[2, 1], [[1, "a"], [2, "b"]], [[0, "x"], [1, "y"]]],
+    [[1, 10, 2, 20]],
+]"#
+    );
+}
+
+#[test]
+fn test_object_keys_values_entries() {
+    let string = r#"
+        let config = { host: "localhost", port: 80 };
+        [Object.keys(config), Object.values(config), Object.entries(config)]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[["host", "port"], ["localhost", 80], [["host", "localhost"], ["port", 80]]]"#
+    );
+}
+
+#[test]
+fn test_callback_errors_propagate_with_stack() {
+    let string = r#"
+        fn check(n) { 10 / (n - 2) }
+        let result = null;
+        try { [1, 2, 3].map(check); } catch (e) { result = e.stack; }
+        let sorted = null;
+        try { [1, "a"].sort(); } catch (e) { sorted = e.message; }
+        [result, sorted]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val.to_string(),
+        r#"[
+    "    at check (called at 4:15)\n    at array.map (called at 4:15)\n    at <main>",
+    "array.sort cannot compare 1 and a",
+]"#
+    );
+}
+
+#[test]
+fn test_template_literals() {
+    let string = r#"
+        let user = { name: "ann", tags: ["a", "b"] };
+        let n = 2;
+        [
+            `Hello ${user.name}, you have ${n + 1} items`,
+            `${user.tags} ${({ "}": 1 })["}"]} ${`nested ${n * 2}`}`,
+            `multi
+line \${escaped} \` ${"}"}`,
+            `${1.5}${null}${true}`,
+        ]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        r#"[
+    "Hello ann, you have 3 items",
+    "[\"a\", \"b\"] 1 nested 4",
+    "multi\nline ${escaped} ` }",
+    "1.5nulltrue",
+]"#
+    );
+}
+
+#[test]
+fn test_template_expression_errors_report_source_position() {
+    let string = "let x = 1;\nlet s = `value: ${x} and ${missing()}`;".to_string();
+    let mut env = environment::global_env();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught ReferenceError: missing variable is not defined\n    at <main>"
+    );
+    let string = "fn fail() { 1 / 0 }\n`${\n  fail()}`".to_string();
+    let error = repl::execute(&mut env, string).unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("at fail (called at 3:3)\n    at <main>"));
+}
+
+#[test]
+fn test_json_parse_and_stringify_round_trip() {
+    let string = r#"
+        let payload = json.parse("{\"id\": 7, \"price\": 2.5, \"tags\": [\"a\", \"b\\n\"], \"ok\": true, \"next\": null}");
+        let text = json.stringify(payload);
+        [payload.id + 1, payload.tags[1], text == json.stringify(json.parse(text)), json.stringify([1, {}], 2)]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        r#"[
+    8,
+    "b\n",
+    true,
+    "[\n  1,\n  {}\n]",
+]"#
+    );
+    let string = r#"json.stringify({ name: "x", list: [1, 2.5, null] })"#.to_string();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        runtime_val,
+        RuntimeVal::String(r#"{"name":"x","list":[1,2.5,null]}"#.to_string())
+    );
+}
+
+#[test]
+fn test_json_errors() {
+    let string = r#"
+        let errors = [];
+        try { json.parse("{\"a\": 1,\n  }"); } catch (e) { errors = [...errors, e.kind + ": " + e.message]; }
+        let cyclic = { items: [] };
+        cyclic.items.push(cyclic);
+        try { json.stringify(cyclic); } catch (e) { errors = [...errors, e.message]; }
+        fn handler() {}
+        try { json.stringify({ handler }); } catch (e) { errors = [...errors, e.kind]; }
+        let shared = [1];
+        [errors, json.stringify([shared, shared])]
+    "#
+    .to_string();
+    let mut env = environment::global_env();
+    let runtime_val = repl::execute(&mut env, string).unwrap();
+    assert_eq!(
+        format!("{:#}", runtime_val),
+        r#"[
+    [
+        "SyntaxError: invalid JSON at 2:3: expected string key",
+        "cannot serialize a cyclic structure to JSON",
+        "TypeError",
+    ],
+    "[[1],[1]]",
+]"#
+    );
+}
+
+#[test]
+fn test_json_numbers_follow_the_grammar() {
+    let mut interpreter = Interpreter::new();
+    for (text, expected) in [
+        ("0", RuntimeVal::Number(0)),
+        ("-0", RuntimeVal::Number(0)),
+        ("-12", RuntimeVal::Number(-12)),
+        ("2.5", RuntimeVal::Float(2.5)),
+        ("-0.125", RuntimeVal::Float(-0.125)),
+        ("1e2", RuntimeVal::Float(100.0)),
+        ("1E+2", RuntimeVal::Float(100.0)),
+        ("25e-1", RuntimeVal::Float(2.5)),
+        ("99999999999999999999", RuntimeVal::Float(1e20)),
+    ] {
+        assert_eq!(
+            interpreter
+                .eval(format!("json.parse({:?})", text).as_str())
+                .unwrap(),
+            expected,
+            "{}",
+            text
+        );
+    }
+    for (text, message) in [
+        ("01", "invalid number 01"),
+        ("-01", "invalid number -01"),
+        ("1.", "invalid number 1."),
+        (".5", "unexpected '.'"),
+        ("-", "invalid number -"),
+        ("--1", "invalid number --1"),
+        ("+1", "unexpected '+'"),
+        ("1e", "invalid number 1e"),
+        ("1e+", "invalid number 1e+"),
+        ("1.e3", "invalid number 1.e3"),
+        ("1-2", "invalid number 1-2"),
+        ("1e400", "number 1e400 is out of range"),
+        ("-1e400", "number -1e400 is out of range"),
+    ] {
+        let source = format!(
+            "try {{ json.parse({:?}) }} catch (e) {{ e.kind + \": \" + e.message }}",
+            text
+        );
+        assert_eq!(
+            interpreter.eval(source.as_str()).unwrap(),
+            RuntimeVal::String(format!("SyntaxError: invalid JSON at 1:1: {}", message)),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_interpreter_embedding_api() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("limit", 3).unwrap();
+    interpreter.set_global("greeting", "hi").unwrap();
+    interpreter
+        .eval("fn shout(name, times) { greeting.upper() + \" \" + name.repeat(times) }")
+        .unwrap();
+    let result = interpreter
+        .call_function("shout", vec!["ab".into_value(), limit(&interpreter)])
+        .unwrap();
+    assert_eq!(String::from_value(result).unwrap(), "HI ababab");
+
+    interpreter.set_global("limit", 5).unwrap();
+    let total = interpreter.eval("let total = limit * 2; total").unwrap();
+    assert_eq!(i64::from_value(total).unwrap(), 10);
+    assert_eq!(
+        interpreter.get_global("total"),
+        Some(RuntimeVal::Number(10))
+    );
+    assert_eq!(interpreter.get_global("missing"), None);
+
+    assert!(interpreter.set_global("true", false).is_err());
+    let error = u32::from_value(RuntimeVal::Number(-1)).unwrap_err();
+    assert_eq!(error.to_string(), "expected u32, found out of range int -1");
+    let error = Vec::<u32>::from_value(vec![1, -1].into_value()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected u32, found out of range int -1 at [1]"
+    );
+    assert_eq!(
+        usize::MAX.into_value(),
+        RuntimeVal::Float(usize::MAX as f64)
+    );
+    assert_eq!(
+        usize::from_value(RuntimeVal::Float(usize::MAX as f64))
+            .unwrap_err()
+            .to_string(),
+        "expected usize, found float"
+    );
+    let error = bool::from_value(RuntimeVal::String("yes".to_string())).unwrap_err();
+    assert_eq!(error.to_string(), "expected bool, found string");
+
+    fn limit(interpreter: &Interpreter) -> RuntimeVal {
+        interpreter.get_global("limit").unwrap()
+    }
+}
+
+#[test]
+fn test_conversions_check_the_range_of_the_rust_type() {
+    fn int(num: isize) -> RuntimeVal {
+        RuntimeVal::Number(num)
+    }
+    assert_eq!(u8::from_value(int(255)), Ok(255));
+    assert_eq!(u8::from_value(int(0)), Ok(0));
+    assert_eq!(i8::from_value(int(-128)), Ok(-128));
+    assert_eq!(i8::from_value(int(127)), Ok(127));
+    assert_eq!(u16::from_value(int(65535)), Ok(65535));
+    assert_eq!(i16::from_value(int(-32768)), Ok(-32768));
+    assert_eq!(u64::from_value(int(isize::MAX)), Ok(isize::MAX as u64));
+    for error in [
+        u8::from_value(int(256)).unwrap_err(),
+        u8::from_value(int(-1)).unwrap_err(),
+        i8::from_value(int(128)).unwrap_err(),
+        i8::from_value(int(-129)).unwrap_err(),
+        u16::from_value(int(65536)).unwrap_err(),
+        i16::from_value(int(32768)).unwrap_err(),
+        u64::from_value(int(-1)).unwrap_err(),
+        f32::from_value(RuntimeVal::Float(1e39)).unwrap_err(),
+    ] {
+        assert_eq!(error.kind, ErrorKind::Range, "{}", error);
+    }
+    assert_eq!(
+        i8::from_value(int(-129)).unwrap_err().to_string(),
+        "expected i8, found out of range int -129"
+    );
+    assert_eq!(u64::MAX.into_value(), RuntimeVal::Float(u64::MAX as f64));
+    assert_eq!(i8::MIN.into_value(), int(-128));
+
+    assert_eq!(f32::from_value(int(3)), Ok(3.0));
+    assert_eq!(
+        f32::from_value(RuntimeVal::Float(f32::MAX as f64)),
+        Ok(f32::MAX)
+    );
+    assert_eq!(f32::from_value(RuntimeVal::Float(-0.5)), Ok(-0.5));
+    assert!(f32::from_value(RuntimeVal::Float(f64::NAN))
+        .unwrap()
+        .is_nan());
+    assert_eq!(1.5f32.into_value(), RuntimeVal::Float(1.5));
+
+    assert_eq!(char::from_value('x'.into_value()), Ok('x'));
+    for text in ["", "ab"] {
+        let error = char::from_value(text.into_value()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Value);
+    }
+    assert_eq!(
+        char::from_value(int(1)).unwrap_err().to_string(),
+        "expected char, found int"
+    );
+
+    // A host function raises the conversion error in the script.
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .set_global(
+            "byte",
+            native_function("byte", |env, args| {
+                let byte =
+                    u8::from_value(args[0].clone()).map_err(|error| error.into_error(env))?;
+                Ok(byte.into_value())
+            }),
+        )
+        .unwrap();
+    assert_eq!(
+        interpreter
+            .eval("try { byte(256) } catch (e) { e.kind + \": \" + e.message }")
+            .unwrap(),
+        RuntimeVal::String("RangeError: expected u8, found out of range int 256".to_string())
+    );
+}
+
+#[test]
+fn test_syntax_errors_are_catchable_values() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("let x = 1;").unwrap();
+    let error = interpreter.eval("let y = 1;\nlet = 2;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught SyntaxError: unexpected `=`, expected identifier at 2:5\n    at <main>"
+    );
+    let error = interpreter.eval("let s = \"open;").unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught SyntaxError: unterminated string literal at 1:9"));
+    let error = interpreter
+        .eval("let big = 99999999999999999999;")
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught SyntaxError: integer literal out of range at 1:11"));
+    let error = interpreter.eval("[1, 2").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("expected comma between array elements"));
+    // Nothing from a program with a syntax error runs, and the interpreter stays usable.
+    assert_eq!(interpreter.get_global("y"), None);
+    assert_eq!(interpreter.eval("x + 1").unwrap(), RuntimeVal::Number(2));
+}
+
+#[derive(Debug, PartialEq, IntoValue, FromValue)]
+struct Order {
+    id: i64,
+    items: Vec<Item>,
+    note: Option<String>,
+    status: Status,
+}
+
+#[derive(Debug, PartialEq, IntoValue, FromValue)]
+struct Item {
+    name: String,
+    price: f64,
+}
+
+#[derive(Debug, PartialEq, IntoValue, FromValue)]
+enum Status {
+    Pending,
+    Shipped { tracking: String },
+    Refunded(f64),
+}
+
+#[test]
+fn test_derived_conversions_round_trip_through_scripts() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .set_global("Status", RuntimeVal::Enum(Status::enumeration()))
+        .unwrap();
+    let order = Order {
+        id: 7,
+        items: vec![Item {
+            name: "pen".to_string(),
+            price: 1.5,
+        }],
+        note: None,
+        status: Status::Pending,
+    };
+    interpreter.set_global("order", order).unwrap();
+    let result = interpreter
+        .eval(
+            r#"
+            let label = match order.status { Status.Pending => "pending", _ => "other" };
+            {
+                id: order.id + 1,
+                items: [...order.items, { name: "ink", price: 2 }],
+                note: label,
+                status: Status.Shipped("ab12"),
+            }
+            "#,
+        )
+        .unwrap();
+    assert_eq!(
+        Order::from_value(result).unwrap(),
+        Order {
+            id: 8,
+            items: vec![
+                Item {
+                    name: "pen".to_string(),
+                    price: 1.5,
+                },
+                Item {
+                    name: "ink".to_string(),
+                    price: 2.0,
+                },
+            ],
+            note: Some("pending".to_string()),
+            status: Status::Shipped {
+                tracking: "ab12".to_string()
+            },
+        }
+    );
+
+    let counts = interpreter.eval("{ a: 1, \"b c\": 2 }").unwrap();
+    let counts = HashMap::<String, (i32,)>::from_value(counts).unwrap_err();
+    assert_eq!(
+        counts.to_string(),
+        "expected array of length 1, found int at a"
+    );
+    let pair = <(String, Option<bool>)>::from_value(interpreter.eval("[\"x\", null]").unwrap());
+    assert_eq!(pair.unwrap(), ("x".to_string(), None));
+    let refund = Status::Refunded(2.5).into_value();
+    assert_eq!(refund.to_string(), "Status.Refunded(2.5)");
+    assert_eq!(Status::from_value(refund).unwrap(), Status::Refunded(2.5));
+}
+
+#[test]
+fn test_conversion_errors_locate_the_wrong_value() {
+    let mut interpreter = Interpreter::new();
+    let errors: Vec<String> = [
+        "{ id: 1, items: [{ name: \"pen\", price: 1 }, { name: \"ink\", price: \"2\" }], status: \"Pending\" }",
+        "{ id: 1, items: [], status: \"Lost\" }",
+        "{ id: 1, items: [], note: 3, status: \"Pending\" }",
+        "{ items: [], status: \"Pending\" }",
+        "{ id: 1, items: [], status: \"Refunded\" }",
+        "[1]",
+    ]
+    .iter()
+    .map(|source| {
+        let value = interpreter.eval(source).unwrap();
+        Order::from_value(value).unwrap_err().to_string()
+    })
+    .collect();
+    assert_eq!(
+        errors,
+        [
+            "expected float, found string at items[1].price",
+            "expected Status variant, found variant Lost at status",
+            "expected string, found int at note",
+            "expected i64, found nothing at id",
+            "expected variant fields of length 1, found variant fields of length 0 at status.Refunded",
+            "expected Order object, found array",
+        ]
+    );
+}
+
+#[test]
+fn test_resource_limits_stop_runaway_scripts() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(Limits {
+        max_steps: Some(5_000),
+        max_call_depth: Some(20),
+        max_nesting_depth: None,
+        max_source_depth: None,
+        max_heap_bytes: Some(1_000_000),
+        timeout: None,
+    });
+    let busy = r#"
+        let result = "not stopped";
+        try {
+            "x".repeat(5000).chars().map(fn(c) { c + c });
+        } catch (e) {
+            result = "caught";
+        } finally {
+            result = "finally ran";
+        }
+        result
+    "#;
+    let limits: Vec<RuntimeError> = [
+        busy,
+        "fn down(n) { down(n + 1) } try { down(0); } catch (e) { e.kind }",
+        "\"ab\".repeat(600000)",
+        "fn grow(s) { grow(s + s) } grow(\"x\")",
+    ]
+    .iter()
+    .map(|source| interpreter.eval(source).unwrap_err())
+    .collect();
+    assert_eq!(
+        limits,
+        [
+            RuntimeError::LimitExceeded(Limit::Steps(5_000)),
+            RuntimeError::LimitExceeded(Limit::CallDepth(20)),
+            RuntimeError::LimitExceeded(Limit::HeapBytes(1_000_000)),
+            RuntimeError::LimitExceeded(Limit::HeapBytes(1_000_000)),
+        ]
+    );
+    assert_eq!(
+        limits[0].to_string(),
+        "LimitExceeded: step limit of 5000 exceeded"
+    );
+    // Every eval gets a fresh budget.
+    assert_eq!(
+        interpreter.eval("[1, 2, 3].map(fn(n) { n * 2 })").unwrap(),
+        RuntimeVal::array(vec![2, 4, 6].into_iter().map(RuntimeVal::Number).collect())
+    );
+}
+
+#[test]
+fn test_time_limit() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval("fn spin(n) { [1, 2, 3, 4].map(fn(x) { x + n }) }")
+        .unwrap();
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_millis(20)),
+        ..Limits::default()
+    });
+    let error = interpreter
+        .eval("\"x\".repeat(1000000).chars().map(fn(c) { spin(1) })")
+        .unwrap_err();
+    assert_eq!(
+        error,
+        RuntimeError::LimitExceeded(Limit::Time(Duration::from_millis(20)))
+    );
+    assert!(interpreter
+        .call_function("spin", vec![5.into_value()])
+        .is_ok());
+}
+
+#[test]
+fn test_errors_are_rust_errors() {
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<RuntimeVal, Box<dyn Error>> {
+        Ok(interpreter.eval(source)?)
+    }
+    let mut interpreter = Interpreter::new();
+    let error = run(&mut interpreter, "return 1;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Uncaught SyntaxError: return statement outside of function\n    at <main>"
+    );
+    assert!(error.source().is_none());
+    interpreter.set_limits(Limits {
+        max_steps: Some(10),
+        ..Limits::default()
+    });
+    let error = run(&mut interpreter, "[1, 2, 3].map(fn(x) { x * 2 })").unwrap_err();
+    assert_eq!(
+        error.source().map(|limit| limit.to_string()),
+        Some("step limit of 10 exceeded".to_string())
+    );
+}
+
+#[test]
+fn test_nesting_limit() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(Limits {
+        max_nesting_depth: Some(100),
+        ..Limits::default()
+    });
+    let error = interpreter
+        .eval("fn wrap(n) { [wrap(n + 1)] } wrap(0)")
+        .unwrap_err();
+    assert_eq!(error, RuntimeError::LimitExceeded(Limit::NestingDepth(100)));
+    assert_eq!(
+        error.to_string(),
+        "LimitExceeded: nesting depth limit of 100 exceeded"
+    );
+    assert_eq!(
+        interpreter.eval("[[[1]]][0][0][0] + 1").unwrap(),
+        RuntimeVal::Number(2)
+    );
+}
+
+#[test]
+fn test_natives_check_the_heap_limit_before_allocating() {
+    let dir = module_dir("large", &[]);
+    fs::write(dir.join("large.txt"), "x".repeat(2_000_000)).unwrap();
+    let mut interpreter = Interpreter::with_capabilities(Capabilities::all());
+    interpreter.set_limits(Limits {
+        max_heap_bytes: Some(1_000_000),
+        timeout: Some(Duration::from_secs(10)),
+        ..Limits::default()
+    });
+    let read = format!("fs.read_text({:?})", dir.join("large.txt"));
+    for source in [
+        read.as_str(),
+        "fs.read_text(\"/dev/zero\")",
+        "\"ab\".repeat(1000).replace(\"a\", \"x\".repeat(10000))",
+        "json.stringify(\"x\".repeat(600000))",
+        "\"x\".repeat(500000).chars()",
+        "\"x,\".repeat(300000).split(\",\")",
+        "process.run(\"yes\")",
+    ] {
+        assert_eq!(
+            interpreter.eval(source).unwrap_err(),
+            RuntimeError::LimitExceeded(Limit::HeapBytes(1_000_000)),
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        interpreter
+            .eval("process.run(\"sh\", [\"-c\", \"yes | head -c 1000\"]).stdout.len()")
+            .unwrap(),
+        RuntimeVal::Number(1000)
+    );
+}
+
+#[test]
+fn test_process_run_stops_at_the_time_limit() {
+    let mut interpreter = Interpreter::with_capabilities(Capabilities::all());
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_millis(200)),
+        ..Limits::default()
+    });
+    let started = Instant::now();
+    for source in [
+        "process.run(\"sleep\", [\"5\"])",
+        // The child exits at once but leaves `sleep` holding its output open.
+        "process.run(\"sh\", [\"-c\", \"sleep 5 &\"])",
+    ] {
+        assert_eq!(
+            interpreter.eval(source).unwrap_err(),
+            RuntimeError::LimitExceeded(Limit::Time(Duration::from_millis(200))),
+            "{}",
+            source
+        );
+    }
+    assert!(started.elapsed() < Duration::from_secs(4));
+}
+
+#[test]
+fn test_limits_cover_walking_shared_values() {
+    let mut interpreter = Interpreter::new();
+    // `d(s, n)` is tiny in memory but unfolds into 2^n copies of `s`.
+    interpreter
+        .eval("fn pair(a) { [a, a] } fn d(s, n) { match n { 0 => s, _ => pair(d(s, n - 1)) } }")
+        .unwrap();
+    interpreter.set_limits(Limits {
+        max_heap_bytes: Some(1_000_000),
+        timeout: Some(Duration::from_secs(2)),
+        ..Limits::default()
+    });
+    for source in [
+        "json.stringify(d([1], 40))",
+        "`${d([1], 40)}`",
+        "print(d([1], 40))",
+        "\"\" + d([1], 40)",
+    ] {
+        assert!(
+            matches!(
+                interpreter.eval(source).unwrap_err(),
+                RuntimeError::LimitExceeded(Limit::HeapBytes(_) | Limit::Time(_))
+            ),
+            "{}",
+            source
+        );
+    }
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_secs(2)),
+        ..Limits::default()
+    });
+    assert_eq!(
+        interpreter.eval("d([1], 60) == d([1], 60)").unwrap(),
+        RuntimeVal::Bool(true)
+    );
+    assert_eq!(
+        interpreter.eval("d([1], 60) == d([2], 60)").unwrap(),
+        RuntimeVal::Bool(false)
+    );
+    // Error messages only show the start of a value.
+    let error = interpreter.eval("d([1], 60) - 1").unwrap_err();
+    assert!(error.to_string().len() < 200, "{}", error);
+}
+
+#[test]
+fn test_time_limit_covers_parsing() {
+    let elements = vec!["1"; 200_000].join(", ");
+    let tokens = lexer::tokenize(format!("[{}]", elements)).unwrap();
+    let error =
+        parser::parse_until(tokens, Some(Instant::now()), parser::MAX_NESTING_DEPTH).unwrap_err();
+    assert_eq!(error.message, "parsing took too long");
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_millis(1)),
+        ..Limits::default()
+    });
+    assert_eq!(
+        interpreter.eval(&format!("[{}]", elements)).unwrap_err(),
+        RuntimeError::LimitExceeded(Limit::Time(Duration::from_millis(1)))
+    );
+    // Long programs parse in linear time.
+    interpreter.set_limits(Limits::default());
+    assert_eq!(
+        interpreter.eval(&format!("[{}].len()", elements)).unwrap(),
+        RuntimeVal::Number(200_000)
+    );
+}
+
+#[test]
+fn test_deeply_nested_code_is_a_syntax_error() {
+    let depth = 20_000;
+    let sources = [
+        format!("{}1{}", "[".repeat(depth), "]".repeat(depth)),
+        format!("{}1{}", "(".repeat(depth), ")".repeat(depth)),
+        format!("{}1{}", "{ a: ".repeat(depth), " }".repeat(depth)),
+        format!("{}1", "-".repeat(depth)),
+        format!("{}1{}", "fn() { ".repeat(depth), " }".repeat(depth)),
+        format!(
+            "fn f(x) {{ x }} {}1{}",
+            "f(".repeat(depth),
+            ")".repeat(depth)
+        ),
+        format!("let {}a{} = [1];", "[".repeat(depth), "]".repeat(depth)),
+        format!("let a: number{} = [];", "[]".repeat(depth)),
+    ];
+    let mut interpreter = Interpreter::new();
+    for source in sources {
+        let error = interpreter.eval(&source).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Uncaught SyntaxError: code nested more than 256 levels deep"),
+            "{}",
+            error
+        );
+    }
+    // Chains are flat, but still build trees as deep as they are long.
+    let chains = [
+        format!("{}1", "!".repeat(depth)),
+        vec!["1"; depth].join(" + "),
+        format!("let a = {{}}; a{}", ".a".repeat(depth)),
+        format!("fn f() {{ f }} f{}", "()".repeat(depth)),
+        // Chains started by other chains add up.
+        format!(
+            "{}1{}",
+            "(".repeat(3),
+            format!("{})", " + 1".repeat(4000)).repeat(3)
+        ),
+    ];
+    for source in chains {
+        let error = interpreter.eval(&source).unwrap_err().to_string();
+        assert!(
+            error.starts_with(
+                "Uncaught SyntaxError: expression chained more than 10000 levels deep"
+            ),
+            "{}",
+            error
+        );
+    }
+    let templates = format!("{}1{}", "`${".repeat(depth), "}`".repeat(depth));
+    assert!(interpreter
+        .eval(&templates)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Uncaught SyntaxError: templates nested more than 256 levels deep"));
+    // Code nested just within the limit still runs, prints and formats.
+    let nested = format!("{}1{}", "[".repeat(255), "]".repeat(255));
+    let value = interpreter.eval(&nested).unwrap();
+    assert_eq!(value.to_string().matches('[').count(), 255);
+    assert!(formatter::format(&nested).is_ok());
+    let sum = vec!["1"; 200].join(" + ");
+    assert_eq!(interpreter.eval(&sum).unwrap(), RuntimeVal::Number(200));
+}
+
+#[test]
+fn test_flat_chains_are_not_nesting() {
+    let chains = [
+        vec!["1"; 1000].join(" + "),
+        format!("let o = {{ a: 1 }}; {}", vec!["o.a"; 1000].join(" + ")),
+        format!("let a = {{}}; a.a = a; a{}.a == a", ".a".repeat(1000)),
+        format!("fn f() {{ f }} f{} == f", "()".repeat(1000)),
+        format!("{}true", "!".repeat(1000)),
+    ];
+    let mut interpreter = Interpreter::new();
+    let values: Vec<String> = chains
+        .iter()
+        .map(|source| interpreter.eval(source).unwrap().to_string())
+        .collect();
+    assert_eq!(values, ["1000", "1000", "true", "true", "true"]);
+    // Every pass over the tree copes with a chain as long as the parser allows.
+    let longest = vec!["1"; 10_000].join(" + ");
+    assert_eq!(
+        interpreter.eval(&longest).unwrap(),
+        RuntimeVal::Number(10_000)
+    );
+    assert!(formatter::format(&longest).is_ok());
+    let program = parser::parse(lexer::tokenize(longest).unwrap()).unwrap();
+    assert!(checker::check(&program).is_empty());
+    assert_eq!(
+        lint(&chains[0], &LintConfig::default()),
+        Vec::<String>::new()
+    );
+
+    let nested = format!("{}1{}", "[".repeat(300), "]".repeat(300));
+    assert!(interpreter.eval(&nested).is_err());
+    interpreter.set_limits(Limits {
+        max_source_depth: Some(400),
+        ..Limits::default()
+    });
+    assert!(interpreter.eval(&nested).is_ok());
+    interpreter.set_limits(Limits {
+        max_source_depth: Some(10),
+        ..Limits::default()
+    });
+    let error = interpreter.eval("[[[[[[[[[[[1]]]]]]]]]]]").unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Uncaught SyntaxError: code nested more than 10 levels deep"));
+}
+
+#[test]
+fn test_format() {
+    let source = r#"let total=add(1,// first
+  2);
+
+
+// helpers
+fn add(a,b,){
+  // sum
+  a+b // result
+}
+class Point extends Base {
+  move(dx) { this.x += dx; }
+  // origin
+  x = 0;
+}
+"#;
+    let expected = r#"let total = add(
+    1, // first
+    2
+);
+
+// helpers
+fn add(a, b) {
+    // sum
+    a + b // result
+}
+class Point extends Base {
+    move(dx) {
+        this.x += dx;
+    }
+
+    // origin
+    x = 0;
+}
+"#;
+    assert_eq!(formatter::format(source).unwrap(), expected);
+    assert_eq!(formatter::format(expected).unwrap(), expected);
+}
+
+#[test]
+fn test_format_keeps_comments_in_place() {
+    let cases = [
+        ("let arr =\n// c3\n[1, 2];\n", "// c3\nlet arr = [1, 2];\n"),
+        ("fn g() { // todo\n}\n", "fn g() { // todo\n}\n"),
+        ("fn g() {\n// todo\n}\n", "fn g() {\n    // todo\n}\n"),
+        (
+            "fn f(a, // c\n b) { // only\n}\n",
+            "fn f(\n    a, // c\n    b,\n) { // only\n}\n",
+        ),
+        ("class A {\n// c\n}\n", "class A {\n    // c\n}\n"),
+        ("f( // c\n);\n", "f( // c\n);\n"),
+        (
+            "fn add(a, b) // c\n{ a + b }\n",
+            "fn add(a, b) { // c\n    a + b\n}\n",
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(formatter::format(source).unwrap(), expected, "{}", source);
+    }
+}
+
+// Formatting is idempotent with a comment at any point of the corpus, on its own line or
+// after code.
+#[test]
+fn test_format_is_idempotent() {
+    let corpus = [
+        fs::read_to_string("src/test.v").unwrap(),
+        r#"let total = add(1, 2);
+fn add(a, b) {
+    a + b
+}
+class Point extends Base {
+    move(dx) { this.x += dx; }
+    x = 0;
+}
+let arr = [1, [2, 3], { a: 1, b: [] }];
+let s = match x { 1 => "a", _ => { b } };
+f(fn(a) { a }, [], {});
+"#
+        .to_string(),
+        r#"enum Shape { Circle(r), Empty }
+let { a, b: [c, d] } = o;
+const names: (string | null)[] = [];
+fn greet(p: { name: string }, t: int = 1): string { p.name }
+try { x(); } catch (e) { y(); } finally { z(); }
+import { q } from "./q.v";
+export fn h() { return `a${1 + 2}b`; }
+let v = a?.b ?? -c[0] * (d + e);
+"#
+        .to_string(),
+    ];
+    for source in corpus {
+        let tokens = lexer::tokenize(source.clone()).unwrap();
+        for (_, span) in tokens {
+            for comment in [" // c\n", "\n// c\n"] {
+                let mut commented = source.clone();
+                commented.insert_str(span.start, comment);
+                let once = formatter::format(&commented).unwrap();
+                assert!(once.contains("// c"), "{}", commented);
+                assert_eq!(formatter::format(&once).unwrap(), once, "{}", commented);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format_preserves_behaviour() {
+    let source = fs::read_to_string("src/test.v").unwrap();
+    let formatted = formatter::format(&source).unwrap();
+    let original = repl::execute(&mut environment::global_env(), source).unwrap();
+    let reformatted = repl::execute(&mut environment::global_env(), formatted).unwrap();
+    assert_eq!(original, reformatted);
+
+    let formatted =
+        formatter::format("let xs = [1, 2, 3,]; let n = (1 + 2) * -(-xs[0]); n").unwrap();
+    assert_eq!(
+        formatted,
+        "let xs = [1, 2, 3];\nlet n = (1 + 2) * -(-xs[0]);\nn;\n"
+    );
+    let mut env = environment::global_env();
+    assert_eq!(
+        repl::execute(&mut env, formatted),
+        Ok(RuntimeVal::Number(3))
+    );
+}
+
+#[test]
+fn test_comments_are_ignored() {
+    let mut env = environment::global_env();
+    let result = repl::execute(
+        &mut env,
+        "// leading\nlet x = 1; // one\nlet y = x / 2.0; // not a comment: 8 // 2\n// trailing\nx + y".to_string(),
+    );
+    assert_eq!(result, Ok(RuntimeVal::Float(1.5)));
+    let error = formatter::format("let x = ;").unwrap_err();
+    assert_eq!(error.message, "unexpected `;`");
+    for (source, message) in [
+        ("1 +", "unexpected end of input"),
+        ("let x = }", "unexpected `}`"),
+        (
+            "return 1",
+            "unexpected end of input, expected semicolon after return statement",
+        ),
+        ("match x { ) => 1 }", "unexpected `)` in pattern"),
+        ("let x: 5 = 1;", "expected type, found number 5"),
+        (
+            "import { a } \"m\";",
+            "expected from after import names, found string \"m\"",
+        ),
+    ] {
+        let error = parser::parse(lexer::tokenize(source.to_string()).unwrap()).unwrap_err();
+        assert_eq!(error.message, message, "{}", source);
+    }
+}
+
+// Each diagnostic as `line:column rule`.
+fn lint(source: &str, config: &LintConfig) -> Vec<String> {
+    let program = parser::parse(lexer::tokenize(source.to_string()).unwrap()).unwrap();
+    let globals = environment::global_env().globals();
+    linter::lint(&program, &globals, config)
+        .iter()
+        .map(|diagnostic| format!("{} {}", diagnostic.span, diagnostic.rule.name()))
+        .collect()
+}
+
+#[test]
+fn test_lint() {
+    let source = r#"let unused = 1;
+const limit = 10;
+fn check(value) {
+    let limit = value;
+    return limit;
+    print(limit);
+}
+const point = { x: 1, y: 2 };
+print(check(point.z), point.x == point.x);
+fn later() { helper() }
+fn helper() { later() }
+limit += 1;
+"#;
+    assert_eq!(
+        lint(source, &LintConfig::default()),
+        vec![
+            "1:5 unused-variable",
+            "4:9 shadowing",
+            "5:5 unreachable-code",
+            "9:19 absent-field",
+            "9:23 self-comparison",
+            "12:1 const-assignment",
+        ]
+    );
+    // The interpreter rejects the same assignments, even those that would leave the
+    // constant unchanged.
+    for (source, limit) in [
+        ("const limit = 10; limit += 1;", RuntimeVal::Number(10)),
+        ("const limit = 10; limit ??= 3;", RuntimeVal::Number(10)),
+        ("const limit = null; limit ??= 3;", RuntimeVal::Null),
+        (
+            "const limit = 10; fn f() { limit ??= 3 } f()",
+            RuntimeVal::Number(10),
+        ),
+        ("const limit = 10; limit++;", RuntimeVal::Number(10)),
+    ] {
+        let mut env = environment::global_env();
+        let program = parser::parse(lexer::tokenize(source.to_string()).unwrap()).unwrap();
+        assert!(
+            linter::lint(&program, &env.globals(), &LintConfig::default())
+                .iter()
+                .any(|diagnostic| diagnostic.rule == linter::Rule::ConstAssignment),
+            "{}",
+            source
+        );
+        let error = repl::execute(&mut env, source.to_string()).unwrap_err();
+        assert!(
+            error.to_string().contains("Cannot reassign variable limit"),
+            "{}",
+            source
+        );
+        assert_eq!(env.lookup_variable("limit"), Ok(limit), "{}", source);
+    }
+}
+
+#[test]
+fn test_lint_is_conservative() {
+    let source = r#"const config = { debug: false };
+config.verbose = true;
+print(config.verbose);
+fn update(object) { object.b = 2; }
+let shared = { a: 1 };
+update(shared);
+print(shared.b);
+let _scratch = 1;
+export const api = 1;
+let total = 0;
+total += 1;
+let result = total;
+"#;
+    assert_eq!(lint(source, &LintConfig::default()), Vec::<String>::new());
+}
+
+#[test]
+fn test_lint_config() {
+    let source = "let a = 1;\nlet b = a == a;\n";
+    let config = LintConfig::parse("# quieter\nunused-variable = off\nshadowing = on\n").unwrap();
+    assert_eq!(lint(source, &config), vec!["2:9 self-comparison"]);
+    assert_eq!(
+        LintConfig::parse("self-comparison = maybe").unwrap_err(),
+        "line 1: expected on or off for self-comparison, found maybe"
+    );
+    assert_eq!(
+        LintConfig::parse("\nno-such-rule = off").unwrap_err(),
+        "line 2: unknown rule no-such-rule"
+    );
+}
+
+// Runs the language server on a scripted session of JSON-RPC messages, returning its exit
+// status and the messages it sent.
+fn lsp_session(messages: &[String]) -> (i32, Vec<String>) {
+    let input: String = messages
+        .iter()
+        .map(|message| format!("Content-Length: {}\r\n\r\n{}", message.len(), message))
+        .collect();
+    let mut output = vec![];
+    let status = lsp::serve(input.as_bytes(), &mut output);
+    let output = String::from_utf8(output).unwrap();
+    let sent = output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|message| message.split_once("\r\n\r\n").unwrap().1.to_string())
+        .collect();
+    (status, sent)
+}
+
+fn lsp_open(text: &str) -> String {
+    let text = text.replace('\n', "\\n").replace('"', "\\\"");
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///main.v","languageId":"v","version":1,"text":"{}"}}}}}}"#,
+        text
+    )
+}
+
+fn lsp_request(id: usize, method: &str, line: usize, character: usize) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{{"textDocument":{{"uri":"file:///main.v"}},"position":{{"line":{},"character":{}}},"context":{{"includeDeclaration":true}}}}}}"#,
+        id, method, line, character
+    )
+}
+
+const LSP_INITIALIZE: &str =
+    r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"rootUri":null,"capabilities":{}}}"#;
+const LSP_SHUTDOWN: &str = r#"{"jsonrpc":"2.0","id":99,"method":"shutdown"}"#;
+const LSP_EXIT: &str = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+
+#[test]
+fn test_lsp_diagnostics() {
+    let change = lsp_open("let = 1;")
+        .replace("didOpen", "didChange")
+        .replace(
+            r#""languageId":"v","version":1,"text":"let = 1;"}"#,
+            r#""version":2},"contentChanges":[{"text":"let = 1;"}]"#,
+        );
+    let (status, sent) = lsp_session(&[
+        LSP_INITIALIZE.to_string(),
+        lsp_open("let unused = 1;\nprint(missing);"),
+        change,
+        LSP_SHUTDOWN.to_string(),
+        LSP_EXIT.to_string(),
+    ]);
+    assert_eq!(status, 0);
+    assert!(sent[0].contains(r#""definitionProvider":true"#));
+    assert!(sent[0].contains(r#""completionProvider":{"triggerCharacters":["."]}"#));
+    assert!(sent[1].contains(
+        r#"{"range":{"start":{"line":0,"character":4},"end":{"line":0,"character":10}},"severity":2,"code":"unused-variable","source":"v-lang","message":"unused is declared but never used"}"#
+    ));
+    assert!(sent[1].contains(
+        r#"{"range":{"start":{"line":1,"character":6},"end":{"line":1,"character":13}},"severity":1,"code":"undefined-variable","source":"v-lang","message":"missing is not defined"}"#
+    ));
+    assert!(sent[2].contains(r#""severity":1,"code":"syntax""#));
+    assert_eq!(sent[3], r#"{"jsonrpc":"2.0","id":99,"result":null}"#);
+
+    // Exiting without a shutdown request is an error.
+    let (status, _) = lsp_session(&[LSP_INITIALIZE.to_string(), LSP_EXIT.to_string()]);
+    assert_eq!(status, 1);
+}
+
+#[test]
+fn test_lsp_navigation() {
+    let source = "const limit = 10;\nfn grow(size) {\n  return size + limit;\n}\nprint(grow(2));\n";
+    let (_, sent) = lsp_session(&[
+        LSP_INITIALIZE.to_string(),
+        lsp_open(source),
+        lsp_request(1, "textDocument/definition", 2, 17),
+        lsp_request(2, "textDocument/references", 0, 8),
+        lsp_request(3, "textDocument/hover", 2, 17),
+        lsp_request(4, "textDocument/hover", 4, 2),
+        lsp_request(5, "textDocument/documentSymbol", 0, 0),
+    ]);
+    let limit = r#"{"start":{"line":0,"character":6},"end":{"line":0,"character":11}}"#;
+    let usage = r#"{"start":{"line":2,"character":16},"end":{"line":2,"character":21}}"#;
+    assert_eq!(
+        sent[2],
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":{{"uri":"file:///main.v","range":{}}}}}"#,
+            limit
+        )
+    );
+    assert_eq!(
+        sent[3],
+        format!(
+            r#"{{"jsonrpc":"2.0","id":2,"result":[{{"uri":"file:///main.v","range":{}}},{{"uri":"file:///main.v","range":{}}}]}}"#,
+            limit, usage
+        )
+    );
+    assert!(sent[4].contains(r#""value":"```v\nconst limit: int\n```""#));
+    assert!(sent[5].contains(r#""value":"```v\n(global) print: function\n```""#));
+    assert!(sent[6].contains(r#"{"name":"limit","kind":14,"#));
+    assert!(sent[6].contains(r#"{"name":"grow","kind":12,"#));
+}
+
+#[test]
+fn test_lsp_completion_and_formatting() {
+    let source = "let config = { name: \"v\", depth: 2 };\nfn show(item) {\n  \n}\nshow(config);";
+    // Completing after a dot, while the text does not parse, uses the text that last did.
+    let edited = "let config = { name: \\\"v\\\", depth: 2 };\\nconfig.";
+    let change = format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"file:///main.v","version":2}},"contentChanges":[{{"text":"{}"}}]}}}}"#,
+        edited
+    );
+    let (_, sent) = lsp_session(&[
+        LSP_INITIALIZE.to_string(),
+        lsp_open(source),
+        lsp_request(1, "textDocument/completion", 2, 2),
+        lsp_request(2, "textDocument/formatting", 0, 0),
+        change,
+        lsp_request(3, "textDocument/completion", 1, 7),
+    ]);
+    assert!(sent[2].starts_with(
+        r#"{"jsonrpc":"2.0","id":1,"result":[{"label":"item","kind":6,"detail":"(parameter) item"},{"label":"config","kind":6,"detail":"let config: object"},{"label":"show","kind":3,"detail":"fn show(item)"},"#
+    ));
+    assert!(sent[2].contains(r#"{"label":"print","kind":3,"detail":"function"}"#));
+    assert!(sent[2].contains(r#"{"label":"match","kind":14}"#));
+    assert!(sent[3].contains(
+        r#""newText":"let config = { name: \"v\", depth: 2 };\nfn show(item) {}\nshow(config);\n""#
+    ));
+    assert_eq!(
+        sent[5],
+        r#"{"jsonrpc":"2.0","id":3,"result":[{"label":"name","kind":5},{"label":"depth","kind":5}]}"#
+    );
+}
+
+fn type_check(source: &str) -> Vec<String> {
+    let program = parser::parse(lexer::tokenize(source.to_string()).unwrap()).unwrap();
+    checker::check(&program)
+        .iter()
+        .map(|error| format!("{} {}", error.span, error.message))
+        .collect()
+}
+
+#[test]
+fn test_format_annotations() {
+    let source = r#"let  id:int=1;
+const names :  (string|null)[] = [];
+fn greet(person:{name:string,age:int?},times: int=1):string{ person.name }
+class Point { constructor(x:float) { this.x = x; } }
+"#;
+    let expected = r#"let id: int = 1;
+const names: (string | null)[] = [];
+fn greet(person: { name: string, age: int? }, times: int = 1): string {
+    person.name
+}
+class Point {
+    constructor(x: float) {
+        this.x = x;
+    }
+}
+"#;
+    assert_eq!(formatter::format(source).unwrap(), expected);
+    assert_eq!(formatter::format(expected).unwrap(), expected);
+}
+
+#[test]
+fn test_typed_code_runs_unchanged() {
+    let source = r#"class Point {
+    x = 0;
+    constructor(x: int) { this.x = x; }
+    scale(by: float): float { this.x * by }
+}
+enum Shape { Circle(r), Empty }
+let count: int = 2;
+let label: string? = null;
+fn describe(item: { x: int }, prefix: string = "x="): string {
+    return prefix + item.x;
+}
+let shape: Shape = Shape.Circle(1);
+let point: Point = Point(count);
+let values: (int | float)[] = [point.scale(1.5), count];
+describe(point) + " " + (label ?? "none") + " " + values[0]
+"#;
+    assert_eq!(type_check(source), Vec::<String>::new());
+    let mut env = environment::global_env();
+    assert_eq!(
+        repl::execute(&mut env, source.to_string()),
+        Ok(RuntimeVal::String("x=2 none 3.0".to_string()))
+    );
+    // Code without annotations is never reported, even where it would fail to run.
+    let untyped = fs::read_to_string("src/test.v").unwrap();
+    assert_eq!(type_check(&untyped), Vec::<String>::new());
+    assert_eq!(
+        type_check("let a = 1; a = \"one\"; a - 1;"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_type_errors() {
+    let source = r#"let count: int = "five";
+fn add(a: int, b: int): int { a + b }
+add(1, "2");
+add(1);
+let name: string? = null;
+let size = name + 1;
+fn label(): string { return 5; }
+let widget: Widget = 1;
+count = 2.5;
+let person: { name: string } = { nam: "x" };
+"#;
+    assert_eq!(
+        type_check(source),
+        vec![
+            "1:5 expected int for count, found string",
+            "3:1 expected int for parameter b, found string",
+            "4:1 missing argument b of type int",
+            "6:12 unsupported operands for +: null and int",
+            "7:22 expected string to be returned, found int",
+            "8:13 unknown type Widget",
+            "9:1 expected int for count, found float",
+            "10:5 expected { name: string } for person, found { nam: string }",
+        ]
+    );
 }
 
 pub mod test_file {
@@ -36,6 +2434,7 @@ pub mod test_file {
     use crate::repl;
     use crate::runtime::environment;
 
+    #[allow(dead_code)]
     pub fn test_with_file() {
         let string = fs::read_to_string("src/test.v").unwrap();
         let mut env = environment::global_env();
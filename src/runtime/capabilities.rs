@@ -0,0 +1,109 @@
+use std::path::{Component, Path, PathBuf};
+
+// What a group of resources scripts may use: all of them, or only those listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Allow<T> {
+    All,
+    Only(Vec<T>),
+}
+
+impl<T> Default for Allow<T> {
+    fn default() -> Self {
+        Allow::Only(vec![])
+    }
+}
+
+// The I/O a program may perform, fixed when its global environment is created. The
+// default allows nothing; the command line runner allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    // Directories whose files, at any depth, may be read, including by `import`.
+    pub read: Allow<PathBuf>,
+    // Directories whose files, at any depth, may be created, changed or removed.
+    pub write: Allow<PathBuf>,
+    // Environment variables that may be read.
+    pub env_vars: Allow<String>,
+    // Programs that may be started, by name or path as the script passes them.
+    pub commands: Allow<String>,
+    // Whether the host's standard input may be read. A read blocks until a line arrives,
+    // which no limit can interrupt.
+    pub stdin: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Capabilities {
+            read: Allow::All,
+            write: Allow::All,
+            env_vars: Allow::All,
+            commands: Allow::All,
+            stdin: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Capabilities::default()
+    }
+
+    pub fn can_read(&self, path: &Path) -> bool {
+        within(&self.read, path)
+    }
+
+    pub fn can_write(&self, path: &Path) -> bool {
+        within(&self.write, path)
+    }
+
+    pub fn can_read_env_var(&self, name: &str) -> bool {
+        match &self.env_vars {
+            Allow::All => true,
+            Allow::Only(names) => names.iter().any(|allowed| allowed == name),
+        }
+    }
+
+    pub fn can_run(&self, command: &str) -> bool {
+        match &self.commands {
+            Allow::All => true,
+            Allow::Only(commands) => commands.iter().any(|allowed| allowed == command),
+        }
+    }
+}
+
+// Whether `path` is inside one of the allowed directories. Both sides are resolved first,
+// so `..` and symbolic links cannot lead outside them.
+fn within(allow: &Allow<PathBuf>, path: &Path) -> bool {
+    let directories = match allow {
+        Allow::All => return true,
+        Allow::Only(directories) => directories,
+    };
+    let Some(path) = resolve(path) else {
+        return false;
+    };
+    directories
+        .iter()
+        .filter_map(|directory| resolve(directory))
+        .any(|directory| path.starts_with(directory))
+}
+
+// The absolute, canonical form of `path`. Parts that do not exist yet, like a file about
+// to be written, are appended to their closest existing ancestor.
+pub fn resolve(path: &Path) -> Option<PathBuf> {
+    let absolute = std::env::current_dir().ok()?.join(path);
+    let mut missing = vec![];
+    let mut existing = absolute.as_path();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            for component in missing.iter().rev() {
+                match component {
+                    Component::Normal(name) => resolved.push(name),
+                    Component::CurDir => {}
+                    // `..` after a missing directory is not resolved rather than guessed.
+                    _ => return None,
+                }
+            }
+            return Some(resolved);
+        }
+        missing.extend(existing.components().next_back());
+        existing = existing.parent()?;
+    }
+}
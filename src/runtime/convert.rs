@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::hash::BuildHasher;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use crate::frontend::lexer;
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::types::{Enum, RuntimeVal, Variant};
+
+// Conversion of a Rust value into a v-lang value. `#[derive(IntoValue)]` implements it
+// for structs, which become objects, and enums, which become variants.
+pub trait IntoValue {
+    fn into_value(self) -> RuntimeVal;
+}
+
+// Implemented by `#[derive(IntoValue)]` on enums: the v-lang enum their variants belong
+// to, which a host can declare as a global so scripts can create and match them.
+pub trait EnumValue {
+    fn enumeration() -> Rc<Enum>;
+}
+
+// Conversion of a v-lang value, typically a script's result, into a Rust value.
+// `#[derive(FromValue)]` implements it for structs and enums.
+pub trait FromValue: Sized {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError>;
+}
+
+// A value that does not have the shape the Rust side expects. `path` locates the value
+// inside the converted one, like `.items[2].price`, and is empty for the value itself.
+// `kind` is the error it raises in a script: a RangeError for numbers the Rust type can't
+// hold, a ValueError for strings that aren't one `char`, a TypeError otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    pub path: String,
+    pub expected: String,
+    pub found: String,
+    pub kind: ErrorKind,
+}
+
+impl ConversionError {
+    pub fn new(expected: &str, found: &RuntimeVal) -> Self {
+        ConversionError {
+            path: String::new(),
+            expected: expected.to_string(),
+            found: found.type_name().to_string(),
+            kind: ErrorKind::Type,
+        }
+    }
+
+    pub fn out_of_range(expected: &str, found: impl Display) -> Self {
+        ConversionError {
+            path: String::new(),
+            expected: expected.to_string(),
+            found: format!("out of range {}", found),
+            kind: ErrorKind::Range,
+        }
+    }
+
+    // Prefixes the path with the position of the failing value in its parent: `.field`
+    // or `[index]`.
+    pub fn within(mut self, segment: &str) -> Self {
+        self.path.insert_str(0, segment);
+        self
+    }
+
+    // The error a native function raises when its arguments don't convert.
+    pub fn into_error(self, env: &Environment) -> RuntimeError {
+        env.error(self.kind, self.to_string())
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path.trim_start_matches('.'))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl IntoValue for RuntimeVal {
+    fn into_value(self) -> RuntimeVal {
+        self
+    }
+}
+
+impl FromValue for RuntimeVal {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::Null
+    }
+}
+
+impl FromValue for () {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::Null => Ok(()),
+            other => Err(ConversionError::new("null", &other)),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::Bool(self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::Bool(b_val) => Ok(b_val),
+            other => Err(ConversionError::new("bool", &other)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::String(self.to_string())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::String(str) => Ok(str),
+            other => Err(ConversionError::new("string", &other)),
+        }
+    }
+}
+
+impl IntoValue for char {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::String(self.to_string())
+    }
+}
+
+// A string of exactly one character.
+impl FromValue for char {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::String(str) => {
+                let mut chars = str.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(char), None) => Ok(char),
+                    _ => Err(ConversionError {
+                        path: String::new(),
+                        expected: "char".to_string(),
+                        found: format!("string of length {}", str.chars().count()),
+                        kind: ErrorKind::Value,
+                    }),
+                }
+            }
+            other => Err(ConversionError::new("char", &other)),
+        }
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::Float(self)
+    }
+}
+
+// Integers are accepted where a float is expected, as in script arithmetic.
+impl FromValue for f64 {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::Float(num) => Ok(num),
+            RuntimeVal::Number(num) => Ok(num as f64),
+            other => Err(ConversionError::new("float", &other)),
+        }
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::Float(self as f64)
+    }
+}
+
+// Like `f64`, but finite floats beyond `f32::MAX` are rejected rather than rounded to
+// infinity.
+impl FromValue for f32 {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::Float(num) if num.is_finite() && num.abs() > f32::MAX as f64 => Err(
+                ConversionError::out_of_range("f32", format!("float {}", num)),
+            ),
+            RuntimeVal::Float(num) => Ok(num as f32),
+            RuntimeVal::Number(num) => Ok(num as f32),
+            other => Err(ConversionError::new("f32", &other)),
+        }
+    }
+}
+
+// Integer types convert through `isize`, the representation of script integers. Values
+// too large for a script integer become floats; values out of the target's range are
+// rejected.
+macro_rules! integer_conversions {
+    ($($int:ty),*) => {$(
+        impl IntoValue for $int {
+            fn into_value(self) -> RuntimeVal {
+                match isize::try_from(self) {
+                    Ok(num) => RuntimeVal::Number(num),
+                    Err(_) => RuntimeVal::Float(self as f64),
+                }
+            }
+        }
+
+        impl FromValue for $int {
+            fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+                match value {
+                    RuntimeVal::Number(num) => <$int>::try_from(num).map_err(|_| {
+                        ConversionError::out_of_range(stringify!($int), format!("int {}", num))
+                    }),
+                    other => Err(ConversionError::new(stringify!($int), &other)),
+                }
+            }
+        }
+    )*};
+}
+
+integer_conversions!(isize, i64, i32, i16, i8, usize, u64, u32, u16, u8);
+
+// `None` is null.
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> RuntimeVal {
+        match self {
+            Some(value) => value.into_value(),
+            None => RuntimeVal::Null,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeVal::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        array_elements(value, "array")?
+            .into_iter()
+            .enumerate()
+            .map(|(index, element)| {
+                T::from_value(element).map_err(|error| error.within(&format!("[{}]", index)))
+            })
+            .collect()
+    }
+}
+
+// Maps are objects; their entries keep the map's iteration order.
+impl<T: IntoValue, S> IntoValue for HashMap<String, T, S> {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::object(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+impl<T: FromValue, S: BuildHasher + Default> FromValue for HashMap<String, T, S> {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        object_entries(value)
+    }
+}
+
+impl<T: IntoValue> IntoValue for IndexMap<String, T> {
+    fn into_value(self) -> RuntimeVal {
+        RuntimeVal::object(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for IndexMap<String, T> {
+    fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+        object_entries(value)
+    }
+}
+
+fn object_entries<T: FromValue, M: FromIterator<(String, T)>>(
+    value: RuntimeVal,
+) -> Result<M, ConversionError> {
+    properties(value, "object")?
+        .into_iter()
+        .map(|(key, value)| {
+            let value = T::from_value(value).map_err(|error| error.within(&key_segment(&key)))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+// `.key` when the key could be written that way in a script, `["key"]` otherwise.
+fn key_segment(key: &str) -> String {
+    match lexer::is_identifier(key) {
+        true => format!(".{}", key),
+        false => format!("[{:?}]", key),
+    }
+}
+
+// Tuples are arrays of exactly their length.
+macro_rules! tuple_conversions {
+    ($(($($name:ident $index:tt),+))*) => {$(
+        impl<$($name: IntoValue),+> IntoValue for ($($name,)+) {
+            fn into_value(self) -> RuntimeVal {
+                RuntimeVal::array(vec![$(self.$index.into_value()),+])
+            }
+        }
+
+        impl<$($name: FromValue),+> FromValue for ($($name,)+) {
+            fn from_value(value: RuntimeVal) -> Result<Self, ConversionError> {
+                let length = [$($index),+].len();
+                let elements = array_elements(value, &format!("array of length {}", length))?;
+                check_length(&elements, length, "array")?;
+                Ok(($(element(&elements, $index)?,)+))
+            }
+        }
+    )*};
+}
+
+tuple_conversions! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+}
+
+// What follows supports the code generated by `#[derive(FromValue, IntoValue)]`.
+
+pub fn object(properties: Vec<(&str, RuntimeVal)>) -> RuntimeVal {
+    RuntimeVal::object(
+        properties
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+// The properties of an object, which `expected` names in errors.
+pub fn properties(
+    value: RuntimeVal,
+    expected: &str,
+) -> Result<IndexMap<String, RuntimeVal>, ConversionError> {
+    match value {
+        RuntimeVal::Object(object) => Ok(object.borrow().properties.clone()),
+        other => Err(ConversionError::new(expected, &other)),
+    }
+}
+
+// Converts the property `key`. A missing property converts like null, so optional
+// fields may be left out.
+pub fn field<T: FromValue>(
+    properties: &IndexMap<String, RuntimeVal>,
+    key: &str,
+) -> Result<T, ConversionError> {
+    let value = properties.get(key).cloned();
+    let missing = value.is_none();
+    T::from_value(value.unwrap_or(RuntimeVal::Null)).map_err(|mut error| {
+        if missing && error.path.is_empty() {
+            error.found = "nothing".to_string();
+        }
+        error.within(&key_segment(key))
+    })
+}
+
+pub fn array_elements(
+    value: RuntimeVal,
+    expected: &str,
+) -> Result<Vec<RuntimeVal>, ConversionError> {
+    match value {
+        RuntimeVal::Array(elements) => Ok(elements.borrow().clone()),
+        other => Err(ConversionError::new(expected, &other)),
+    }
+}
+
+pub fn check_length(
+    elements: &[RuntimeVal],
+    length: usize,
+    what: &str,
+) -> Result<(), ConversionError> {
+    if elements.len() == length {
+        return Ok(());
+    }
+    Err(ConversionError {
+        path: String::new(),
+        expected: format!("{} of length {}", what, length),
+        found: format!("{} of length {}", what, elements.len()),
+        kind: ErrorKind::Type,
+    })
+}
+
+pub fn element<T: FromValue>(elements: &[RuntimeVal], index: usize) -> Result<T, ConversionError> {
+    T::from_value(elements[index].clone()).map_err(|error| error.within(&format!("[{}]", index)))
+}
+
+// The field `name` of a variant, stored at `index` of its values.
+pub fn named_element<T: FromValue>(
+    elements: &[RuntimeVal],
+    index: usize,
+    name: &str,
+) -> Result<T, ConversionError> {
+    T::from_value(elements[index].clone()).map_err(|error| error.within(&key_segment(name)))
+}
+
+// An enum described by its name and each variant's field names. Derived enums build
+// theirs once, so scripts can match on variants converted from Rust.
+pub fn enumeration(name: &str, variants: &[(&str, &[&str])]) -> Rc<Enum> {
+    Rc::new(Enum {
+        name: name.to_string(),
+        variants: variants
+            .iter()
+            .map(|(variant, fields)| {
+                let fields = fields.iter().map(|field| field.to_string()).collect();
+                (variant.to_string(), fields)
+            })
+            .collect(),
+    })
+}
+
+pub fn variant(enumeration: Rc<Enum>, name: &str, values: Vec<RuntimeVal>) -> RuntimeVal {
+    RuntimeVal::Variant(Rc::new(Variant {
+        enumeration,
+        name: name.to_string(),
+        values,
+    }))
+}
+
+// The name and field values of a variant of an enum named `expected`. Variants are
+// recognised by name, whichever enum they come from; a string stands for a variant
+// without fields.
+pub fn variant_values(
+    value: RuntimeVal,
+    expected: &str,
+) -> Result<(String, Vec<RuntimeVal>), ConversionError> {
+    match value {
+        RuntimeVal::Variant(variant) => Ok((variant.name.clone(), variant.values.clone())),
+        RuntimeVal::String(name) => Ok((name, vec![])),
+        other => Err(ConversionError::new(expected, &other)),
+    }
+}
+
+pub fn unknown_variant(expected: &str, name: &str) -> ConversionError {
+    ConversionError {
+        path: String::new(),
+        expected: expected.to_string(),
+        found: format!("variant {}", name),
+        kind: ErrorKind::Type,
+    }
+}
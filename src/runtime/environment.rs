@@ -1,78 +1,500 @@
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
-use std::ops::Deref;
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
+use crate::frontend::span::Span;
+use crate::runtime::capabilities::Capabilities;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::limits::{allocation_size, read_up_to, Budget, Limits, Meter};
+use crate::runtime::module::{Module, ModuleLoader};
+use crate::runtime::native;
 use crate::runtime::types::RuntimeVal;
 
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 150;
+// Deeper stacks are grown on the heap by `interpreter::evaluate`, at tens of kilobytes per
+// call in debug builds; this keeps a runaway recursion within a few hundred megabytes.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+// What reading a field that does not exist does.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum MissingField {
+    // Raise a catchable ReferenceError.
+    #[default]
+    Error,
+    // Evaluate to null, as does reading any field of null.
+    Null,
+}
+
 #[derive(Debug, Clone)]
-pub struct Environment {
-    parent: Option<Box<Environment>>,
+pub struct Frame {
+    pub function: String,
+    pub call_site: Span,
+}
+
+#[derive(Debug)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    max_depth: usize,
+}
+
+impl CallStack {
+    fn new() -> Self {
+        CallStack {
+            frames: vec![],
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+
+    // Innermost frame first, ending with the top level program. Runs of identical frames,
+    // as left by deep recursion, are shown once with a count of the rest.
+    pub fn trace(&self) -> String {
+        let mut frames = self
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| format!("    at {} (called at {})", frame.function, frame.call_site))
+            .peekable();
+        let mut lines = vec![];
+        while let Some(line) = frames.next() {
+            let mut repeated = 0;
+            while frames.next_if_eq(&line).is_some() {
+                repeated += 1;
+            }
+            lines.push(line.clone());
+            match repeated {
+                0 => {}
+                1 => lines.push(line),
+                _ => lines.push(format!(
+                    "    ... previous frame repeated {} more times",
+                    repeated
+                )),
+            }
+        }
+        lines.push("    at <main>".to_string());
+        lines.join("\n")
+    }
+}
+
+#[derive(Default)]
+struct Scope {
+    parent: Option<Environment>,
     variables: HashMap<String, RuntimeVal>,
     constants: HashSet<String>,
 }
 
+// A handle to a scope. Cloning it shares the scope, which is how closures capture
+// the environment they were defined in. Every scope of one program shares a call stack.
+#[derive(Clone)]
+pub struct Environment {
+    scope: Rc<RefCell<Scope>>,
+    call_stack: Rc<RefCell<CallStack>>,
+    missing_field: Rc<Cell<MissingField>>,
+    module: Rc<Module>,
+    module_loader: Rc<RefCell<ModuleLoader>>,
+    budget: Rc<Budget>,
+    capabilities: Rc<Capabilities>,
+}
+
+impl Debug for Environment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scope = self.scope.borrow();
+        let mut names: Vec<&String> = scope.variables.keys().collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("variables", &names)
+            .field("parent", &scope.parent)
+            .finish()
+    }
+}
+
 impl Environment {
     pub fn new(parent: Option<Environment>) -> Self {
+        let (call_stack, missing_field, module, module_loader, budget, capabilities) = match &parent
+        {
+            Some(parent) => (
+                parent.call_stack.clone(),
+                parent.missing_field.clone(),
+                parent.module.clone(),
+                parent.module_loader.clone(),
+                parent.budget.clone(),
+                parent.capabilities.clone(),
+            ),
+            None => (
+                Rc::new(RefCell::new(CallStack::new())),
+                Rc::new(Cell::new(MissingField::default())),
+                Rc::new(Module::default()),
+                Rc::new(RefCell::new(ModuleLoader::default())),
+                Rc::new(Budget::default()),
+                Rc::new(Capabilities::default()),
+            ),
+        };
         Environment {
-            parent: parent.map(Box::new),
-            variables: HashMap::new(),
-            constants: HashSet::new(),
+            scope: Rc::new(RefCell::new(Scope {
+                parent,
+                ..Scope::default()
+            })),
+            call_stack,
+            missing_field,
+            module,
+            module_loader,
+            budget,
+            capabilities,
+        }
+    }
+
+    // A fresh global scope for the module at `path`, sharing this program's call stack,
+    // settings, capabilities and module cache.
+    pub(crate) fn module_env(&self, path: PathBuf) -> Environment {
+        let mut environment = global_env_with(self.capabilities.as_ref().clone());
+        environment.call_stack = self.call_stack.clone();
+        environment.missing_field = self.missing_field.clone();
+        environment.module_loader = self.module_loader.clone();
+        environment.budget = self.budget.clone();
+        environment.module = Rc::new(Module::new(Some(path)));
+        if let Some(args) = self.root().scope.borrow().variables.get("args") {
+            environment.root_variable("args", args.clone());
+        }
+        environment
+    }
+
+    // Sets the `args` global, seen by this program and every module it imports, to the
+    // arguments the script was started with.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        let args = RuntimeVal::array(args.into_iter().map(RuntimeVal::String).collect());
+        self.root_variable("args", args);
+    }
+
+    // Replaces a global of the root scope, even a constant one.
+    fn root_variable(&self, variable: &str, value: RuntimeVal) {
+        let root = self.root();
+        let mut scope = root.scope.borrow_mut();
+        scope.variables.insert(variable.to_string(), value);
+    }
+
+    // The names declared in the root scope, and whether each is a constant.
+    pub fn globals(&self) -> HashMap<String, bool> {
+        let root = self.root();
+        let scope = root.scope.borrow();
+        scope
+            .variables
+            .keys()
+            .map(|name| (name.clone(), scope.constants.contains(name)))
+            .collect()
+    }
+
+    fn root(&self) -> Environment {
+        let mut environment = self.clone();
+        loop {
+            let parent = environment.scope.borrow().parent.clone();
+            match parent {
+                Some(parent) => environment = parent,
+                None => return environment,
+            }
         }
     }
 
+    // Marks a root scope as the top level of the file at `path`, so its imports resolve
+    // relative to that file.
+    pub fn set_module_path(&mut self, path: PathBuf) {
+        self.module = Rc::new(Module::new(Some(path)));
+    }
+
+    pub(crate) fn module(&self) -> Rc<Module> {
+        self.module.clone()
+    }
+
+    pub(crate) fn module_loader(&self) -> Rc<RefCell<ModuleLoader>> {
+        self.module_loader.clone()
+    }
+
     pub(crate) fn declare_variable(
         &mut self,
         variable: &str,
         value: RuntimeVal,
         constant: bool,
-    ) -> RuntimeVal {
-        if self.variables.contains_key(variable.deref()) {
-            panic!("variable is already defined; {} = {:?}", variable, value)
+    ) -> Result<RuntimeVal, RuntimeError> {
+        let mut scope = self.scope.borrow_mut();
+        if scope.variables.contains_key(variable) {
+            return Err(self.error(
+                ErrorKind::Reference,
+                format!(
+                    "variable is already defined; {} = {}",
+                    variable,
+                    value.preview()
+                ),
+            ));
         }
         if constant {
-            self.constants.insert(variable.to_string());
+            scope.constants.insert(variable.to_string());
+        }
+
+        scope.variables.insert(variable.to_string(), value.clone());
+        Ok(value)
+    }
+
+    pub(crate) fn assign_variable(
+        &mut self,
+        variable: &str,
+        value: RuntimeVal,
+    ) -> Result<RuntimeVal, RuntimeError> {
+        let environment = self.assignable(variable)?;
+        let mut scope = environment.scope.borrow_mut();
+        scope.variables.insert(variable.to_string(), value.clone());
+        Ok(value)
+    }
+
+    // The scope declaring `variable`, unless it is a constant. Updates like `x ??= y` check
+    // this up front, as they fail on a constant even when they would leave it unchanged.
+    pub(crate) fn assignable(&self, variable: &str) -> Result<Environment, RuntimeError> {
+        let environment = self.resolve(variable)?;
+        if environment.scope.borrow().constants.contains(variable) {
+            return Err(self.error(
+                ErrorKind::Type,
+                format!(
+                    "Cannot reassign variable {} as it is already defined as a constant",
+                    variable
+                ),
+            ));
         }
+        Ok(environment)
+    }
+
+    pub(crate) fn lookup_variable(&mut self, variable: &str) -> Result<RuntimeVal, RuntimeError> {
+        let environment = self.resolve(variable)?;
+        let scope = environment.scope.borrow();
+        Ok(scope.variables.get(variable).cloned().unwrap())
+    }
 
-        self.variables.insert(variable.to_string(), value.clone());
-        value
+    // Runs `body` inside a fresh child scope; declarations made there are dropped afterwards.
+    pub(crate) fn scoped<T>(&mut self, body: impl FnOnce(&mut Environment) -> T) -> T {
+        let mut scope = Environment::new(Some(self.clone()));
+        body(&mut scope)
     }
 
-    pub(crate) fn assign_variable(&mut self, variable: &str, value: RuntimeVal) -> RuntimeVal {
-        let environment = self.resolve(variable);
-        let is_constant = environment.constants.contains(variable);
-        if is_constant {
-            panic!(
-                "Cannot reassign variable {} as it is already defined as a constant",
-                variable
+    pub(crate) fn push_frame(&self, function: String, call_site: Span) -> Result<(), RuntimeError> {
+        let mut call_stack = self.call_stack.borrow_mut();
+        self.budget
+            .check_call_depth(call_stack.frames.len())
+            .map_err(RuntimeError::LimitExceeded)?;
+        if call_stack.frames.len() >= call_stack.max_depth {
+            let message = format!(
+                "stack overflow: maximum call depth of {} exceeded calling {}",
+                call_stack.max_depth, function
             );
+            drop(call_stack);
+            return Err(self.error(ErrorKind::StackOverflow, message));
         }
-        environment
-            .variables
-            .insert(variable.to_string(), value.clone());
-        value
+        call_stack.frames.push(Frame {
+            function,
+            call_site,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn pop_frame(&self) {
+        self.call_stack.borrow_mut().frames.pop();
+    }
+
+    // Where the innermost running function was called from; natives calling back into
+    // scripts report this as the call site of the callback.
+    pub(crate) fn call_site(&self) -> Span {
+        match self.call_stack.borrow().frames.last() {
+            Some(frame) => frame.call_site,
+            None => Span::default(),
+        }
+    }
+
+    pub fn set_max_call_depth(&self, max_depth: usize) -> Result<(), String> {
+        if max_depth > MAX_CALL_DEPTH {
+            return Err(format!(
+                "maximum call depth must be at most {}, found {}",
+                MAX_CALL_DEPTH, max_depth
+            ));
+        }
+        self.call_stack.borrow_mut().max_depth = max_depth;
+        Ok(())
+    }
+
+    pub fn set_missing_field(&self, policy: MissingField) {
+        self.missing_field.set(policy);
+    }
+
+    pub(crate) fn missing_field(&self) -> MissingField {
+        self.missing_field.get()
+    }
+
+    // Limits apply to every scope and module of the program and start counting anew.
+    pub fn set_limits(&self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.budget.limits()
+    }
+
+    // Starts a new run against the limits, as each repl line or `Interpreter::eval` does.
+    pub fn reset_usage(&self) {
+        self.budget.reset();
+    }
+
+    pub(crate) fn step(&self) -> Result<(), RuntimeError> {
+        self.budget.step().map_err(RuntimeError::LimitExceeded)
+    }
+
+    pub(crate) fn check_time(&self) -> Result<(), RuntimeError> {
+        self.budget
+            .check_time()
+            .map_err(RuntimeError::LimitExceeded)
+    }
+
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.budget.deadline()
+    }
+
+    // Evaluates `body` one node deeper, against the nesting depth limit.
+    pub(crate) fn nested<T>(
+        &mut self,
+        body: impl FnOnce(&mut Environment) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        self.budget.enter().map_err(RuntimeError::LimitExceeded)?;
+        let result = body(self);
+        self.budget.leave();
+        result
+    }
+
+    // Charges `bytes` about to be allocated against the heap limit.
+    pub(crate) fn allocate(&self, bytes: usize) -> Result<(), RuntimeError> {
+        self.budget
+            .allocate(bytes)
+            .map_err(RuntimeError::LimitExceeded)
     }
 
-    pub(crate) fn lookup_variable(&mut self, variable: &str) -> Option<RuntimeVal> {
-        let environment = self.resolve(variable);
-        environment.variables.get(variable).cloned()
+    // Fails if `bytes` more would exceed the heap limit; for natives to call before building
+    // a large value, which is charged once returned.
+    pub(crate) fn check_allocation(&self, bytes: usize) -> Result<(), RuntimeError> {
+        self.budget
+            .check_allocation(bytes)
+            .map_err(RuntimeError::LimitExceeded)
     }
 
-    fn resolve(&mut self, variable: &str) -> &mut Environment {
-        if self.variables.contains_key(variable) {
-            return self;
+    // Reads all of `reader`, failing once more has been read than fits in the heap limit so
+    // that natives never buffer unbounded input.
+    pub(crate) fn read_limited(
+        &self,
+        reader: impl Read,
+    ) -> Result<io::Result<Vec<u8>>, RuntimeError> {
+        let mut bytes = vec![];
+        let result = read_up_to(reader, self.budget.heap_left(), &mut bytes);
+        self.check_allocation(bytes.len())?;
+        Ok(result.map(|_| bytes))
+    }
+
+    // The number of bytes natives may buffer before the heap limit stops them.
+    pub(crate) fn heap_left(&self) -> Option<usize> {
+        self.budget.heap_left()
+    }
+
+    pub(crate) fn meter(&self) -> Meter<'_> {
+        Meter::new(&self.budget)
+    }
+
+    // Charges a value that was just created, returning it.
+    pub(crate) fn allocated(&self, value: RuntimeVal) -> Result<RuntimeVal, RuntimeError> {
+        self.allocate(allocation_size(&value))?;
+        Ok(value)
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    // Each check raises a PermissionError unless the program's capabilities allow the
+    // operation; native functions doing I/O call them first. `action` describes the
+    // operation in the message, as in "cannot read <path>".
+    pub fn check_read(&self, path: &Path, action: &str) -> Result<(), RuntimeError> {
+        self.permit(self.capabilities.can_read(path), action, path.display())
+    }
+
+    pub fn check_write(&self, path: &Path, action: &str) -> Result<(), RuntimeError> {
+        self.permit(self.capabilities.can_write(path), action, path.display())
+    }
+
+    pub fn check_env_var(&self, name: &str) -> Result<(), RuntimeError> {
+        self.permit(
+            self.capabilities.can_read_env_var(name),
+            "cannot read environment variable",
+            name,
+        )
+    }
+
+    pub fn check_run(&self, command: &str) -> Result<(), RuntimeError> {
+        self.permit(self.capabilities.can_run(command), "cannot run", command)
+    }
+
+    pub fn check_stdin(&self) -> Result<(), RuntimeError> {
+        self.permit(self.capabilities.stdin, "cannot read", "stdin")
+    }
+
+    fn permit(
+        &self,
+        allowed: bool,
+        action: &str,
+        target: impl Display,
+    ) -> Result<(), RuntimeError> {
+        if allowed {
+            return Ok(());
         }
-        if self.parent.is_some() {
-            let parent = self.parent.as_mut().unwrap();
-            return parent.as_mut().resolve(variable);
+        Err(self.error(
+            ErrorKind::Permission,
+            format!("permission denied: {} {}", action, target),
+        ))
+    }
+
+    pub(crate) fn error(&self, kind: ErrorKind, message: String) -> RuntimeError {
+        RuntimeError::new(kind, message, self.call_stack.borrow().trace())
+    }
+
+    fn resolve(&self, variable: &str) -> Result<Environment, RuntimeError> {
+        let mut environment = self.clone();
+        loop {
+            if environment.scope.borrow().variables.contains_key(variable) {
+                return Ok(environment);
+            }
+            let parent = environment.scope.borrow().parent.clone();
+            match parent {
+                Some(parent) => environment = parent,
+                None => {
+                    return Err(self.error(
+                        ErrorKind::Reference,
+                        format!("{} variable is not defined", variable),
+                    ))
+                }
+            }
         }
-        panic!("{} variable is not defined", variable);
     }
 }
 
+// The global environment of a program with unrestricted I/O, as run from the command line.
 pub fn global_env() -> Environment {
+    global_env_with(Capabilities::all())
+}
+
+pub fn global_env_with(capabilities: Capabilities) -> Environment {
     let mut environment = Environment::new(None);
-    environment.declare_variable("null", RuntimeVal::Null, true);
-    environment.declare_variable("true", RuntimeVal::Bool(true), true);
-    environment.declare_variable("false", RuntimeVal::Bool(false), true);
+    environment.capabilities = Rc::new(capabilities);
+    for (name, value) in [
+        ("null", RuntimeVal::Null),
+        ("true", RuntimeVal::Bool(true)),
+        ("false", RuntimeVal::Bool(false)),
+        ("args", RuntimeVal::array(vec![])),
+    ] {
+        environment.declare_variable(name, value, true).unwrap();
+    }
+    native::declare_globals(&mut environment);
     environment
 }
-
@@ -0,0 +1,103 @@
+use indexmap::IndexMap;
+use std::fmt::{Display, Formatter};
+
+use crate::runtime::limits::Limit;
+use crate::runtime::types::RuntimeVal;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorKind {
+    Reference,
+    Type,
+    Arithmetic,
+    StackOverflow,
+    Match,
+    Range,
+    Import,
+    Value,
+    Syntax,
+    Permission,
+    Io,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Reference => "ReferenceError",
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Arithmetic => "ArithmeticError",
+            ErrorKind::StackOverflow => "StackOverflowError",
+            ErrorKind::Match => "MatchError",
+            ErrorKind::Range => "RangeError",
+            ErrorKind::Import => "ImportError",
+            ErrorKind::Value => "ValueError",
+            ErrorKind::Syntax => "SyntaxError",
+            ErrorKind::Permission => "PermissionError",
+            ErrorKind::Io => "IOError",
+        }
+    }
+}
+
+// What stopped a run. `eval` only ever returns `Thrown`, `LimitExceeded` or `Exit`; the
+// other variants carry control flow inside the interpreter.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum RuntimeError {
+    // Any value raised with `throw` or by a failing builtin. Catchable with try/catch.
+    Thrown(RuntimeVal),
+    // Unwinds the current function call; never escapes `call_function`.
+    Return(RuntimeVal),
+    // Stops an optional chain at a null `?.`; never escapes the chain.
+    ShortCircuit,
+    // A resource limit was reached. Unlike thrown errors it cannot be caught, so it always
+    // ends the run.
+    LimitExceeded(Limit),
+    // `exit(code)` was called. Uncatchable too; the command line runner exits the process
+    // with `code`, while embedders get it back from `eval`.
+    Exit(i32),
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: String, stack: String) -> Self {
+        RuntimeError::Thrown(RuntimeVal::object(IndexMap::from([
+            (
+                "kind".to_string(),
+                RuntimeVal::String(kind.name().to_string()),
+            ),
+            ("message".to_string(), RuntimeVal::String(message)),
+            ("stack".to_string(), RuntimeVal::String(stack)),
+        ])))
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::Thrown(RuntimeVal::Object(object)) => {
+                let map = &object.borrow().properties;
+                match (map.get("kind"), map.get("message"), map.get("stack")) {
+                    (Some(kind), Some(message), Some(stack)) => {
+                        write!(f, "Uncaught {}: {}\n{}", kind, message, stack)
+                    }
+                    (Some(kind), Some(message), None) => {
+                        write!(f, "Uncaught {}: {}", kind, message)
+                    }
+                    _ => write!(f, "Uncaught {}", RuntimeVal::Object(object.clone())),
+                }
+            }
+            RuntimeError::Thrown(value) => write!(f, "Uncaught {}", value),
+            RuntimeError::Return(_) => write!(f, "Uncaught return statement outside of function"),
+            RuntimeError::ShortCircuit => write!(f, "Uncaught ?. outside of an optional chain"),
+            RuntimeError::LimitExceeded(limit) => write!(f, "LimitExceeded: {}", limit),
+            RuntimeError::Exit(code) => write!(f, "exited with status {}", code),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RuntimeError::LimitExceeded(limit) => Some(limit),
+            _ => None,
+        }
+    }
+}
@@ -1,76 +1,443 @@
- use std::collections::HashMap;
+use indexmap::IndexMap;
 
-use RuntimeVal::{Null, Number};
+use RuntimeVal::{Bool, Null, Number};
 
-use crate::frontend::ast::Ast;
-use crate::runtime::environment::Environment;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use crate::frontend::ast::{Ast, Property};
+use crate::frontend::span::Span;
+use crate::runtime::environment::{Environment, MissingField};
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::eval::patterns::bind_pattern;
+use crate::runtime::eval::statements::evaluate_program;
 use crate::runtime::interpreter::evaluate;
-use crate::runtime::types::RuntimeVal;
+use crate::runtime::native::{array, native_function, string};
+use crate::runtime::types::{BoundMethod, Class, Enum, Function, Object, RuntimeVal, Variant};
 
 pub fn evaluate_object_literal(
     env: &mut Environment,
-    properties: Vec<(String, Option<Box<Ast>>)>,
-) -> RuntimeVal {
-    let mut objects: HashMap<String, RuntimeVal> = HashMap::new();
-    for (key, value) in properties {
-        let variable_name = key.clone();
-        let val = evaluate(env, Ast::PropertyLiteral { key, value });
-        objects.insert(variable_name, val);
+    properties: Vec<Property>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let mut objects: IndexMap<String, RuntimeVal> = IndexMap::new();
+    for property in properties {
+        match property {
+            Property::Named(key, value) => {
+                let variable_name = key.clone();
+                let val = evaluate(env, Ast::PropertyLiteral { key, value })?;
+                objects.insert(variable_name, val);
+            }
+            Property::Computed(key, value) => {
+                let key = match evaluate(env, key)? {
+                    RuntimeVal::String(key) => key,
+                    key @ (Number(_) | Bool(_) | Null) => key.to_string(),
+                    other => {
+                        return Err(env.error(
+                            ErrorKind::Type,
+                            format!("{} cannot be used as an object key", other.preview()),
+                        ))
+                    }
+                };
+                let val = evaluate(env, value)?;
+                objects.insert(key, val);
+            }
+            Property::Method(key, function) => {
+                let function = match function {
+                    Ast::FunctionDeclaration {
+                        parameters, body, ..
+                    } => Function {
+                        name: key.clone(),
+                        parameters,
+                        body,
+                        closure: env.clone(),
+                        method: true,
+                    },
+                    _ => panic!("never executed branch. method body expected"),
+                };
+                objects.insert(key, RuntimeVal::Function(Rc::new(function)));
+            }
+            // Later properties overwrite earlier ones, so spreads merge in source order.
+            Property::Spread(source) => match evaluate(env, source)? {
+                RuntimeVal::Object(source) => {
+                    let source = source.borrow();
+                    for (key, value) in source.properties.iter() {
+                        objects.insert(key.clone(), value.clone());
+                    }
+                }
+                Null => {}
+                other => {
+                    return Err(env.error(
+                        ErrorKind::Type,
+                        format!("cannot spread {} into an object", other.preview()),
+                    ))
+                }
+            },
+        }
     }
 
-    RuntimeVal::Object(objects)
+    Ok(RuntimeVal::object(objects))
 }
 
 pub fn evaluate_property_literal(
     env: &mut Environment,
     key: String,
     value: Option<Box<Ast>>,
-) -> RuntimeVal {
+) -> Result<RuntimeVal, RuntimeError> {
     match value {
         None => evaluate_identifier(env, key),
         Some(exp) => evaluate(env, *exp),
     }
 }
 
+pub fn evaluate_array_literal(
+    env: &mut Environment,
+    elements: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
+    Ok(RuntimeVal::array(evaluate_elements(env, elements)?))
+}
+
+// Evaluates array elements or call arguments, expanding `...array` in place.
+fn evaluate_elements(
+    env: &mut Environment,
+    elements: Vec<Ast>,
+) -> Result<Vec<RuntimeVal>, RuntimeError> {
+    let mut values = Vec::with_capacity(elements.len());
+    for element in elements {
+        match element {
+            Ast::SpreadElement { argument } => match evaluate(env, *argument)? {
+                RuntimeVal::Array(elements) => values.extend(elements.borrow().iter().cloned()),
+                other => {
+                    return Err(env.error(
+                        ErrorKind::Type,
+                        format!(
+                            "cannot spread {}; only arrays can be spread",
+                            other.preview()
+                        ),
+                    ))
+                }
+            },
+            element => values.push(evaluate(env, element)?),
+        }
+    }
+    Ok(values)
+}
+
+pub fn evaluate_spread_element(env: &mut Environment) -> Result<RuntimeVal, RuntimeError> {
+    Err(env.error(
+        ErrorKind::Type,
+        "spread is only allowed in array literals, object literals and call arguments".to_string(),
+    ))
+}
+
+// Joins the parts using their `Display` form, so strings appear without quotes.
+pub fn evaluate_template_literal(
+    env: &mut Environment,
+    parts: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let mut result = String::new();
+    for part in parts {
+        let value = evaluate(env, part)?;
+        result.push_str(value.display(env)?.as_str());
+        env.check_allocation(result.len())?;
+    }
+    Ok(RuntimeVal::String(result))
+}
+
 pub fn evaluate_binary_expression(
     env: &mut Environment,
     left: Ast,
     right: Ast,
-    operator: char,
-) -> RuntimeVal {
-    let left_val = evaluate(env, left);
-    let right_val = evaluate(env, right);
+    operator: String,
+) -> Result<RuntimeVal, RuntimeError> {
+    let left_val = evaluate(env, left)?;
+    // `&&`, `||` and `??` short circuit and evaluate to one of their operands.
+    match operator.as_str() {
+        "&&" if !left_val.is_truthy() => return Ok(left_val),
+        "||" if left_val.is_truthy() => return Ok(left_val),
+        "??" if left_val != Null => return Ok(left_val),
+        "&&" | "||" | "??" => return evaluate(env, right),
+        _ => {}
+    }
+    let right_val = evaluate(env, right)?;
+    apply_binary_operator(env, left_val, right_val, operator.as_str())
+}
+
+fn apply_binary_operator(
+    env: &mut Environment,
+    left_val: RuntimeVal,
+    right_val: RuntimeVal,
+    operator: &str,
+) -> Result<RuntimeVal, RuntimeError> {
+    if let Some((num1, num2)) = float_operands(&left_val, &right_val) {
+        return apply_float_operator(env, num1, num2, operator);
+    }
+    let result = match (&left_val, &right_val, operator) {
+        (_, _, "==") => return Ok(Bool(left_val == right_val)),
+        (_, _, "!=") => return Ok(Bool(left_val != right_val)),
+        (Number(num1), Number(num2), "<" | ">" | "<=" | ">=") => {
+            return Ok(Bool(compare(num1.cmp(num2), operator)))
+        }
+        (RuntimeVal::String(str1), RuntimeVal::String(str2), "<" | ">" | "<=" | ">=") => {
+            return Ok(Bool(compare(str1.cmp(str2), operator)))
+        }
+        (Number(num1), Number(num2), "/" | "%") if *num2 == 0 => {
+            return Err(env.error(
+                ErrorKind::Arithmetic,
+                format!("division by zero in {} {} {}", num1, operator, num2),
+            ))
+        }
+        (Number(num1), Number(num2), "+") => num1.checked_add(*num2),
+        (Number(num1), Number(num2), "-") => num1.checked_sub(*num2),
+        (Number(num1), Number(num2), "*") => num1.checked_mul(*num2),
+        (Number(num1), Number(num2), "/") => num1.checked_div(*num2),
+        (Number(num1), Number(num2), "%") => num1.checked_rem(*num2),
+        (RuntimeVal::String(_), _, "+") | (_, RuntimeVal::String(_), "+") => {
+            let joined = left_val.display(env)? + right_val.display(env)?.as_str();
+            return Ok(RuntimeVal::String(joined));
+        }
+        _ => {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!(
+                    "unsupported operands for {}: {} and {}",
+                    operator,
+                    left_val.preview(),
+                    right_val.preview()
+                ),
+            ))
+        }
+    };
+    result.map(Number).ok_or_else(|| {
+        env.error(
+            ErrorKind::Arithmetic,
+            format!(
+                "integer overflow in {} {} {}",
+                left_val, operator, right_val
+            ),
+        )
+    })
+}
+
+// Arithmetic involving a float is done in floating point.
+fn float_operands(left: &RuntimeVal, right: &RuntimeVal) -> Option<(f64, f64)> {
+    match (left, right) {
+        (RuntimeVal::Float(num1), RuntimeVal::Float(num2)) => Some((*num1, *num2)),
+        (RuntimeVal::Float(num1), Number(num2)) => Some((*num1, *num2 as f64)),
+        (Number(num1), RuntimeVal::Float(num2)) => Some((*num1 as f64, *num2)),
+        _ => None,
+    }
+}
+
+fn apply_float_operator(
+    env: &mut Environment,
+    num1: f64,
+    num2: f64,
+    operator: &str,
+) -> Result<RuntimeVal, RuntimeError> {
+    let result = match operator {
+        "==" => return Ok(Bool(num1 == num2)),
+        "!=" => return Ok(Bool(num1 != num2)),
+        "<" | ">" | "<=" | ">=" => {
+            let ordering = num1.partial_cmp(&num2);
+            return Ok(Bool(
+                ordering.is_some_and(|ordering| compare(ordering, operator)),
+            ));
+        }
+        "/" | "%" if num2 == 0.0 => {
+            return Err(env.error(
+                ErrorKind::Arithmetic,
+                format!("division by zero in {:?} {} {:?}", num1, operator, num2),
+            ))
+        }
+        "+" => num1 + num2,
+        "-" => num1 - num2,
+        "*" => num1 * num2,
+        "/" => num1 / num2,
+        "%" => num1 % num2,
+        _ => {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!(
+                    "unsupported operands for {}: {:?} and {:?}",
+                    operator, num1, num2
+                ),
+            ))
+        }
+    };
+    Ok(RuntimeVal::Float(result))
+}
 
-    match (left_val, right_val, operator) {
-        (Number(num1), Number(num2), '+') => Number(num1 + num2),
-        (Number(num1), Number(num2), '-') => Number(num1 - num2),
-        (Number(num1), Number(num2), '*') => Number(num1 * num2),
-        (Number(num1), Number(num2), '/') => Number(num1 / num2),
-        (Number(num1), Number(num2), '%') => Number(num1 % num2),
-        _ => Null,
+fn compare(ordering: Ordering, operator: &str) -> bool {
+    match operator {
+        "<" => ordering == Ordering::Less,
+        ">" => ordering == Ordering::Greater,
+        "<=" => ordering != Ordering::Greater,
+        _ => ordering != Ordering::Less,
     }
 }
 
-pub fn evaluate_identifier(env: &mut Environment, var: String) -> RuntimeVal {
-    env.lookup_variable(var.as_str()).unwrap()
+pub fn evaluate_unary_expression(
+    env: &mut Environment,
+    operator: String,
+    argument: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    let value = evaluate(env, argument)?;
+    match (operator.as_str(), &value) {
+        ("!", _) => Ok(Bool(!value.is_truthy())),
+        ("-", RuntimeVal::Float(num)) => Ok(RuntimeVal::Float(-num)),
+        ("-", Number(num)) => num.checked_neg().map(Number).ok_or_else(|| {
+            env.error(
+                ErrorKind::Arithmetic,
+                format!("integer overflow in -{}", num),
+            )
+        }),
+        _ => Err(env.error(
+            ErrorKind::Type,
+            format!("unsupported operand for {}: {}", operator, value.preview()),
+        )),
+    }
+}
+
+pub fn evaluate_identifier(env: &mut Environment, var: String) -> Result<RuntimeVal, RuntimeError> {
+    env.lookup_variable(var.as_str())
 }
 
 pub fn evaluate_assignment_expression(
     env: &mut Environment,
     assignee: Ast,
     value: Ast,
-) -> RuntimeVal {
-    let variable: String = match assignee {
-        Ast::Identifier(v) => v,
-        _ => {
-            panic!(
+) -> Result<RuntimeVal, RuntimeError> {
+    match assignee {
+        Ast::Identifier(variable, _) => {
+            let runtime_val = evaluate(env, value)?;
+            env.assign_variable(variable.as_str(), runtime_val)
+        }
+        Ast::MemberExpr {
+            object,
+            property,
+            computed,
+            ..
+        } => {
+            let object = evaluate(env, *object)?;
+            let key = evaluate_member_key(env, *property, computed)?;
+            let runtime_val = evaluate(env, value)?;
+            set_member(env, object, key, runtime_val)
+        }
+        _ => Err(env.error(
+            ErrorKind::Type,
+            format!(
                 "invalid left hand side in assignment operation found {:?}",
                 assignee
-            )
+            ),
+        )),
+    }
+}
+
+pub fn evaluate_compound_assignment_expression(
+    env: &mut Environment,
+    assignee: Ast,
+    operator: String,
+    value: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    // `a ??= b` only evaluates `b`, and only assigns, when `a` is null or missing.
+    let nullish = operator == "??";
+    let (_, updated) = update_target(env, assignee, nullish, |env, current| {
+        if nullish {
+            return match current {
+                Null => evaluate(env, value).map(Some),
+                _ => Ok(None),
+            };
         }
-    };
-    let runtime_val = evaluate(env, value);
-    env.assign_variable(variable.as_str(), runtime_val)
+        let value = evaluate(env, value)?;
+        apply_binary_operator(env, current, value, operator.as_str()).map(Some)
+    })?;
+    Ok(updated)
+}
+
+pub fn evaluate_update_expression(
+    env: &mut Environment,
+    operator: String,
+    argument: Ast,
+    prefix: bool,
+) -> Result<RuntimeVal, RuntimeError> {
+    let (previous, updated) = update_target(env, argument, false, |env, current| match current {
+        Number(_) | RuntimeVal::Float(_) => {
+            let operator = &operator[..1];
+            apply_binary_operator(env, current, Number(1), operator).map(Some)
+        }
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("unsupported operand for {}: {}", operator, other.preview()),
+        )),
+    })?;
+    Ok(if prefix { updated } else { previous })
+}
+
+// Reads an assignment target, lets `update` compute its replacement and stores it,
+// returning the previous and the final value. `update` returns `None` to leave the
+// target untouched. Member targets evaluate their object and key only once; with
+// `missing_as_null` a missing object field reads as null instead of failing.
+fn update_target(
+    env: &mut Environment,
+    target: Ast,
+    missing_as_null: bool,
+    update: impl FnOnce(&mut Environment, RuntimeVal) -> Result<Option<RuntimeVal>, RuntimeError>,
+) -> Result<(RuntimeVal, RuntimeVal), RuntimeError> {
+    match target {
+        Ast::Identifier(variable, _) => {
+            env.assignable(variable.as_str())?;
+            let current = env.lookup_variable(variable.as_str())?;
+            match update(env, current.clone())? {
+                Some(value) => Ok((current, env.assign_variable(variable.as_str(), value)?)),
+                None => Ok((current.clone(), current)),
+            }
+        }
+        Ast::MemberExpr {
+            object,
+            property,
+            computed,
+            ..
+        } => {
+            let object = evaluate(env, *object)?;
+            let key = evaluate_member_key(env, *property, computed)?;
+            let current = match get_member(env, object.clone(), key.clone()) {
+                Err(_)
+                    if missing_as_null
+                        && matches!(
+                            (&object, &key),
+                            (RuntimeVal::Object(_), RuntimeVal::String(_))
+                        ) =>
+                {
+                    Null
+                }
+                result => result?,
+            };
+            match update(env, current.clone())? {
+                Some(value) => Ok((current, set_member(env, object, key, value)?)),
+                None => Ok((current.clone(), current)),
+            }
+        }
+        _ => Err(env.error(
+            ErrorKind::Type,
+            format!(
+                "invalid left hand side in assignment operation found {:?}",
+                target
+            ),
+        )),
+    }
+}
+
+fn evaluate_member_key(
+    env: &mut Environment,
+    property: Ast,
+    computed: bool,
+) -> Result<RuntimeVal, RuntimeError> {
+    match (computed, property) {
+        (false, Ast::Identifier(member, _)) => Ok(RuntimeVal::String(member)),
+        (_, property) => evaluate(env, property),
+    }
 }
 
 pub(crate) fn evaluate_member_expression(
@@ -78,32 +445,390 @@ pub(crate) fn evaluate_member_expression(
     object: Ast,
     property: Ast,
     computed: bool,
-) -> RuntimeVal {
-    if computed | matches!(object, Ast::MemberExpr { .. }) {
-        let obj = evaluate(env, object);
-        if let (RuntimeVal::Object(map), Ast::Identifier(var)) = (obj, property) {
-            if !map.contains_key(var.as_str()) {
-                panic!("{} field not found in object", var)
+) -> Result<RuntimeVal, RuntimeError> {
+    if let Ast::Identifier(name, _) = &object {
+        if name == "super" {
+            let key = evaluate_member_key(env, property, computed)?;
+            return evaluate_super_method(env, key);
+        }
+    }
+    // A `?.` access finds null where a field is missing, whatever the global policy.
+    let missing_field = match object {
+        Ast::NullGuard { .. } => MissingField::Null,
+        _ => env.missing_field(),
+    };
+    let obj = evaluate(env, object)?;
+    let key = evaluate_member_key(env, property, computed)?;
+    read_member(env, obj, key, missing_field)
+}
+
+pub fn evaluate_optional_chain(
+    env: &mut Environment,
+    expression: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    match evaluate(env, expression) {
+        Err(RuntimeError::ShortCircuit) => Ok(Null),
+        result => result,
+    }
+}
+
+pub fn evaluate_null_guard(
+    env: &mut Environment,
+    argument: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    match evaluate(env, argument)? {
+        Null => Err(RuntimeError::ShortCircuit),
+        value => Ok(value),
+    }
+}
+
+pub(crate) fn get_member(
+    env: &mut Environment,
+    obj: RuntimeVal,
+    key: RuntimeVal,
+) -> Result<RuntimeVal, RuntimeError> {
+    let missing_field = env.missing_field();
+    read_member(env, obj, key, missing_field)
+}
+
+fn read_member(
+    env: &mut Environment,
+    obj: RuntimeVal,
+    key: RuntimeVal,
+    missing_field: MissingField,
+) -> Result<RuntimeVal, RuntimeError> {
+    match (obj, key) {
+        (RuntimeVal::Object(object), RuntimeVal::String(key)) => {
+            let method = {
+                let object_ref = object.borrow();
+                match object_ref.properties.get(key.as_str()) {
+                    Some(RuntimeVal::Function(method)) if method.method => {
+                        let method = method.clone();
+                        drop(object_ref);
+                        return Ok(bind_method(RuntimeVal::Object(object), method));
+                    }
+                    Some(value) => return Ok(value.clone()),
+                    None => {}
+                }
+                object_ref
+                    .class
+                    .as_ref()
+                    .and_then(|class| class.find_method(key.as_str()))
+            };
+            match method {
+                Some(method) => Ok(bind_method(RuntimeVal::Object(object), method)),
+                None if missing_field == MissingField::Null => Ok(Null),
+                None => Err(env.error(
+                    ErrorKind::Reference,
+                    format!("{} field not found in object", key),
+                )),
+            }
+        }
+        (RuntimeVal::Array(elements), Number(index)) => {
+            let elements = elements.borrow();
+            match array_index(index, elements.len()) {
+                Some(index) => Ok(elements[index].clone()),
+                None => Err(env.error(
+                    ErrorKind::Range,
+                    format!(
+                        "index {} out of bounds for array of length {}",
+                        index,
+                        elements.len()
+                    ),
+                )),
+            }
+        }
+        (RuntimeVal::Enum(enumeration), RuntimeVal::String(key)) => {
+            match enumeration.variants.get(key.as_str()) {
+                Some(fields) if fields.is_empty() => Ok(RuntimeVal::Variant(Rc::new(Variant {
+                    enumeration: enumeration.clone(),
+                    name: key,
+                    values: vec![],
+                }))),
+                Some(_) => Ok(variant_constructor(enumeration.clone(), key)),
+                None => Err(env.error(
+                    ErrorKind::Reference,
+                    format!("{} variant not found in enum {}", key, enumeration.name),
+                )),
+            }
+        }
+        // Arrays and strings have native methods, bound to the value they are read from.
+        (RuntimeVal::Array(elements), RuntimeVal::String(key)) => match array::method(&key) {
+            Some(method) => Ok(RuntimeVal::BoundMethod(Rc::new(BoundMethod {
+                receiver: RuntimeVal::Array(elements),
+                method,
+            }))),
+            None if missing_field == MissingField::Null => Ok(Null),
+            None => Err(env.error(
+                ErrorKind::Reference,
+                format!("{} method not found on array", key),
+            )),
+        },
+        (RuntimeVal::String(str), RuntimeVal::String(key)) => match string::method(&key) {
+            Some(method) => Ok(RuntimeVal::BoundMethod(Rc::new(BoundMethod {
+                receiver: RuntimeVal::String(str),
+                method,
+            }))),
+            None if missing_field == MissingField::Null => Ok(Null),
+            None => Err(env.error(
+                ErrorKind::Reference,
+                format!("{} method not found on string", key),
+            )),
+        },
+        (RuntimeVal::String(str), Number(index)) => {
+            let len = str.chars().count();
+            match array_index(index, len) {
+                Some(index) => Ok(RuntimeVal::String(
+                    str.chars().nth(index).unwrap().to_string(),
+                )),
+                None => Err(env.error(
+                    ErrorKind::Range,
+                    format!("index {} out of bounds for string of length {}", index, len),
+                )),
+            }
+        }
+        (RuntimeVal::Variant(variant), RuntimeVal::String(key)) => match variant.field(&key) {
+            Some(value) => Ok(value),
+            None if missing_field == MissingField::Null => Ok(Null),
+            None => Err(env.error(
+                ErrorKind::Reference,
+                format!(
+                    "{} field not found in {}",
+                    key,
+                    RuntimeVal::Variant(variant)
+                ),
+            )),
+        },
+        (Null, _) if missing_field == MissingField::Null => Ok(Null),
+        (obj, key) => Err(env.error(
+            ErrorKind::Type,
+            format!("cannot read field {} of {}", key, obj),
+        )),
+    }
+}
+
+pub(crate) fn set_member(
+    env: &mut Environment,
+    obj: RuntimeVal,
+    key: RuntimeVal,
+    value: RuntimeVal,
+) -> Result<RuntimeVal, RuntimeError> {
+    match (obj, key) {
+        (RuntimeVal::Object(object), RuntimeVal::String(key)) => {
+            let mut object = object.borrow_mut();
+            if !object.properties.contains_key(key.as_str()) {
+                env.allocate(size_of::<RuntimeVal>() + size_of::<String>() + key.len())?;
             }
-            return map.get(var.as_str()).unwrap().clone();
+            object.properties.insert(key, value.clone());
+            Ok(value)
         }
-    } else if let (Ast::Identifier(obj), Ast::Identifier(member)) = (object, property) {
-        if let Some(RuntimeVal::Object(map)) = env.lookup_variable(obj.as_str()) {
-            if !map.contains_key(member.as_str()) {
-                panic!("{} field not found in object {}", member, obj)
+        (RuntimeVal::Array(elements), Number(index)) => {
+            let mut elements = elements.borrow_mut();
+            match array_index(index, elements.len()) {
+                Some(index) => {
+                    elements[index] = value.clone();
+                    Ok(value)
+                }
+                None => Err(env.error(
+                    ErrorKind::Range,
+                    format!(
+                        "index {} out of bounds for array of length {}",
+                        index,
+                        elements.len()
+                    ),
+                )),
             }
-            return map.get(member.as_str()).unwrap().clone();
-        } else {
-            panic!("{} no definition found", obj);
         }
+        (obj, key) => Err(env.error(
+            ErrorKind::Type,
+            format!("cannot set field {} of {}", key, obj),
+        )),
+    }
+}
+
+fn array_index(index: isize, len: usize) -> Option<usize> {
+    usize::try_from(index).ok().filter(|index| *index < len)
+}
+
+// `Shape.Circle` evaluates to a function building `Shape.Circle(...)` values.
+fn variant_constructor(enumeration: Rc<Enum>, variant: String) -> RuntimeVal {
+    let name = format!("{}.{}", enumeration.name, variant);
+    native_function(name.clone().as_str(), move |env, args| {
+        let arity = enumeration.variants[variant.as_str()].len();
+        if args.len() != arity {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!("{} expects {} values, got {}", name, arity, args.len()),
+            ));
+        }
+        Ok(RuntimeVal::Variant(Rc::new(Variant {
+            enumeration: enumeration.clone(),
+            name: variant.clone(),
+            values: args,
+        })))
+    })
+}
+
+// `super.name` inside a method: the parent class' method, bound to the current `this`.
+fn evaluate_super_method(
+    env: &mut Environment,
+    key: RuntimeVal,
+) -> Result<RuntimeVal, RuntimeError> {
+    let parent = match env.lookup_variable("super")? {
+        RuntimeVal::Class(parent) => parent,
+        other => {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!("super must refer to a class, found {}", other.preview()),
+            ))
+        }
+    };
+    let this = env.lookup_variable("this")?;
+    match parent.find_method(key.to_string().as_str()) {
+        Some(method) => Ok(bind_method(this, method)),
+        None => Err(env.error(
+            ErrorKind::Reference,
+            format!("{} method not found in class {}", key, parent.name),
+        )),
     }
-    Null
+}
+
+fn bind_method(receiver: RuntimeVal, method: Rc<Function>) -> RuntimeVal {
+    RuntimeVal::BoundMethod(Rc::new(BoundMethod {
+        receiver,
+        method: RuntimeVal::Function(method),
+    }))
 }
 
 pub(crate) fn evaluate_call_expression(
     env: &mut Environment,
     caller: Ast,
     args: Vec<Ast>,
-) -> RuntimeVal {
-    Null
+    span: Span,
+) -> Result<RuntimeVal, RuntimeError> {
+    let callee = match &caller {
+        // `super(...)` runs the parent constructor on the instance being constructed.
+        Ast::Identifier(name, _) if name == "super" => {
+            let this = env.lookup_variable("this")?;
+            match env.lookup_variable("super")? {
+                RuntimeVal::Class(parent) => match parent.find_method("constructor") {
+                    Some(constructor) => bind_method(this, constructor),
+                    None => return Ok(Null),
+                },
+                other => {
+                    return Err(env.error(
+                        ErrorKind::Type,
+                        format!("super must refer to a class, found {}", other.preview()),
+                    ))
+                }
+            }
+        }
+        _ => evaluate(env, caller)?,
+    };
+    let arg_values = evaluate_elements(env, args)?;
+    call_function(env, callee, arg_values, span)
+}
+
+pub(crate) fn call_function(
+    env: &mut Environment,
+    callee: RuntimeVal,
+    args: Vec<RuntimeVal>,
+    call_site: Span,
+) -> Result<RuntimeVal, RuntimeError> {
+    match callee {
+        RuntimeVal::NativeFunction(function) => {
+            env.push_frame(function.name.clone(), call_site)?;
+            let result = (function.func)(env, args);
+            env.pop_frame();
+            // Whatever a native returns is charged as new, which overcounts values it
+            // returns without creating them.
+            env.allocated(result?)
+        }
+        RuntimeVal::Function(function) => call_user_function(env, &function, None, args, call_site),
+        RuntimeVal::Class(class) => construct(env, class, args, call_site),
+        RuntimeVal::BoundMethod(bound) => match &bound.method {
+            RuntimeVal::Function(function) => {
+                call_user_function(env, function, Some(bound.receiver.clone()), args, call_site)
+            }
+            method => {
+                let mut args = args;
+                args.insert(0, bound.receiver.clone());
+                call_function(env, method.clone(), args, call_site)
+            }
+        },
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} is not a function", other.preview()),
+        )),
+    }
+}
+
+fn call_user_function(
+    env: &mut Environment,
+    function: &Function,
+    this: Option<RuntimeVal>,
+    args: Vec<RuntimeVal>,
+    call_site: Span,
+) -> Result<RuntimeVal, RuntimeError> {
+    env.push_frame(function.name.clone(), call_site)?;
+    let mut scope = Environment::new(Some(function.closure.clone()));
+    let mut result = match this {
+        Some(this) => scope.declare_variable("this", this, true),
+        None => Ok(Null),
+    };
+    let mut args = args.into_iter();
+    for parameter in &function.parameters {
+        if result.is_err() {
+            break;
+        }
+        let value = args.next().unwrap_or(Null);
+        result = bind_pattern(&mut scope, parameter, value, false).map(|_| Null);
+    }
+    if result.is_ok() {
+        result = evaluate_program(&mut scope, function.body.clone());
+    }
+    env.pop_frame();
+    match result {
+        Err(RuntimeError::Return(value)) => Ok(value),
+        result => result,
+    }
+}
+
+// Calling a class creates an instance: fields are initialised from the root class down,
+// then the closest constructor runs with `this` bound to the new instance.
+fn construct(
+    env: &mut Environment,
+    class: Rc<Class>,
+    args: Vec<RuntimeVal>,
+    call_site: Span,
+) -> Result<RuntimeVal, RuntimeError> {
+    let instance = RuntimeVal::Object(Rc::new(RefCell::new(Object {
+        class: Some(class.clone()),
+        properties: IndexMap::new(),
+    })));
+    initialize_fields(&class, &instance)?;
+    if let Some(constructor) = class.find_method("constructor") {
+        call_user_function(env, &constructor, Some(instance.clone()), args, call_site)?;
+    }
+    env.allocated(instance)
+}
+
+fn initialize_fields(class: &Class, instance: &RuntimeVal) -> Result<(), RuntimeError> {
+    if let Some(parent) = &class.parent {
+        initialize_fields(parent, instance)?;
+    }
+    for (field, value) in &class.fields {
+        let value = match value {
+            Some(value) => {
+                let mut scope = Environment::new(Some(class.closure.clone()));
+                scope.declare_variable("this", instance.clone(), true)?;
+                evaluate(&mut scope, value.clone())?
+            }
+            None => Null,
+        };
+        if let RuntimeVal::Object(object) = instance {
+            object.borrow_mut().properties.insert(field.clone(), value);
+        }
+    }
+    Ok(())
 }
@@ -1,2 +1,3 @@
 pub mod expressions;
+pub mod patterns;
 pub mod statements;
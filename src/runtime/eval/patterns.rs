@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use crate::frontend::ast::{Ast, MatchArm, Pattern};
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::interpreter::evaluate;
+use crate::runtime::types::{Enum, RuntimeVal};
+
+pub fn evaluate_match_expression(
+    env: &mut Environment,
+    subject: Ast,
+    arms: Vec<MatchArm>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let value = evaluate(env, subject)?;
+    check_exhaustive(env, &arms)?;
+    for arm in arms {
+        let mut bindings = vec![];
+        if !match_pattern(env, &arm.pattern, &value, &mut bindings)? {
+            continue;
+        }
+        let result = env.scoped(|scope| {
+            for (name, value) in bindings {
+                scope.declare_variable(name.as_str(), value, false)?;
+            }
+            if let Some(guard) = arm.guard {
+                if !evaluate(scope, guard)?.is_truthy() {
+                    return Ok(None);
+                }
+            }
+            evaluate(scope, arm.body).map(Some)
+        })?;
+        if let Some(result) = result {
+            return Ok(result);
+        }
+    }
+    Err(env.error(
+        ErrorKind::Match,
+        format!("no match arm matched {}", value.preview()),
+    ))
+}
+
+// Tries to match `value` against `pattern`, collecting the names it binds.
+pub fn match_pattern(
+    env: &mut Environment,
+    pattern: &Pattern,
+    value: &RuntimeVal,
+    bindings: &mut Vec<(String, RuntimeVal)>,
+) -> Result<bool, RuntimeError> {
+    match pattern {
+        Pattern::Wildcard => Ok(true),
+        Pattern::Literal(literal) => Ok(evaluate(env, literal.clone())? == *value),
+        Pattern::Binding(name, _) => {
+            bindings.push((name.clone(), value.clone()));
+            Ok(true)
+        }
+        Pattern::Object { properties, rest } => {
+            let object = match value {
+                RuntimeVal::Object(object) => object.borrow().properties.clone(),
+                _ => return Ok(false),
+            };
+            for (key, pattern) in properties {
+                let value = match (object.get(key.as_str()), pattern) {
+                    (Some(value), _) => value.clone(),
+                    (None, Pattern::Default { .. }) => RuntimeVal::Null,
+                    (None, _) => return Ok(false),
+                };
+                if !match_pattern(env, pattern, &value, bindings)? {
+                    return Ok(false);
+                }
+            }
+            if let Some(rest) = rest {
+                let remaining: IndexMap<String, RuntimeVal> = object
+                    .into_iter()
+                    .filter(|(key, _)| !properties.iter().any(|(matched, _)| matched == key))
+                    .collect();
+                bindings.push((rest.clone(), RuntimeVal::object(remaining)));
+            }
+            Ok(true)
+        }
+        Pattern::Array { elements, rest } => {
+            let values = match value {
+                RuntimeVal::Array(values) => values.borrow().clone(),
+                _ => return Ok(false),
+            };
+            // Trailing elements with defaults may be left out.
+            let required = elements
+                .iter()
+                .rposition(|element| !matches!(element, Pattern::Default { .. }))
+                .map_or(0, |index| index + 1);
+            if values.len() < required || (rest.is_none() && values.len() > elements.len()) {
+                return Ok(false);
+            }
+            for (index, pattern) in elements.iter().enumerate() {
+                let value = values.get(index).cloned().unwrap_or(RuntimeVal::Null);
+                if !match_pattern(env, pattern, &value, bindings)? {
+                    return Ok(false);
+                }
+            }
+            if let Some(rest) = rest {
+                let remaining = values.get(elements.len()..).unwrap_or_default().to_vec();
+                bindings.push((rest.clone(), RuntimeVal::array(remaining)));
+            }
+            Ok(true)
+        }
+        Pattern::Variant {
+            enumeration,
+            variant,
+            fields,
+        } => {
+            let enumeration = resolve_enum(env, enumeration, variant)?;
+            let value = match value {
+                RuntimeVal::Variant(value)
+                    if Rc::ptr_eq(&value.enumeration, &enumeration) && value.name == *variant =>
+                {
+                    value
+                }
+                _ => return Ok(false),
+            };
+            // `Shape.Circle` without parentheses matches any circle.
+            if fields.is_empty() {
+                return Ok(true);
+            }
+            if fields.len() != value.values.len() {
+                return Err(env.error(
+                    ErrorKind::Type,
+                    format!(
+                        "pattern {}.{} expects {} fields, found {}",
+                        enumeration.name,
+                        variant,
+                        value.values.len(),
+                        fields.len()
+                    ),
+                ));
+            }
+            for (pattern, value) in fields.iter().zip(value.values.iter()) {
+                if !match_pattern(env, pattern, value, bindings)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        Pattern::Default { pattern, default } => {
+            let value = match value {
+                RuntimeVal::Null => evaluate(env, *default.clone())?,
+                value => value.clone(),
+            };
+            match_pattern(env, pattern, &value, bindings)
+        }
+    }
+}
+
+// Binds the names in `pattern` in `env`, failing with a TypeError when the value does
+// not have the shape the pattern describes.
+pub fn bind_pattern(
+    env: &mut Environment,
+    pattern: &Pattern,
+    value: RuntimeVal,
+    constant: bool,
+) -> Result<(), RuntimeError> {
+    let mut bindings = vec![];
+    if !match_pattern(env, pattern, &value, &mut bindings)? {
+        return Err(env.error(
+            ErrorKind::Type,
+            format!(
+                "cannot destructure {} with pattern {}",
+                value.preview(),
+                pattern
+            ),
+        ));
+    }
+    for (name, value) in bindings {
+        env.declare_variable(name.as_str(), value, constant)?;
+    }
+    Ok(())
+}
+
+fn resolve_enum(
+    env: &mut Environment,
+    enumeration: &Ast,
+    variant: &str,
+) -> Result<Rc<Enum>, RuntimeError> {
+    match evaluate(env, enumeration.clone())? {
+        RuntimeVal::Enum(enumeration) if enumeration.variants.contains_key(variant) => {
+            Ok(enumeration)
+        }
+        RuntimeVal::Enum(enumeration) => Err(env.error(
+            ErrorKind::Reference,
+            format!("{} variant not found in enum {}", variant, enumeration.name),
+        )),
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} is not an enum", other.preview()),
+        )),
+    }
+}
+
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    matches!(pattern, Pattern::Wildcard | Pattern::Binding(..))
+}
+
+// A match over enum variants must handle every variant, either directly or through a
+// catch-all arm. Checked before any arm runs so a missing case fails on every input.
+fn check_exhaustive(env: &mut Environment, arms: &[MatchArm]) -> Result<(), RuntimeError> {
+    let mut enumeration: Option<Rc<Enum>> = None;
+    let mut covered = HashSet::new();
+    for arm in arms {
+        if arm.guard.is_none() && is_irrefutable(&arm.pattern) {
+            return Ok(());
+        }
+        if let Pattern::Variant {
+            enumeration: path,
+            variant,
+            fields,
+        } = &arm.pattern
+        {
+            let resolved = resolve_enum(env, path, variant)?;
+            enumeration.get_or_insert(resolved);
+            if arm.guard.is_none() && fields.iter().all(is_irrefutable) {
+                covered.insert(variant.clone());
+            }
+        }
+    }
+    let enumeration = match enumeration {
+        Some(enumeration) => enumeration,
+        None => return Ok(()),
+    };
+    let missing: Vec<String> = enumeration
+        .variants
+        .keys()
+        .filter(|variant| !covered.contains(*variant))
+        .map(|variant| format!("{}.{}", enumeration.name, variant))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(env.error(
+        ErrorKind::Match,
+        format!(
+            "non-exhaustive match on enum {}: missing {}",
+            enumeration.name,
+            missing.join(", ")
+        ),
+    ))
+}
@@ -1,14 +1,23 @@
-use crate::frontend::ast::Ast;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use crate::frontend::ast::{Ast, Pattern};
 use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::eval::patterns::bind_pattern;
 use crate::runtime::interpreter;
-use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::{Class, Enum, Function, RuntimeVal};
 
-pub fn evaluate_program(env: &mut Environment, statements: Vec<Ast>) -> RuntimeVal {
+pub fn evaluate_program(
+    env: &mut Environment,
+    statements: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
     let mut res = RuntimeVal::Null;
     for statement in statements {
-        res = interpreter::evaluate(env, statement);
+        res = interpreter::evaluate(env, statement)?;
     }
-    res
+    Ok(res)
 }
 
 pub fn evaluate_variable_declaration(
@@ -16,7 +25,174 @@ pub fn evaluate_variable_declaration(
     constant: bool,
     identifier: String,
     value: Ast,
-) -> RuntimeVal {
-    let val = interpreter::evaluate(env, value);
+) -> Result<RuntimeVal, RuntimeError> {
+    let val = interpreter::evaluate(env, value)?;
     env.declare_variable(identifier.as_str(), val, constant)
 }
+
+pub fn evaluate_destructuring_declaration(
+    env: &mut Environment,
+    constant: bool,
+    pattern: Pattern,
+    value: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    let val = interpreter::evaluate(env, value)?;
+    bind_pattern(env, &pattern, val.clone(), constant)?;
+    Ok(val)
+}
+
+pub fn evaluate_block_statement(
+    env: &mut Environment,
+    statements: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
+    env.scoped(|scope| evaluate_program(scope, statements))
+}
+
+pub fn evaluate_try_statement(
+    env: &mut Environment,
+    block: Ast,
+    param: Option<String>,
+    handler: Option<Box<Ast>>,
+    finalizer: Option<Box<Ast>>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let mut result = interpreter::evaluate(env, block);
+    if let (Err(RuntimeError::Thrown(error)), Some(handler)) = (&result, handler) {
+        let error = error.clone();
+        result = env.scoped(|scope| {
+            if let Some(param) = param {
+                scope.declare_variable(param.as_str(), error, false)?;
+            }
+            interpreter::evaluate(scope, *handler)
+        });
+    }
+    if let Some(finalizer) = finalizer {
+        interpreter::evaluate(env, *finalizer)?;
+    }
+    result
+}
+
+pub fn evaluate_throw_statement(
+    env: &mut Environment,
+    argument: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    let value = interpreter::evaluate(env, argument)?;
+    Err(RuntimeError::Thrown(value))
+}
+
+pub fn evaluate_function_declaration(
+    env: &mut Environment,
+    name: Option<String>,
+    parameters: Vec<Pattern>,
+    body: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let function = RuntimeVal::Function(Rc::new(Function {
+        name: name.clone().unwrap_or_else(|| "<anonymous>".to_string()),
+        parameters,
+        body,
+        closure: env.clone(),
+        method: false,
+    }));
+    match name {
+        Some(name) => env.declare_variable(name.as_str(), function, false),
+        None => Ok(function),
+    }
+}
+
+pub fn evaluate_export_declaration(
+    env: &mut Environment,
+    declaration: Ast,
+) -> Result<RuntimeVal, RuntimeError> {
+    let names = match &declaration {
+        Ast::VariableDeclaration { identifier, .. } => vec![identifier.clone()],
+        Ast::DestructuringDeclaration { pattern, .. } => pattern.bound_names(),
+        Ast::FunctionDeclaration {
+            name: Some(name), ..
+        }
+        | Ast::ClassDeclaration { name, .. }
+        | Ast::EnumDeclaration { name, .. } => vec![name.clone()],
+        _ => vec![],
+    };
+    let value = interpreter::evaluate(env, declaration)?;
+    env.module().exports.borrow_mut().extend(names);
+    Ok(value)
+}
+
+pub fn evaluate_return_statement(
+    env: &mut Environment,
+    argument: Option<Box<Ast>>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let value = match argument {
+        Some(argument) => interpreter::evaluate(env, *argument)?,
+        None => RuntimeVal::Null,
+    };
+    Err(RuntimeError::Return(value))
+}
+
+pub fn evaluate_class_declaration(
+    env: &mut Environment,
+    name: String,
+    parent: Option<Box<Ast>>,
+    fields: Vec<(String, Option<Ast>)>,
+    methods: Vec<Ast>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let parent = match parent {
+        Some(parent) => match interpreter::evaluate(env, *parent)? {
+            RuntimeVal::Class(parent) => Some(parent),
+            other => {
+                return Err(env.error(
+                    ErrorKind::Type,
+                    format!(
+                        "class {} can only extend a class, found {}",
+                        name,
+                        other.preview()
+                    ),
+                ))
+            }
+        },
+        None => None,
+    };
+    // Methods close over a scope holding `super`, so they can reach the parent class.
+    let mut closure = Environment::new(Some(env.clone()));
+    if let Some(parent) = &parent {
+        closure.declare_variable("super", RuntimeVal::Class(parent.clone()), true)?;
+    }
+    let mut class_methods = IndexMap::new();
+    for method in methods {
+        if let Ast::FunctionDeclaration {
+            name: Some(method_name),
+            parameters,
+            body,
+            ..
+        } = method
+        {
+            let function = Function {
+                name: format!("{}.{}", name, method_name),
+                parameters,
+                body,
+                closure: closure.clone(),
+                method: true,
+            };
+            class_methods.insert(method_name, Rc::new(function));
+        }
+    }
+    let class = RuntimeVal::Class(Rc::new(Class {
+        name: name.clone(),
+        parent,
+        fields,
+        methods: class_methods,
+        closure,
+    }));
+    env.declare_variable(name.as_str(), class, false)
+}
+
+pub fn evaluate_enum_declaration(
+    env: &mut Environment,
+    name: String,
+    variants: Vec<(String, Vec<String>)>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let enumeration = RuntimeVal::Enum(Rc::new(Enum {
+        name: name.clone(),
+        variants: variants.into_iter().collect(),
+    }));
+    env.declare_variable(name.as_str(), enumeration, true)
+}
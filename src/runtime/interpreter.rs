@@ -1,9 +1,39 @@
 use crate::frontend::ast::Ast;
 use crate::runtime::environment::Environment;
-use crate::runtime::eval::{expressions, statements};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::eval::{expressions, patterns, statements};
+use crate::runtime::module;
 use crate::runtime::types::RuntimeVal;
 
-pub fn evaluate(env: &mut Environment, ast: Ast) -> RuntimeVal {
+// Evaluation recurses for every nested node, and each level takes several Rust frames, so
+// the thread's stack runs out long before the call depth limit is reached. Once less than
+// `STACK_RED_ZONE` bytes are left, evaluation goes on in a new `STACK_SEGMENT` on the heap.
+// Printing and comparing values, which nest as deeply as scripts build them, do the same.
+pub(crate) const STACK_RED_ZONE: usize = 256 * 1024;
+pub(crate) const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+// Evaluates one node. Each node counts as a step, and strings, arrays and objects created
+// by literals and operators are charged against the heap limit.
+pub fn evaluate(env: &mut Environment, ast: Ast) -> Result<RuntimeVal, RuntimeError> {
+    env.step()?;
+    let allocates = matches!(
+        ast,
+        Ast::BinaryExpr { .. }
+            | Ast::CompoundAssignmentExpr { .. }
+            | Ast::TemplateLiteral { .. }
+            | Ast::ObjectLiteral { .. }
+            | Ast::ArrayLiteral { .. }
+    );
+    let value = env.nested(|env| {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || evaluate_node(env, ast))
+    })?;
+    match allocates {
+        true => env.allocated(value),
+        false => Ok(value),
+    }
+}
+
+fn evaluate_node(env: &mut Environment, ast: Ast) -> Result<RuntimeVal, RuntimeError> {
     match ast {
         Ast::Program { statements } => statements::evaluate_program(env, statements),
 
@@ -11,29 +41,122 @@ pub fn evaluate(env: &mut Environment, ast: Ast) -> RuntimeVal {
             constant,
             identifier,
             value,
+            ..
         } => statements::evaluate_variable_declaration(env, constant, identifier, *value),
 
-        Ast::AssignmentExpr { assignee, value } => {
-            expressions::evaluate_assignment_expression(env, *assignee, *value)
+        Ast::DestructuringDeclaration {
+            constant,
+            pattern,
+            value,
+            ..
+        } => statements::evaluate_destructuring_declaration(env, constant, *pattern, *value),
+
+        Ast::BlockStatement { statements } => statements::evaluate_block_statement(env, statements),
+
+        Ast::TryStatement {
+            block,
+            param,
+            handler,
+            finalizer,
+        } => statements::evaluate_try_statement(
+            env,
+            *block,
+            param.map(|(param, _)| param),
+            handler,
+            finalizer,
+        ),
+
+        Ast::ThrowStatement { argument, .. } => {
+            statements::evaluate_throw_statement(env, *argument)
+        }
+
+        Ast::FunctionDeclaration {
+            name,
+            parameters,
+            body,
+            ..
+        } => statements::evaluate_function_declaration(env, name, parameters, body),
+
+        Ast::ClassDeclaration {
+            name,
+            parent,
+            fields,
+            methods,
+            ..
+        } => statements::evaluate_class_declaration(env, name, parent, fields, methods),
+
+        Ast::EnumDeclaration { name, variants, .. } => {
+            statements::evaluate_enum_declaration(env, name, variants)
+        }
+
+        Ast::MatchExpr { subject, arms } => {
+            patterns::evaluate_match_expression(env, *subject, arms)
         }
 
+        Ast::ImportDeclaration {
+            names,
+            source,
+            span,
+        } => module::import(env, names, source, span),
+
+        Ast::ExportDeclaration { declaration } => {
+            statements::evaluate_export_declaration(env, *declaration)
+        }
+
+        Ast::ReturnStatement { argument, .. } => {
+            statements::evaluate_return_statement(env, argument)
+        }
+
+        Ast::AssignmentExpr {
+            assignee, value, ..
+        } => expressions::evaluate_assignment_expression(env, *assignee, *value),
+
+        Ast::CompoundAssignmentExpr {
+            assignee,
+            operator,
+            value,
+            ..
+        } => expressions::evaluate_compound_assignment_expression(env, *assignee, operator, *value),
+
+        Ast::UpdateExpr {
+            operator,
+            argument,
+            prefix,
+            ..
+        } => expressions::evaluate_update_expression(env, operator, *argument, prefix),
+
         Ast::BinaryExpr {
             left,
             right,
             operator,
+            ..
         } => expressions::evaluate_binary_expression(env, *left, *right, operator),
 
-        Ast::Identifier(var) => expressions::evaluate_identifier(env, var),
-        Ast::NumericLiteral(num) => RuntimeVal::Number(num),
+        Ast::UnaryExpr { operator, argument } => {
+            expressions::evaluate_unary_expression(env, operator, *argument)
+        }
+
+        Ast::Identifier(var, _) => expressions::evaluate_identifier(env, var),
+        Ast::NumericLiteral(num) => Ok(RuntimeVal::Number(num)),
+        Ast::FloatLiteral(num) => Ok(RuntimeVal::Float(num)),
+        Ast::StringLiteral(str) => Ok(RuntimeVal::String(str)),
+        Ast::TemplateLiteral { parts } => expressions::evaluate_template_literal(env, parts),
         Ast::PropertyLiteral { key, value } => {
             expressions::evaluate_property_literal(env, key, value)
         }
         Ast::ObjectLiteral { properties } => expressions::evaluate_object_literal(env, properties),
+        Ast::ArrayLiteral { elements } => expressions::evaluate_array_literal(env, elements),
+        Ast::OptionalChain { expression } => expressions::evaluate_optional_chain(env, *expression),
+        Ast::NullGuard { argument } => expressions::evaluate_null_guard(env, *argument),
+        Ast::SpreadElement { .. } => expressions::evaluate_spread_element(env),
         Ast::MemberExpr {
             object,
             property,
             computed,
+            ..
         } => expressions::evaluate_member_expression(env, *object, *property, computed),
-        Ast::CallExpr { caller, args } => expressions::evaluate_call_expression(env, *caller, args),
+        Ast::CallExpr { caller, args, span } => {
+            expressions::evaluate_call_expression(env, *caller, args, span)
+        }
     }
 }
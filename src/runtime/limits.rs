@@ -0,0 +1,219 @@
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::Read;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+use crate::runtime::types::RuntimeVal;
+
+// The wall clock is read once per this many steps, keeping the check cheap.
+const STEPS_PER_CLOCK_CHECK: u64 = 256;
+
+// Bounds on the resources one run of a program may use, for running untrusted scripts.
+// `None` leaves a resource unbounded. Exceeding a limit raises `LimitExceeded`, which
+// scripts cannot catch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    // Ast nodes evaluated.
+    pub max_steps: Option<u64>,
+    // Nested function calls. Without it, deep recursion raises a catchable
+    // StackOverflowError at the environment's maximum call depth instead.
+    pub max_call_depth: Option<usize>,
+    // Ast nodes being evaluated inside one another, counted across calls. Evaluation grows
+    // the stack on the heap as it nests, so this bounds the memory deep nesting can take.
+    pub max_nesting_depth: Option<usize>,
+    // Levels blocks, expressions, patterns and types may nest in the source, beyond which
+    // it is a syntax error. Unlike the other limits, `None` means the parser's default of
+    // `MAX_NESTING_DEPTH` rather than no bound, as every pass over the syntax tree recurses.
+    pub max_source_depth: Option<usize>,
+    // Bytes allocated for strings, arrays and objects, counted as they are created and
+    // never given back. Approximate: each value is charged its shallow size.
+    pub max_heap_bytes: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+// The limit a program ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps(u64),
+    CallDepth(usize),
+    NestingDepth(usize),
+    HeapBytes(usize),
+    Time(Duration),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(steps) => write!(f, "step limit of {} exceeded", steps),
+            Limit::CallDepth(depth) => write!(f, "call depth limit of {} exceeded", depth),
+            Limit::NestingDepth(depth) => write!(f, "nesting depth limit of {} exceeded", depth),
+            Limit::HeapBytes(bytes) => write!(f, "heap limit of {} bytes exceeded", bytes),
+            Limit::Time(timeout) => write!(f, "time limit of {:?} exceeded", timeout),
+        }
+    }
+}
+
+impl std::error::Error for Limit {}
+
+// What the current run has used so far, measured against its limits.
+#[derive(Debug, Default)]
+pub struct Budget {
+    limits: Cell<Limits>,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    heap_bytes: Cell<usize>,
+    started: Cell<Option<Instant>>,
+}
+
+impl Budget {
+    pub fn limits(&self) -> Limits {
+        self.limits.get()
+    }
+
+    pub fn set_limits(&self, limits: Limits) {
+        self.limits.set(limits);
+        self.reset();
+    }
+
+    // Starts a new run: usage goes back to zero and the clock restarts.
+    pub fn reset(&self) {
+        self.steps.set(0);
+        self.heap_bytes.set(0);
+        self.started.set(Some(Instant::now()));
+    }
+
+    pub fn step(&self) -> Result<(), Limit> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        let limits = self.limits.get();
+        if let Some(max_steps) = limits.max_steps {
+            if steps > max_steps {
+                return Err(Limit::Steps(max_steps));
+            }
+        }
+        match steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) {
+            true => self.check_time(),
+            false => Ok(()),
+        }
+    }
+
+    pub fn check_time(&self) -> Result<(), Limit> {
+        match (self.limits.get().timeout, self.deadline()) {
+            (Some(timeout), Some(deadline)) if Instant::now() > deadline => {
+                Err(Limit::Time(timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // When the current run runs out of time, if it has a time limit.
+    pub fn deadline(&self) -> Option<Instant> {
+        Some(self.started.get()? + self.limits.get().timeout?)
+    }
+
+    // Goes one node deeper into evaluation; every successful `enter` is paired with a
+    // `leave`.
+    pub fn enter(&self) -> Result<(), Limit> {
+        let depth = self.depth.get();
+        match self.limits.get().max_nesting_depth {
+            Some(max_depth) if depth >= max_depth => Err(Limit::NestingDepth(max_depth)),
+            _ => {
+                self.depth.set(depth + 1);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    pub fn check_call_depth(&self, depth: usize) -> Result<(), Limit> {
+        match self.limits.get().max_call_depth {
+            Some(max_depth) if depth >= max_depth => Err(Limit::CallDepth(max_depth)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn allocate(&self, bytes: usize) -> Result<(), Limit> {
+        self.check_allocation(bytes)?;
+        self.heap_bytes.set(self.heap_bytes.get() + bytes);
+        Ok(())
+    }
+
+    // Whether `bytes` more would still fit, without charging them.
+    pub fn check_allocation(&self, bytes: usize) -> Result<(), Limit> {
+        let heap_bytes = self.heap_bytes.get().saturating_add(bytes);
+        match self.limits.get().max_heap_bytes {
+            Some(max_bytes) if heap_bytes > max_bytes => Err(Limit::HeapBytes(max_bytes)),
+            _ => Ok(()),
+        }
+    }
+
+    // How many more bytes fit under the heap limit, if there is one.
+    pub fn heap_left(&self) -> Option<usize> {
+        let max_bytes = self.limits.get().max_heap_bytes?;
+        Some(max_bytes.saturating_sub(self.heap_bytes.get()))
+    }
+}
+
+// Counts the work of walking a value to write it out, as printing and serializing do,
+// against the run's limits. Shared arrays and objects are walked once per reference, so
+// a small value can stand for text far larger than the heap limit.
+pub struct Meter<'a> {
+    budget: &'a Budget,
+    visits: u64,
+    // The limit a walk ran into, which the writer itself can only report as failing.
+    pub exceeded: Option<Limit>,
+}
+
+impl<'a> Meter<'a> {
+    pub fn new(budget: &'a Budget) -> Self {
+        Meter {
+            budget,
+            visits: 0,
+            exceeded: None,
+        }
+    }
+
+    // Called for each value visited, with the length of the text written so far.
+    pub fn visit(&mut self, written: usize) -> Result<(), Limit> {
+        self.visits += 1;
+        let mut result = self.budget.check_allocation(written);
+        if result.is_ok() && self.visits.is_multiple_of(STEPS_PER_CLOCK_CHECK) {
+            result = self.budget.check_time();
+        }
+        if let Err(limit) = result {
+            self.exceeded = Some(limit);
+        }
+        result
+    }
+}
+
+// Reads `reader` to the end into `bytes`, stopping one byte past `limit` so that callers
+// can tell input that does not fit from input that just fits.
+pub fn read_up_to(
+    reader: impl Read,
+    limit: Option<usize>,
+    bytes: &mut Vec<u8>,
+) -> io::Result<usize> {
+    let cap = limit.map_or(u64::MAX, |limit| limit as u64 + 1);
+    reader.take(cap).read_to_end(bytes)
+}
+
+// The bytes charged for a newly created value: string contents, or one slot per array
+// element or object property. Nested values are charged when they are created.
+pub fn allocation_size(value: &RuntimeVal) -> usize {
+    match value {
+        RuntimeVal::String(str) => str.len(),
+        RuntimeVal::Array(elements) => elements.borrow().len() * size_of::<RuntimeVal>(),
+        RuntimeVal::Object(object) => {
+            let properties = &object.borrow().properties;
+            properties.len() * (size_of::<RuntimeVal>() + size_of::<String>())
+                + properties.keys().map(String::len).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
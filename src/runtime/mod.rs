@@ -1,4 +1,10 @@
+pub mod capabilities;
+pub mod convert;
 pub mod environment;
+pub mod error;
 pub mod eval;
 pub mod interpreter;
-pub mod types;
\ No newline at end of file
+pub mod limits;
+pub mod module;
+pub mod native;
+pub mod types;
@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::frontend::span::Span;
+use crate::repl;
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::types::RuntimeVal;
+
+// The file a scope was loaded from, together with the names it exports.
+#[derive(Debug, Default)]
+pub struct Module {
+    pub path: Option<PathBuf>,
+    pub exports: RefCell<Vec<String>>,
+}
+
+impl Module {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Module {
+            path,
+            exports: RefCell::new(vec![]),
+        }
+    }
+
+    // Imports are resolved against the importing file, or the working directory in the repl.
+    fn resolve(&self, source: &str) -> PathBuf {
+        match self.path.as_ref().and_then(|path| path.parent()) {
+            Some(directory) => directory.join(source),
+            None => PathBuf::from(source),
+        }
+    }
+
+    pub(crate) fn location(&self, span: Span) -> String {
+        match &self.path {
+            Some(path) => format!("{}:{}", path.display(), span),
+            None => span.to_string(),
+        }
+    }
+}
+
+// Shared by every module of one program: evaluated modules by canonical path, and the
+// modules currently being evaluated, innermost last, to detect import cycles.
+#[derive(Debug, Default)]
+pub struct ModuleLoader {
+    cache: HashMap<PathBuf, Environment>,
+    loading: Vec<PathBuf>,
+}
+
+// Evaluates `import { names } from "source";`, declaring each imported name as a constant.
+pub fn import(
+    env: &mut Environment,
+    names: Vec<(String, String)>,
+    source: String,
+    span: Span,
+) -> Result<RuntimeVal, RuntimeError> {
+    let module = load(env, source.as_str(), span)?;
+    let exports = module.module().exports.borrow().clone();
+    for (name, alias) in names {
+        if !exports.contains(&name) {
+            return Err(env.error(
+                ErrorKind::Import,
+                format!(
+                    "{} is not exported by {} at {}",
+                    name,
+                    source,
+                    env.module().location(span)
+                ),
+            ));
+        }
+        let value = module.clone().lookup_variable(name.as_str())?;
+        env.declare_variable(alias.as_str(), value, true)?;
+    }
+    Ok(RuntimeVal::Null)
+}
+
+// Runs the program's entry file in `env`, whose module path is already set. The entry is
+// registered with the loader like an imported module, so that importing it back is an
+// import cycle rather than a second run of it.
+pub fn run_entry(env: &mut Environment, source: String) -> Result<RuntimeVal, RuntimeError> {
+    let module = env.module();
+    let Some(path) = module.path.as_ref() else {
+        return repl::execute(env, source);
+    };
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+    let loader = env.module_loader();
+    loader.borrow_mut().loading.push(path.clone());
+    let result = repl::execute(env, source);
+    loader.borrow_mut().loading.pop();
+    if result.is_ok() {
+        loader.borrow_mut().cache.insert(path, env.clone());
+    }
+    result
+}
+
+fn load(env: &mut Environment, source: &str, span: Span) -> Result<Environment, RuntimeError> {
+    let importer = env.module();
+    // Checked before looking for the file, so scripts cannot probe which files exist.
+    env.check_read(&importer.resolve(source), "cannot import")?;
+    let path = match fs::canonicalize(importer.resolve(source)) {
+        Ok(path) => path,
+        Err(error) => {
+            return Err(env.error(
+                ErrorKind::Import,
+                format!(
+                    "cannot find module {} imported at {}: {}",
+                    source,
+                    importer.location(span),
+                    error
+                ),
+            ))
+        }
+    };
+    let loader = env.module_loader();
+    if let Some(module) = loader.borrow().cache.get(&path) {
+        return Ok(module.clone());
+    }
+    if let Some(start) = loader
+        .borrow()
+        .loading
+        .iter()
+        .position(|entry| *entry == path)
+    {
+        let cycle: Vec<String> = loader.borrow().loading[start..]
+            .iter()
+            .chain([&path])
+            .map(|entry| display_name(entry))
+            .collect();
+        return Err(env.error(
+            ErrorKind::Import,
+            format!(
+                "import cycle detected at {}: {}",
+                importer.location(span),
+                cycle.join(" -> ")
+            ),
+        ));
+    }
+    let source_code = match fs::read_to_string(&path) {
+        Ok(source_code) => source_code,
+        Err(error) => {
+            return Err(env.error(
+                ErrorKind::Import,
+                format!("cannot read module {}: {}", path.display(), error),
+            ))
+        }
+    };
+    let mut module = env.module_env(path.clone());
+    loader.borrow_mut().loading.push(path.clone());
+    let result = repl::execute(&mut module, source_code);
+    loader.borrow_mut().loading.pop();
+    result?;
+    loader.borrow_mut().cache.insert(path, module.clone());
+    Ok(module)
+}
+
+fn display_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    }
+}
@@ -0,0 +1,384 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{call_back, check_arity, integer_arg, native_function};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Bool, Float, Null, Number};
+
+type Elements = Rc<RefCell<Vec<RuntimeVal>>>;
+
+// An array method receives the array it was called on and its own arguments.
+type ArrayMethod =
+    fn(&mut Environment, &Elements, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+const METHODS: [(&str, ArrayMethod); 16] = [
+    ("len", len),
+    ("push", push),
+    ("pop", pop),
+    ("insert", insert),
+    ("remove", remove),
+    ("map", map),
+    ("filter", filter),
+    ("reduce", reduce),
+    ("find", find),
+    ("any", any),
+    ("all", all),
+    ("sort", sort),
+    ("reverse", reverse),
+    ("zip", zip),
+    ("enumerate", enumerate),
+    ("flat_map", flat_map),
+];
+
+// Looks up an array method as a native function taking the array as its first argument,
+// ready to be bound to a receiver.
+pub fn method(name: &str) -> Option<RuntimeVal> {
+    let (name, method) = *METHODS.iter().find(|(method, _)| *method == name)?;
+    let qualified = format!("array.{}", name);
+    Some(native_function(
+        qualified.as_str(),
+        move |env, mut args| match args.remove(0) {
+            RuntimeVal::Array(receiver) => method(env, &receiver, args),
+            other => Err(env.error(
+                ErrorKind::Type,
+                format!("array.{} called on {}", name, other.preview()),
+            )),
+        },
+    ))
+}
+
+// Callbacks may change the array while it is being walked, so methods iterate over a copy.
+fn snapshot(elements: &Elements) -> Vec<RuntimeVal> {
+    elements.borrow().clone()
+}
+
+fn len(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.len", &args, 0, 0)?;
+    Ok(Number(elements.borrow().len() as isize))
+}
+
+// Appends the arguments and returns the new length.
+fn push(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.push", &args, 1, usize::MAX)?;
+    env.allocate(args.len() * size_of::<RuntimeVal>())?;
+    let mut elements = elements.borrow_mut();
+    elements.extend(args);
+    Ok(Number(elements.len() as isize))
+}
+
+// Removes and returns the last element, or null when the array is empty.
+fn pop(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.pop", &args, 0, 0)?;
+    Ok(elements.borrow_mut().pop().unwrap_or(Null))
+}
+
+fn position(
+    env: &Environment,
+    name: &str,
+    value: &RuntimeVal,
+    len: usize,
+) -> Result<usize, RuntimeError> {
+    let index = integer_arg(env, name, value)?;
+    match usize::try_from(index) {
+        Ok(index) if index <= len => Ok(index),
+        _ => Err(env.error(
+            ErrorKind::Range,
+            format!(
+                "{} index {} out of bounds for array of length {}",
+                name, index, len
+            ),
+        )),
+    }
+}
+
+fn insert(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.insert", &args, 2, 2)?;
+    let len = elements.borrow().len();
+    let index = position(env, "array.insert", &args[0], len)?;
+    env.allocate(size_of::<RuntimeVal>())?;
+    elements.borrow_mut().insert(index, args[1].clone());
+    Ok(Number(len as isize + 1))
+}
+
+// Removes the element at an index and returns it.
+fn remove(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.remove", &args, 1, 1)?;
+    let len = elements.borrow().len();
+    let index = position(env, "array.remove", &args[0], len)?;
+    if index == len {
+        return Err(env.error(
+            ErrorKind::Range,
+            format!(
+                "array.remove index {} out of bounds for array of length {}",
+                index, len
+            ),
+        ));
+    }
+    Ok(elements.borrow_mut().remove(index))
+}
+
+fn map(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.map", &args, 1, 1)?;
+    let mut mapped = vec![];
+    for element in snapshot(elements) {
+        mapped.push(call_back(env, &args[0], vec![element])?);
+    }
+    Ok(RuntimeVal::array(mapped))
+}
+
+fn filter(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.filter", &args, 1, 1)?;
+    let mut kept = vec![];
+    for element in snapshot(elements) {
+        if call_back(env, &args[0], vec![element.clone()])?.is_truthy() {
+            kept.push(element);
+        }
+    }
+    Ok(RuntimeVal::array(kept))
+}
+
+// `reduce(f, initial)` calls `f(accumulator, element)`; without an initial value the
+// first element starts the accumulator.
+fn reduce(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.reduce", &args, 1, 2)?;
+    let mut remaining = snapshot(elements).into_iter();
+    let mut accumulator = match args.get(1).cloned().or_else(|| remaining.next()) {
+        Some(initial) => initial,
+        None => {
+            return Err(env.error(
+                ErrorKind::Type,
+                "array.reduce of an empty array needs an initial value".to_string(),
+            ))
+        }
+    };
+    for element in remaining {
+        accumulator = call_back(env, &args[0], vec![accumulator, element])?;
+    }
+    Ok(accumulator)
+}
+
+// The first element the predicate accepts, or null.
+fn find(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.find", &args, 1, 1)?;
+    for element in snapshot(elements) {
+        if call_back(env, &args[0], vec![element.clone()])?.is_truthy() {
+            return Ok(element);
+        }
+    }
+    Ok(Null)
+}
+
+fn any(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.any", &args, 1, 1)?;
+    for element in snapshot(elements) {
+        if call_back(env, &args[0], vec![element])?.is_truthy() {
+            return Ok(Bool(true));
+        }
+    }
+    Ok(Bool(false))
+}
+
+fn all(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.all", &args, 1, 1)?;
+    for element in snapshot(elements) {
+        if !call_back(env, &args[0], vec![element])?.is_truthy() {
+            return Ok(Bool(false));
+        }
+    }
+    Ok(Bool(true))
+}
+
+// Orders two elements, with `comparator(a, b)` returning a negative, zero or positive
+// number when given, and the natural order of numbers or strings otherwise.
+fn compare(
+    env: &mut Environment,
+    comparator: Option<&RuntimeVal>,
+    a: &RuntimeVal,
+    b: &RuntimeVal,
+) -> Result<Ordering, RuntimeError> {
+    let ordering = match comparator {
+        Some(comparator) => match call_back(env, comparator, vec![a.clone(), b.clone()])? {
+            Number(num) => Some(num.cmp(&0)),
+            Float(num) => num.partial_cmp(&0.0),
+            other => {
+                return Err(env.error(
+                    ErrorKind::Type,
+                    format!(
+                        "array.sort comparator must return a number, got {}",
+                        other.preview()
+                    ),
+                ))
+            }
+        },
+        None => match (a, b) {
+            (Number(num1), Number(num2)) => Some(num1.cmp(num2)),
+            (Number(num1), Float(num2)) => (*num1 as f64).partial_cmp(num2),
+            (Float(num1), Number(num2)) => num1.partial_cmp(&(*num2 as f64)),
+            (Float(num1), Float(num2)) => num1.partial_cmp(num2),
+            (RuntimeVal::String(str1), RuntimeVal::String(str2)) => Some(str1.cmp(str2)),
+            _ => None,
+        },
+    };
+    ordering.ok_or_else(|| {
+        env.error(
+            ErrorKind::Type,
+            format!("array.sort cannot compare {} and {}", a, b),
+        )
+    })
+}
+
+// A stable merge sort; unlike `slice::sort_by` it copes with comparators that fail or
+// are inconsistent.
+fn merge_sort(
+    env: &mut Environment,
+    comparator: Option<&RuntimeVal>,
+    values: Vec<RuntimeVal>,
+) -> Result<Vec<RuntimeVal>, RuntimeError> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+    let mut left = values;
+    let right = left.split_off(left.len() / 2);
+    let left = merge_sort(env, comparator, left)?;
+    let right = merge_sort(env, comparator, right)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(env, comparator, a, b)? == Ordering::Greater {
+            merged.push(right.next().unwrap());
+        } else {
+            merged.push(left.next().unwrap());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+// Sorts in place and returns the array.
+fn sort(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.sort", &args, 0, 1)?;
+    let sorted = merge_sort(env, args.first(), snapshot(elements))?;
+    *elements.borrow_mut() = sorted;
+    Ok(RuntimeVal::Array(elements.clone()))
+}
+
+// Reverses in place and returns the array.
+fn reverse(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.reverse", &args, 0, 0)?;
+    elements.borrow_mut().reverse();
+    Ok(RuntimeVal::Array(elements.clone()))
+}
+
+// Pairs elements with those of another array, stopping at the shorter one.
+fn zip(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.zip", &args, 1, 1)?;
+    let other = match &args[0] {
+        RuntimeVal::Array(other) => snapshot(other),
+        other => {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!("array.zip expects an array, got {}", other.preview()),
+            ))
+        }
+    };
+    let pairs = snapshot(elements)
+        .into_iter()
+        .zip(other)
+        .map(|(a, b)| RuntimeVal::array(vec![a, b]))
+        .collect();
+    Ok(RuntimeVal::array(pairs))
+}
+
+// `[[0, first], [1, second], ...]`
+fn enumerate(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.enumerate", &args, 0, 0)?;
+    let pairs = snapshot(elements)
+        .into_iter()
+        .enumerate()
+        .map(|(index, element)| RuntimeVal::array(vec![Number(index as isize), element]))
+        .collect();
+    Ok(RuntimeVal::array(pairs))
+}
+
+// Maps every element and flattens arrays returned by the callback one level.
+fn flat_map(
+    env: &mut Environment,
+    elements: &Elements,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "array.flat_map", &args, 1, 1)?;
+    let mut mapped = vec![];
+    for element in snapshot(elements) {
+        match call_back(env, &args[0], vec![element])? {
+            RuntimeVal::Array(values) => mapped.extend(snapshot(&values)),
+            value => mapped.push(value),
+        }
+    }
+    Ok(RuntimeVal::array(mapped))
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{check_arity, native_function, string_arg};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Bool, Float, Null, Number};
+
+type FsFn = fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+// The `fs` global. Every function checks the environment's capabilities before touching
+// the file system, and OS failures are thrown as catchable IOErrors.
+pub fn module() -> RuntimeVal {
+    let functions: [(&str, FsFn); 8] = [
+        ("read_text", read_text),
+        ("write_text", write_text),
+        ("append", append),
+        ("exists", exists),
+        ("list_dir", list_dir),
+        ("mkdir", mkdir),
+        ("remove", remove),
+        ("stat", stat),
+    ];
+    let mut properties = IndexMap::new();
+    for (name, function) in functions {
+        let qualified = format!("fs.{}", name);
+        properties.insert(
+            name.to_string(),
+            native_function(qualified.as_str(), function),
+        );
+    }
+    RuntimeVal::object(properties)
+}
+
+fn path_arg(env: &Environment, name: &str, value: &RuntimeVal) -> Result<PathBuf, RuntimeError> {
+    Ok(PathBuf::from(string_arg(env, name, value)?))
+}
+
+// The optional boolean flag at `index`, false when omitted.
+fn flag_arg(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+    index: usize,
+) -> Result<bool, RuntimeError> {
+    match args.get(index) {
+        None | Some(Null) => Ok(false),
+        Some(Bool(flag)) => Ok(*flag),
+        Some(other) => Err(env.error(
+            ErrorKind::Type,
+            format!("{} expects a bool, got {}", name, other.preview()),
+        )),
+    }
+}
+
+fn io_error(env: &Environment, action: &str, path: &Path, error: io::Error) -> RuntimeError {
+    env.error(
+        ErrorKind::Io,
+        format!("{} {}: {}", action, path.display(), error),
+    )
+}
+
+fn read_text(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.read_text", &args, 1, 1)?;
+    let path = path_arg(env, "fs.read_text", &args[0])?;
+    env.check_read(&path, "cannot read")?;
+    let file = fs::File::open(&path).map_err(|e| io_error(env, "cannot read", &path, e))?;
+    // The size on disk rules out most files too large for the heap limit before reading;
+    // the read itself is capped too, as files can grow and special files report no size.
+    if let Ok(metadata) = file.metadata() {
+        env.check_allocation(usize::try_from(metadata.len()).unwrap_or(usize::MAX))?;
+    }
+    let bytes = env
+        .read_limited(file)?
+        .map_err(|e| io_error(env, "cannot read", &path, e))?;
+    let text = String::from_utf8(bytes).map_err(|_| {
+        io_error(
+            env,
+            "cannot read",
+            &path,
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ),
+        )
+    })?;
+    Ok(RuntimeVal::String(text))
+}
+
+fn write_text(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.write_text", &args, 2, 2)?;
+    let path = path_arg(env, "fs.write_text", &args[0])?;
+    let text = string_arg(env, "fs.write_text", &args[1])?;
+    env.check_write(&path, "cannot write")?;
+    fs::write(&path, text).map_err(|e| io_error(env, "cannot write", &path, e))?;
+    Ok(Null)
+}
+
+fn append(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.append", &args, 2, 2)?;
+    let path = path_arg(env, "fs.append", &args[0])?;
+    let text = string_arg(env, "fs.append", &args[1])?;
+    env.check_write(&path, "cannot append to")?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| io_error(env, "cannot append to", &path, e))?;
+    Ok(Null)
+}
+
+fn exists(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.exists", &args, 1, 1)?;
+    let path = path_arg(env, "fs.exists", &args[0])?;
+    env.check_read(&path, "cannot read")?;
+    Ok(Bool(path.exists()))
+}
+
+// The names of the entries in a directory, sorted so results don't depend on the OS.
+fn list_dir(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.list_dir", &args, 1, 1)?;
+    let path = path_arg(env, "fs.list_dir", &args[0])?;
+    env.check_read(&path, "cannot list")?;
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<String>>>()
+        })
+        .map_err(|e| io_error(env, "cannot list", &path, e))?;
+    names.sort();
+    Ok(RuntimeVal::array(
+        names.into_iter().map(RuntimeVal::String).collect(),
+    ))
+}
+
+// `fs.mkdir(path, recursive)` also creates missing parents when `recursive` is true.
+fn mkdir(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.mkdir", &args, 1, 2)?;
+    let path = path_arg(env, "fs.mkdir", &args[0])?;
+    let recursive = flag_arg(env, "fs.mkdir", &args, 1)?;
+    env.check_write(&path, "cannot create")?;
+    let result = if recursive {
+        fs::create_dir_all(&path)
+    } else {
+        fs::create_dir(&path)
+    };
+    result.map_err(|e| io_error(env, "cannot create", &path, e))?;
+    Ok(Null)
+}
+
+// `fs.remove(path, recursive)` removes a file or an empty directory, or a whole tree when
+// `recursive` is true.
+fn remove(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.remove", &args, 1, 2)?;
+    let path = path_arg(env, "fs.remove", &args[0])?;
+    let recursive = flag_arg(env, "fs.remove", &args, 1)?;
+    env.check_write(&path, "cannot remove")?;
+    let result = match fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.is_dir() && recursive => fs::remove_dir_all(&path),
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir(&path),
+        Ok(_) => fs::remove_file(&path),
+        Err(error) => Err(error),
+    };
+    result.map_err(|e| io_error(env, "cannot remove", &path, e))?;
+    Ok(Null)
+}
+
+// `{ size, is_file, is_dir, modified }`, where `modified` is in seconds since the Unix epoch.
+fn stat(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "fs.stat", &args, 1, 1)?;
+    let path = path_arg(env, "fs.stat", &args[0])?;
+    env.check_read(&path, "cannot stat")?;
+    let metadata = fs::metadata(&path).map_err(|e| io_error(env, "cannot stat", &path, e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(Null, |duration| Float(duration.as_secs_f64()));
+    Ok(RuntimeVal::object(IndexMap::from([
+        ("size".to_string(), Number(metadata.len() as isize)),
+        ("is_file".to_string(), Bool(metadata.is_file())),
+        ("is_dir".to_string(), Bool(metadata.is_dir())),
+        ("modified".to_string(), modified),
+    ])))
+}
@@ -0,0 +1,466 @@
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::limits::Meter;
+use crate::runtime::native::{check_arity, integer_arg, native_function, string_arg};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Bool, Float, Null, Number};
+
+// Deeper documents are rejected instead of overflowing the Rust stack.
+const MAX_DEPTH: usize = 256;
+
+// The `json` global: `json.parse(text)` and `json.stringify(value, indent)`.
+pub fn module() -> RuntimeVal {
+    RuntimeVal::object(IndexMap::from([
+        ("parse".to_string(), native_function("json.parse", parse)),
+        (
+            "stringify".to_string(),
+            native_function("json.stringify", stringify),
+        ),
+    ]))
+}
+
+fn parse(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "json.parse", &args, 1, 1)?;
+    let text = string_arg(env, "json.parse", &args[0])?;
+    from_json(text.as_str()).map_err(|message| env.error(ErrorKind::Syntax, message))
+}
+
+// Reads a JSON document outside of a program, as the language server reads messages.
+pub(crate) fn from_json(text: &str) -> Result<RuntimeVal, String> {
+    let mut parser = JsonParser {
+        text,
+        chars: text.char_indices().peekable(),
+        depth: 0,
+    };
+    parser.parse_document().map_err(|(position, message)| {
+        let (line, column) = line_and_column(text, position);
+        format!("invalid JSON at {}:{}: {}", line, column, message)
+    })
+}
+
+// Writes a value as compact JSON.
+pub(crate) fn to_json(value: &RuntimeVal) -> Result<String, String> {
+    let mut writer = JsonWriter {
+        out: String::new(),
+        indent: String::new(),
+        open: vec![],
+        meter: None,
+    };
+    writer.write_value(value, 0)?;
+    Ok(writer.out)
+}
+
+fn line_and_column(text: &str, position: usize) -> (usize, usize) {
+    let before = &text[..position];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+// A syntax error: the byte offset it was found at and what went wrong.
+type ParseError = (usize, String);
+
+struct JsonParser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn parse_document(&mut self) -> Result<RuntimeVal, ParseError> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None => Ok(value),
+            Some(&(position, c)) => Err((position, format!("unexpected {:?} after value", c))),
+        }
+    }
+
+    fn position(&mut self) -> usize {
+        match self.chars.peek() {
+            Some(&(position, _)) => position,
+            None => self.text.len(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        let position = self.position();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((_, c)) => Err((position, format!("expected {:?}, found {:?}", expected, c))),
+            None => Err((
+                position,
+                format!("expected {:?}, found end of input", expected),
+            )),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<RuntimeVal, ParseError> {
+        self.skip_whitespace();
+        let position = self.position();
+        match self.chars.peek() {
+            Some((_, '{')) => self.nested(position, Self::parse_object),
+            Some((_, '[')) => self.nested(position, Self::parse_array),
+            Some((_, '"')) => self.parse_string().map(RuntimeVal::String),
+            Some((_, '-' | '0'..='9')) => self.parse_number(),
+            Some((_, 't')) => self.parse_word("true", Bool(true)),
+            Some((_, 'f')) => self.parse_word("false", Bool(false)),
+            Some((_, 'n')) => self.parse_word("null", Null),
+            Some(&(_, c)) => Err((position, format!("unexpected {:?}", c))),
+            None => Err((position, "unexpected end of input".to_string())),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: fn(&mut Self) -> Result<RuntimeVal, ParseError>,
+    ) -> Result<RuntimeVal, ParseError> {
+        if self.depth >= MAX_DEPTH {
+            return Err((
+                position,
+                format!("nesting deeper than {} levels", MAX_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_word(&mut self, word: &str, value: RuntimeVal) -> Result<RuntimeVal, ParseError> {
+        let position = self.position();
+        if !self.text[position..].starts_with(word) {
+            return Err((position, "unexpected token".to_string()));
+        }
+        for _ in 0..word.len() {
+            self.chars.next();
+        }
+        Ok(value)
+    }
+
+    fn parse_object(&mut self) -> Result<RuntimeVal, ParseError> {
+        self.expect('{')?;
+        let mut properties = IndexMap::new();
+        self.skip_whitespace();
+        if let Some((_, '}')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(RuntimeVal::object(properties));
+        }
+        loop {
+            self.skip_whitespace();
+            let position = self.position();
+            if !matches!(self.chars.peek(), Some((_, '"'))) {
+                return Err((position, "expected string key".to_string()));
+            }
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            properties.insert(key, value);
+            self.skip_whitespace();
+            let position = self.position();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(RuntimeVal::object(properties)),
+                _ => return Err((position, "expected ',' or '}' in object".to_string())),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<RuntimeVal, ParseError> {
+        self.expect('[')?;
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if let Some((_, ']')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(RuntimeVal::array(elements));
+        }
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            let position = self.position();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(RuntimeVal::array(elements)),
+                _ => return Err((position, "expected ',' or ']' in array".to_string())),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.chars.next();
+        let mut str = String::new();
+        loop {
+            let position = self.position();
+            match self.chars.next() {
+                None => return Err((position, "unterminated string".to_string())),
+                Some((_, '"')) => return Ok(str),
+                Some((_, '\\')) => {
+                    let escaped = match self.chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/')) => '/',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => self.parse_unicode_escape(position)?,
+                        _ => return Err((position, "invalid escape sequence".to_string())),
+                    };
+                    str.push(escaped);
+                }
+                Some((_, c)) if c < ' ' => {
+                    return Err((position, "control character in string".to_string()))
+                }
+                Some((_, c)) => str.push(c),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self, position: usize) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|(_, c)| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err((position, "invalid unicode escape".to_string())),
+            }
+        }
+        Ok(code)
+    }
+
+    // `\uXXXX`, combining surrogate pairs written as two escapes.
+    fn parse_unicode_escape(&mut self, position: usize) -> Result<char, ParseError> {
+        let high = self.parse_hex4(position)?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.position()..].starts_with("\\u") {
+                return Err((position, "unpaired surrogate in unicode escape".to_string()));
+            }
+            self.chars.next();
+            self.chars.next();
+            let low = self.parse_hex4(position)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err((position, "invalid surrogate pair".to_string()));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or((position, "invalid unicode escape".to_string()))
+    }
+
+    // Whole numbers that fit become integers; anything else a float.
+    fn parse_number(&mut self) -> Result<RuntimeVal, ParseError> {
+        let start = self.position();
+        while let Some((_, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) = self.chars.peek() {
+            self.chars.next();
+        }
+        let literal = &self.text[start..self.position()];
+        if !is_number(literal) {
+            return Err((start, format!("invalid number {}", literal)));
+        }
+        if !literal.contains(['.', 'e', 'E']) {
+            if let Ok(num) = literal.parse::<isize>() {
+                return Ok(Number(num));
+            }
+        }
+        match literal.parse::<f64>() {
+            Ok(num) if num.is_finite() => Ok(Float(num)),
+            _ => Err((start, format!("number {} is out of range", literal))),
+        }
+    }
+}
+
+// Whether `literal` is a number as RFC 8259 writes them:
+// `-? (0 | [1-9][0-9]*) (\.[0-9]+)? ([eE][+-]?[0-9]+)?`.
+fn is_number(literal: &str) -> bool {
+    let (integer, rest) = digits(literal.strip_prefix('-').unwrap_or(literal));
+    if integer.is_empty() || (integer.len() > 1 && integer.starts_with('0')) {
+        return false;
+    }
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => match digits(fraction) {
+            ("", _) => return false,
+            (_, rest) => rest,
+        },
+        None => rest,
+    };
+    let rest = match rest.strip_prefix(['e', 'E']) {
+        Some(exponent) => match digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent)) {
+            ("", _) => return false,
+            (_, rest) => rest,
+        },
+        None => rest,
+    };
+    rest.is_empty()
+}
+
+// Splits the leading ASCII digits off `text`.
+fn digits(text: &str) -> (&str, &str) {
+    text.split_at(
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len()),
+    )
+}
+
+fn stringify(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "json.stringify", &args, 1, 2)?;
+    let indent = match args.get(1) {
+        Some(indent) => integer_arg(env, "json.stringify", indent)?,
+        None => 0,
+    };
+    if !(0..=10).contains(&indent) {
+        return Err(env.error(
+            ErrorKind::Range,
+            format!(
+                "json.stringify indent must be between 0 and 10, got {}",
+                indent
+            ),
+        ));
+    }
+    let mut writer = JsonWriter {
+        out: String::new(),
+        indent: " ".repeat(indent as usize),
+        open: vec![],
+        meter: Some(env.meter()),
+    };
+    let result = writer.write_value(&args[0], 0);
+    match (result, writer.meter.and_then(|meter| meter.exceeded)) {
+        (Ok(()), _) => Ok(RuntimeVal::String(writer.out)),
+        (Err(_), Some(limit)) => Err(RuntimeError::LimitExceeded(limit)),
+        (Err(message), None) => Err(env.error(ErrorKind::Type, message)),
+    }
+}
+
+struct JsonWriter<'a> {
+    out: String,
+    indent: String,
+    // Addresses of the objects and arrays being written, to detect cycles.
+    open: Vec<usize>,
+    // Meters the writing of script values against the run's limits.
+    meter: Option<Meter<'a>>,
+}
+
+impl JsonWriter<'_> {
+    fn write_value(&mut self, value: &RuntimeVal, depth: usize) -> Result<(), String> {
+        if let Some(meter) = &mut self.meter {
+            // Strings are counted before they are written, as one can be large on its own.
+            let pending = match value {
+                RuntimeVal::String(str) => str.len(),
+                _ => 0,
+            };
+            meter
+                .visit(self.out.len() + pending)
+                .map_err(|limit| limit.to_string())?;
+        }
+        match value {
+            Null => self.out.push_str("null"),
+            Bool(b_val) => write!(self.out, "{}", b_val).unwrap(),
+            Number(num) => write!(self.out, "{}", num).unwrap(),
+            Float(num) if num.is_finite() => write!(self.out, "{:?}", num).unwrap(),
+            Float(num) => return Err(format!("cannot serialize {:?} to JSON", num)),
+            RuntimeVal::String(str) => self.write_string(str),
+            // Instances serialize their fields like plain objects.
+            RuntimeVal::Object(object) => {
+                let address = object.as_ptr() as usize;
+                self.enter(address)?;
+                let properties = object.borrow().properties.clone();
+                self.write_items(
+                    '{',
+                    '}',
+                    properties.iter(),
+                    depth,
+                    |writer, (key, value)| {
+                        writer.write_string(key);
+                        writer.out.push(':');
+                        if !writer.indent.is_empty() {
+                            writer.out.push(' ');
+                        }
+                        writer.write_value(value, depth + 1)
+                    },
+                )?;
+                self.open.pop();
+            }
+            RuntimeVal::Array(elements) => {
+                let address = elements.as_ptr() as usize;
+                self.enter(address)?;
+                let elements = elements.borrow().clone();
+                self.write_items('[', ']', elements.iter(), depth, |writer, element| {
+                    writer.write_value(element, depth + 1)
+                })?;
+                self.open.pop();
+            }
+            other => return Err(format!("cannot serialize {} to JSON", other.preview())),
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, address: usize) -> Result<(), String> {
+        if self.open.contains(&address) {
+            return Err("cannot serialize a cyclic structure to JSON".to_string());
+        }
+        self.open.push(address);
+        Ok(())
+    }
+
+    fn write_items<T>(
+        &mut self,
+        open: char,
+        close: char,
+        items: impl Iterator<Item = T>,
+        depth: usize,
+        mut write_item: impl FnMut(&mut Self, T) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.out.push(open);
+        let mut empty = true;
+        for (index, item) in items.enumerate() {
+            empty = false;
+            if index > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            write_item(self, item)?;
+        }
+        if !empty {
+            self.newline(depth);
+        }
+        self.out.push(close);
+        Ok(())
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if !self.indent.is_empty() {
+            self.out.push('\n');
+            self.out.push_str(self.indent.repeat(depth).as_str());
+        }
+    }
+
+    fn write_string(&mut self, str: &str) {
+        self.out.push('"');
+        for c in str.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if c < ' ' => write!(self.out, "\\u{:04x}", c as u32).unwrap(),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
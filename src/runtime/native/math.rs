@@ -0,0 +1,276 @@
+use std::f64::consts::{E, PI};
+
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{check_arity, integer_arg, native_function, number_arg};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Float, Number};
+
+type MathFn = fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+// The `math` global: constants and numeric helpers.
+pub fn module() -> RuntimeVal {
+    let functions: [(&str, MathFn); 19] = [
+        ("abs", abs),
+        ("min", min),
+        ("max", max),
+        ("pow", pow),
+        ("sqrt", sqrt),
+        ("floor", floor),
+        ("ceil", ceil),
+        ("round", round),
+        ("sin", sin),
+        ("cos", cos),
+        ("tan", tan),
+        ("asin", asin),
+        ("acos", acos),
+        ("atan", atan),
+        ("atan2", atan2),
+        ("log", log),
+        ("gcd", gcd),
+        ("clamp", clamp),
+        ("sign", sign),
+    ];
+    let mut properties = IndexMap::new();
+    properties.insert("PI".to_string(), Float(PI));
+    properties.insert("E".to_string(), Float(E));
+    for (name, function) in functions {
+        let qualified = format!("math.{}", name);
+        properties.insert(
+            name.to_string(),
+            native_function(qualified.as_str(), function),
+        );
+    }
+    RuntimeVal::object(properties)
+}
+
+// Applies `function` to the single float argument of `name`.
+fn unary(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+    function: fn(f64) -> f64,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, name, args, 1, 1)?;
+    let result = function(number_arg(env, name, &args[0])?);
+    finite(env, name, args, result)
+}
+
+// The float result of `name` called with `args`, unless it isn't a number. NaN means the
+// arguments are outside the function's domain, a RangeError like `math.sqrt(-1)`; infinity
+// comes from a pole or overflow, an ArithmeticError like division by zero.
+fn finite(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+    result: f64,
+) -> Result<RuntimeVal, RuntimeError> {
+    let call = || {
+        let args: Vec<String> = args.iter().map(RuntimeVal::preview).collect();
+        format!("{}({})", name, args.join(", "))
+    };
+    if result.is_nan() {
+        Err(env.error(ErrorKind::Range, format!("{} is undefined", call())))
+    } else if result.is_infinite() {
+        Err(env.error(ErrorKind::Arithmetic, format!("{} is infinite", call())))
+    } else {
+        Ok(Float(result))
+    }
+}
+
+fn abs(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.abs", &args, 1, 1)?;
+    match &args[0] {
+        Number(num) => num.checked_abs().map(Number).ok_or_else(|| {
+            env.error(
+                ErrorKind::Arithmetic,
+                format!("integer overflow in math.abs({})", num),
+            )
+        }),
+        other => Ok(Float(number_arg(env, "math.abs", other)?.abs())),
+    }
+}
+
+// Returns whichever argument compares best, keeping its integer or float type.
+fn extreme(
+    env: &Environment,
+    name: &str,
+    args: Vec<RuntimeVal>,
+    better: fn(f64, f64) -> bool,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, name, &args, 1, usize::MAX)?;
+    let mut best = args[0].clone();
+    let mut best_value = number_arg(env, name, &best)?;
+    for arg in args.into_iter().skip(1) {
+        let value = number_arg(env, name, &arg)?;
+        if better(value, best_value) {
+            best = arg;
+            best_value = value;
+        }
+    }
+    Ok(best)
+}
+
+fn min(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    extreme(env, "math.min", args, |value, best| value < best)
+}
+
+fn max(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    extreme(env, "math.max", args, |value, best| value > best)
+}
+
+// Integer powers stay integers; anything else is computed in floating point.
+fn pow(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.pow", &args, 2, 2)?;
+    if let (Number(base), Number(exponent)) = (&args[0], &args[1]) {
+        if let Ok(exponent) = u32::try_from(*exponent) {
+            return base.checked_pow(exponent).map(Number).ok_or_else(|| {
+                env.error(
+                    ErrorKind::Arithmetic,
+                    format!("integer overflow in math.pow({}, {})", base, exponent),
+                )
+            });
+        }
+    }
+    let base = number_arg(env, "math.pow", &args[0])?;
+    let exponent = number_arg(env, "math.pow", &args[1])?;
+    finite(env, "math.pow", &args, base.powf(exponent))
+}
+
+fn sqrt(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.sqrt", &args, 1, 1)?;
+    let value = number_arg(env, "math.sqrt", &args[0])?;
+    if value < 0.0 {
+        return Err(env.error(
+            ErrorKind::Range,
+            format!("math.sqrt of negative number {}", args[0]),
+        ));
+    }
+    Ok(Float(value.sqrt()))
+}
+
+// Rounds a float to an integer with `function`; integers are returned unchanged.
+fn to_integer(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+    function: fn(f64) -> f64,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, name, args, 1, 1)?;
+    match &args[0] {
+        Number(num) => Ok(Number(*num)),
+        other => {
+            let value = function(number_arg(env, name, other)?);
+            if !value.is_finite() || value.abs() >= isize::MAX as f64 {
+                return Err(env.error(
+                    ErrorKind::Range,
+                    format!("{} result {} does not fit in an integer", name, other),
+                ));
+            }
+            Ok(Number(value as isize))
+        }
+    }
+}
+
+fn floor(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    to_integer(env, "math.floor", &args, f64::floor)
+}
+
+fn ceil(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    to_integer(env, "math.ceil", &args, f64::ceil)
+}
+
+fn round(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    to_integer(env, "math.round", &args, f64::round)
+}
+
+fn sin(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.sin", &args, f64::sin)
+}
+
+fn cos(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.cos", &args, f64::cos)
+}
+
+fn tan(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.tan", &args, f64::tan)
+}
+
+fn asin(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.asin", &args, f64::asin)
+}
+
+fn acos(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.acos", &args, f64::acos)
+}
+
+fn atan(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.atan", &args, f64::atan)
+}
+
+fn atan2(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.atan2", &args, 2, 2)?;
+    let y = number_arg(env, "math.atan2", &args[0])?;
+    let x = number_arg(env, "math.atan2", &args[1])?;
+    finite(env, "math.atan2", &args, y.atan2(x))
+}
+
+// The natural logarithm.
+fn log(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    unary(env, "math.log", &args, f64::ln)
+}
+
+fn gcd(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.gcd", &args, 2, 2)?;
+    let mut a = integer_arg(env, "math.gcd", &args[0])?.unsigned_abs();
+    let mut b = integer_arg(env, "math.gcd", &args[1])?.unsigned_abs();
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    isize::try_from(a).map(Number).map_err(|_| {
+        env.error(
+            ErrorKind::Arithmetic,
+            format!("integer overflow in math.gcd({}, {})", args[0], args[1]),
+        )
+    })
+}
+
+fn clamp(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.clamp", &args, 3, 3)?;
+    let value = number_arg(env, "math.clamp", &args[0])?;
+    let low = number_arg(env, "math.clamp", &args[1])?;
+    let high = number_arg(env, "math.clamp", &args[2])?;
+    if low > high {
+        return Err(env.error(
+            ErrorKind::Range,
+            format!(
+                "math.clamp lower bound {} is greater than upper bound {}",
+                args[1], args[2]
+            ),
+        ));
+    }
+    let mut args = args.into_iter();
+    let (value_arg, low_arg, high_arg) = (args.next(), args.next(), args.next());
+    let clamped = if value < low {
+        low_arg
+    } else if value > high {
+        high_arg
+    } else {
+        value_arg
+    };
+    Ok(clamped.unwrap())
+}
+
+fn sign(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "math.sign", &args, 1, 1)?;
+    let value = number_arg(env, "math.sign", &args[0])?;
+    Ok(Number(if value > 0.0 {
+        1
+    } else if value < 0.0 {
+        -1
+    } else {
+        0
+    }))
+}
@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::eval::expressions::call_function;
+use crate::runtime::types::{NativeFunction, RuntimeVal};
+
+pub mod array;
+mod fs;
+pub(crate) mod json;
+mod math;
+mod object;
+mod path;
+mod process;
+pub mod string;
+
+pub fn native_function(
+    name: &str,
+    func: impl Fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> + 'static,
+) -> RuntimeVal {
+    RuntimeVal::NativeFunction(Rc::new(NativeFunction {
+        name: name.to_string(),
+        func: Box::new(func),
+    }))
+}
+
+// Fails with a TypeError unless `name` was called with `min` to `max` arguments.
+pub(crate) fn check_arity(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+    min: usize,
+    max: usize,
+) -> Result<(), RuntimeError> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let expected = match (min, max) {
+        (1, 1) => "1 argument".to_string(),
+        (min, max) if min == max => format!("{} arguments", min),
+        (min, usize::MAX) => format!(
+            "at least {} argument{}",
+            min,
+            if min == 1 { "" } else { "s" }
+        ),
+        (min, max) => format!("{} to {} arguments", min, max),
+    };
+    Err(env.error(
+        ErrorKind::Type,
+        format!("{} expects {}, got {}", name, expected, args.len()),
+    ))
+}
+
+pub(crate) fn number_arg(
+    env: &Environment,
+    name: &str,
+    value: &RuntimeVal,
+) -> Result<f64, RuntimeError> {
+    match value {
+        RuntimeVal::Number(num) => Ok(*num as f64),
+        RuntimeVal::Float(num) => Ok(*num),
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} expects a number, got {}", name, other.preview()),
+        )),
+    }
+}
+
+pub(crate) fn integer_arg(
+    env: &Environment,
+    name: &str,
+    value: &RuntimeVal,
+) -> Result<isize, RuntimeError> {
+    match value {
+        RuntimeVal::Number(num) => Ok(*num),
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} expects an integer, got {}", name, other.preview()),
+        )),
+    }
+}
+
+pub(crate) fn string_arg(
+    env: &Environment,
+    name: &str,
+    value: &RuntimeVal,
+) -> Result<String, RuntimeError> {
+    match value {
+        RuntimeVal::String(str) => Ok(str.clone()),
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} expects a string, got {}", name, other.preview()),
+        )),
+    }
+}
+
+// Calls a script or native function handed to a native, such as a `map` callback.
+pub(crate) fn call_back(
+    env: &mut Environment,
+    function: &RuntimeVal,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let call_site = env.call_site();
+    call_function(env, function.clone(), args, call_site)
+}
+
+pub fn declare_globals(env: &mut Environment) {
+    env.declare_variable("print", native_function("print", print), true)
+        .unwrap();
+    env.declare_variable("math", math::module(), true).unwrap();
+    env.declare_variable("json", json::module(), true).unwrap();
+    env.declare_variable("fs", fs::module(), true).unwrap();
+    env.declare_variable("path", path::module(), true).unwrap();
+    env.declare_variable("env", process::env_module(), true)
+        .unwrap();
+    env.declare_variable("stdin", process::stdin_module(), true)
+        .unwrap();
+    env.declare_variable("stderr", process::stderr_module(), true)
+        .unwrap();
+    env.declare_variable("process", process::process_module(), true)
+        .unwrap();
+    env.declare_variable("exit", native_function("exit", process::exit), true)
+        .unwrap();
+    env.declare_variable("Object", object::module(), true)
+        .unwrap();
+}
+
+fn print(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let line = args
+        .iter()
+        .map(|arg| arg.display(env))
+        .collect::<Result<Vec<_>, _>>()?;
+    println!("{}", line.join(" "));
+    Ok(RuntimeVal::Null)
+}
@@ -0,0 +1,61 @@
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{check_arity, native_function};
+use crate::runtime::types::RuntimeVal;
+
+type ObjectFn = fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+// The `Object` global: `Object.keys(value)`, `Object.values(value)`, `Object.entries(value)`.
+pub fn module() -> RuntimeVal {
+    let functions: [(&str, ObjectFn); 3] =
+        [("keys", keys), ("values", values), ("entries", entries)];
+    let mut properties = IndexMap::new();
+    for (name, function) in functions {
+        let qualified = format!("Object.{}", name);
+        properties.insert(
+            name.to_string(),
+            native_function(qualified.as_str(), function),
+        );
+    }
+    RuntimeVal::object(properties)
+}
+
+// The own properties of the single object argument, in insertion order.
+fn properties(
+    env: &Environment,
+    name: &str,
+    args: &[RuntimeVal],
+) -> Result<IndexMap<String, RuntimeVal>, RuntimeError> {
+    check_arity(env, name, args, 1, 1)?;
+    match &args[0] {
+        RuntimeVal::Object(object) => Ok(object.borrow().properties.clone()),
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("{} expects an object, got {}", name, other.preview()),
+        )),
+    }
+}
+
+fn keys(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let properties = properties(env, "Object.keys", &args)?;
+    Ok(RuntimeVal::array(
+        properties.into_keys().map(RuntimeVal::String).collect(),
+    ))
+}
+
+fn values(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let properties = properties(env, "Object.values", &args)?;
+    Ok(RuntimeVal::array(properties.into_values().collect()))
+}
+
+fn entries(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let properties = properties(env, "Object.entries", &args)?;
+    Ok(RuntimeVal::array(
+        properties
+            .into_iter()
+            .map(|(key, value)| RuntimeVal::array(vec![RuntimeVal::String(key), value]))
+            .collect(),
+    ))
+}
@@ -0,0 +1,108 @@
+use std::path::{Component, Path, PathBuf};
+
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::native::{check_arity, native_function, string_arg};
+use crate::runtime::types::RuntimeVal;
+
+type PathFn = fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+// The `path` global. Paths are plain strings and nothing here touches the file system, so
+// none of these need capabilities.
+pub fn module() -> RuntimeVal {
+    let functions: [(&str, PathFn); 5] = [
+        ("join", join),
+        ("dirname", dirname),
+        ("basename", basename),
+        ("extension", extension),
+        ("normalize", normalize),
+    ];
+    let mut properties = IndexMap::new();
+    for (name, function) in functions {
+        let qualified = format!("path.{}", name);
+        properties.insert(
+            name.to_string(),
+            native_function(qualified.as_str(), function),
+        );
+    }
+    RuntimeVal::object(properties)
+}
+
+fn path_string(path: &Path) -> RuntimeVal {
+    RuntimeVal::String(path.to_string_lossy().into_owned())
+}
+
+// The single path argument of `name`.
+fn path_arg(env: &Environment, name: &str, args: &[RuntimeVal]) -> Result<PathBuf, RuntimeError> {
+    check_arity(env, name, args, 1, 1)?;
+    Ok(PathBuf::from(string_arg(env, name, &args[0])?))
+}
+
+// Like `PathBuf::push`, an absolute part replaces everything before it.
+fn join(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "path.join", &args, 1, usize::MAX)?;
+    let mut path = PathBuf::new();
+    for arg in &args {
+        path.push(string_arg(env, "path.join", arg)?);
+    }
+    Ok(path_string(&path))
+}
+
+// "." for a bare file name, and the root itself for the root.
+fn dirname(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let path = path_arg(env, "path.dirname", &args)?;
+    Ok(match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => path_string(Path::new(".")),
+        Some(parent) => path_string(parent),
+        None if path.has_root() => path_string(&path),
+        None => path_string(Path::new(".")),
+    })
+}
+
+fn basename(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let path = path_arg(env, "path.basename", &args)?;
+    Ok(RuntimeVal::String(
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    ))
+}
+
+// The extension without its dot, or "" when there is none.
+fn extension(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let path = path_arg(env, "path.extension", &args)?;
+    Ok(RuntimeVal::String(
+        path.extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    ))
+}
+
+// Removes `.` segments and folds `..` into the segment before it, without resolving
+// symlinks. Leading `..` segments of a relative path are kept; at the root they are dropped.
+fn normalize(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let path = path_arg(env, "path.normalize", &args)?;
+    let mut normalized = PathBuf::new();
+    let mut depth = 0;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => {
+                normalized.pop();
+                depth -= 1;
+            }
+            Component::ParentDir if normalized.has_root() => {}
+            Component::Normal(_) => {
+                normalized.push(component);
+                depth += 1;
+            }
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    Ok(path_string(&normalized))
+}
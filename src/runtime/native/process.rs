@@ -0,0 +1,248 @@
+use std::io;
+use std::io::{BufRead, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use indexmap::IndexMap;
+
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{check_arity, integer_arg, native_function, string_arg};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Null, Number};
+
+// How often `process.run` checks on the command it is waiting for.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+// The `env` global: `env.get(name)` is the value of an environment variable, or null when
+// it is unset.
+pub fn env_module() -> RuntimeVal {
+    RuntimeVal::object(IndexMap::from([(
+        "get".to_string(),
+        native_function("env.get", env_get),
+    )]))
+}
+
+// The `stdin` global: `stdin.read_line()` and `stdin.lines()`.
+pub fn stdin_module() -> RuntimeVal {
+    RuntimeVal::object(IndexMap::from([
+        (
+            "read_line".to_string(),
+            native_function("stdin.read_line", read_line),
+        ),
+        ("lines".to_string(), native_function("stdin.lines", lines)),
+    ]))
+}
+
+// The `stderr` global: `stderr.print(...)` like `print`, and `stderr.write(text)` without
+// the trailing newline.
+pub fn stderr_module() -> RuntimeVal {
+    RuntimeVal::object(IndexMap::from([
+        (
+            "print".to_string(),
+            native_function("stderr.print", stderr_print),
+        ),
+        (
+            "write".to_string(),
+            native_function("stderr.write", stderr_write),
+        ),
+    ]))
+}
+
+// The `process` global: `process.run(command, args)`.
+pub fn process_module() -> RuntimeVal {
+    RuntimeVal::object(IndexMap::from([(
+        "run".to_string(),
+        native_function("process.run", run),
+    )]))
+}
+
+fn env_get(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "env.get", &args, 1, 1)?;
+    let name = string_arg(env, "env.get", &args[0])?;
+    env.check_env_var(name.as_str())?;
+    Ok(std::env::var(name).map_or(Null, RuntimeVal::String))
+}
+
+// `exit(code)` stops the program with `code`, 0 when omitted. Like a limit being reached
+// it cannot be caught, though `finally` blocks still run.
+pub fn exit(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "exit", &args, 0, 1)?;
+    let code = match args.first() {
+        Some(code) => integer_arg(env, "exit", code)?,
+        None => 0,
+    };
+    match i32::try_from(code) {
+        Ok(code) => Err(RuntimeError::Exit(code)),
+        Err(_) => Err(env.error(
+            ErrorKind::Range,
+            format!("exit code {} is out of range", code),
+        )),
+    }
+}
+
+fn read_stdin_line(env: &Environment, name: &str) -> Result<Option<String>, RuntimeError> {
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|error| env.error(ErrorKind::Io, format!("{}: {}", name, error)))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// The next line of input without its line ending, or null once stdin is closed.
+fn read_line(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "stdin.read_line", &args, 0, 0)?;
+    env.check_stdin()?;
+    Ok(read_stdin_line(env, "stdin.read_line")?.map_or(Null, RuntimeVal::String))
+}
+
+// Every remaining line of input.
+fn lines(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "stdin.lines", &args, 0, 0)?;
+    env.check_stdin()?;
+    let mut lines = vec![];
+    while let Some(line) = read_stdin_line(env, "stdin.lines")? {
+        lines.push(env.allocated(RuntimeVal::String(line))?);
+    }
+    Ok(RuntimeVal::array(lines))
+}
+
+fn stderr_print(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    let line = args
+        .iter()
+        .map(|arg| arg.display(env))
+        .collect::<Result<Vec<_>, _>>()?;
+    eprintln!("{}", line.join(" "));
+    Ok(Null)
+}
+
+fn stderr_write(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "stderr.write", &args, 1, 1)?;
+    let text = string_arg(env, "stderr.write", &args[0])?;
+    let mut stderr = io::stderr();
+    stderr
+        .write_all(text.as_bytes())
+        .and_then(|_| stderr.flush())
+        .map_err(|error| env.error(ErrorKind::Io, format!("stderr.write: {}", error)))?;
+    Ok(Null)
+}
+
+// Runs `command` to completion with the given argument strings and returns
+// `{ stdout, stderr, status }`. `status` is null when the command was killed by a signal.
+fn run(env: &mut Environment, args: Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "process.run", &args, 1, 2)?;
+    let command = string_arg(env, "process.run", &args[0])?;
+    let arguments = match args.get(1) {
+        None | Some(Null) => vec![],
+        Some(RuntimeVal::Array(elements)) => elements
+            .borrow()
+            .iter()
+            .map(|element| string_arg(env, "process.run", element))
+            .collect::<Result<Vec<String>, RuntimeError>>()?,
+        Some(other) => {
+            return Err(env.error(
+                ErrorKind::Type,
+                format!(
+                    "process.run expects an array of arguments, got {}",
+                    other.preview()
+                ),
+            ))
+        }
+    };
+    env.check_run(command.as_str())?;
+    let mut child = Command::new(&command)
+        .args(&arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| env.error(ErrorKind::Io, format!("cannot run {}: {}", command, error)))?;
+    // Both pipes are read at once, so the child can't block on a full one, and the readers
+    // stop once their output together no longer fits in the heap limit.
+    let limit = env.heap_left().unwrap_or(usize::MAX);
+    let read = Arc::new(AtomicUsize::new(0));
+    let stdout = capture(child.stdout.take(), limit, read.clone());
+    let stderr = capture(child.stderr.take(), limit, read.clone());
+    let status = loop {
+        let within_limits = env
+            .check_allocation(read.load(Ordering::Relaxed))
+            .and_then(|_| env.check_time());
+        if let Err(error) = within_limits {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(error);
+        }
+        // A process it started may hold the pipes open after it exits, so the readers are
+        // waited for against the limits too.
+        match child.try_wait() {
+            Ok(Some(status)) if stdout.is_finished() && stderr.is_finished() => break status,
+            Ok(_) => thread::sleep(POLL_INTERVAL),
+            Err(error) => {
+                let _ = child.kill();
+                return Err(env.error(ErrorKind::Io, format!("cannot run {}: {}", command, error)));
+            }
+        }
+    };
+    let [stdout, stderr] = [stdout, stderr].map(|reader| match reader.join() {
+        Ok(Ok(bytes)) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        Ok(Err(error)) => Err(env.error(
+            ErrorKind::Io,
+            format!("cannot read the output of {}: {}", command, error),
+        )),
+        Err(_) => Err(env.error(
+            ErrorKind::Io,
+            format!("cannot read the output of {}", command),
+        )),
+    });
+    env.check_allocation(read.load(Ordering::Relaxed))?;
+    Ok(RuntimeVal::object(IndexMap::from([
+        ("stdout".to_string(), RuntimeVal::String(stdout?)),
+        ("stderr".to_string(), RuntimeVal::String(stderr?)),
+        (
+            "status".to_string(),
+            status.code().map_or(Null, |code| Number(code as isize)),
+        ),
+    ])))
+}
+
+// Reads a pipe of a child process on its own thread until it closes, adding what it
+// reads to `read` and stopping once that is more than `limit`.
+fn capture(
+    pipe: Option<impl Read + Send + 'static>,
+    limit: usize,
+    read: Arc<AtomicUsize>,
+) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        let Some(mut pipe) = pipe else {
+            return Ok(bytes);
+        };
+        let mut buffer = [0; 8192];
+        loop {
+            let count = match pipe.read(&mut buffer) {
+                Ok(0) => return Ok(bytes),
+                Ok(count) => count,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            bytes.extend_from_slice(&buffer[..count]);
+            if read.fetch_add(count, Ordering::Relaxed) + count > limit {
+                return Ok(bytes);
+            }
+        }
+    })
+}
@@ -0,0 +1,356 @@
+use crate::runtime::environment::Environment;
+use crate::runtime::error::{ErrorKind, RuntimeError};
+use crate::runtime::native::{check_arity, integer_arg, native_function, string_arg};
+use crate::runtime::types::RuntimeVal;
+use crate::runtime::types::RuntimeVal::{Bool, Float, Number};
+
+// A string method receives the string it was called on and its own arguments.
+type StringMethod = fn(&mut Environment, &str, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+const METHODS: [(&str, StringMethod); 18] = [
+    ("len", len),
+    ("split", split),
+    ("join", join),
+    ("trim", trim),
+    ("upper", upper),
+    ("lower", lower),
+    ("replace", replace),
+    ("starts_with", starts_with),
+    ("ends_with", ends_with),
+    ("contains", contains),
+    ("index_of", index_of),
+    ("slice", slice),
+    ("pad_start", pad_start),
+    ("pad_end", pad_end),
+    ("repeat", repeat),
+    ("chars", chars),
+    ("parse_int", parse_int),
+    ("parse_float", parse_float),
+];
+
+// Looks up a string method as a native function taking the string as its first argument,
+// ready to be bound to a receiver.
+pub fn method(name: &str) -> Option<RuntimeVal> {
+    let (name, method) = *METHODS.iter().find(|(method, _)| *method == name)?;
+    let qualified = format!("string.{}", name);
+    Some(native_function(
+        qualified.as_str(),
+        move |env, mut args| match args.remove(0) {
+            RuntimeVal::String(receiver) => method(env, receiver.as_str(), args),
+            other => Err(env.error(
+                ErrorKind::Type,
+                format!("string.{} called on {}", name, other.preview()),
+            )),
+        },
+    ))
+}
+
+// Resolves a possibly negative character index against a string of `len` characters,
+// clamping it to the string.
+fn clamp_index(index: isize, len: usize) -> usize {
+    if index < 0 {
+        len.saturating_sub(index.unsigned_abs())
+    } else {
+        (index as usize).min(len)
+    }
+}
+
+// The parts as an array of strings, checking first that the array and its strings fit in
+// the heap limit; the parts are slices of `str`, so their text is at most its length.
+fn strings<'a>(
+    env: &Environment,
+    str: &str,
+    parts: impl Iterator<Item = &'a str> + Clone,
+) -> Result<RuntimeVal, RuntimeError> {
+    let count = parts.clone().count();
+    env.check_allocation(str.len() + count.saturating_mul(size_of::<RuntimeVal>()))?;
+    Ok(RuntimeVal::array(
+        parts
+            .map(|part| RuntimeVal::String(part.to_string()))
+            .collect(),
+    ))
+}
+
+// Each character of `str` as a slice of it.
+fn characters(str: &str) -> impl Iterator<Item = &str> + Clone {
+    str.char_indices()
+        .map(move |(index, char)| &str[index..index + char.len_utf8()])
+}
+
+fn len(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.len", &args, 0, 0)?;
+    Ok(Number(str.chars().count() as isize))
+}
+
+// Without a separator splits on whitespace; an empty separator splits into characters.
+fn split(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.split", &args, 0, 1)?;
+    match args.first() {
+        None => strings(env, str, str.split_whitespace()),
+        Some(separator) => match string_arg(env, "string.split", separator)?.as_str() {
+            "" => strings(env, str, characters(str)),
+            separator => strings(env, str, str.split(separator)),
+        },
+    }
+}
+
+// `", ".join(values)` concatenates the values with the string between them.
+fn join(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.join", &args, 1, 1)?;
+    match &args[0] {
+        RuntimeVal::Array(values) => {
+            let mut joined = String::new();
+            for (index, value) in values.borrow().iter().enumerate() {
+                if index > 0 {
+                    joined.push_str(str);
+                }
+                joined.push_str(value.display(env)?.as_str());
+                env.check_allocation(joined.len())?;
+            }
+            Ok(RuntimeVal::String(joined))
+        }
+        other => Err(env.error(
+            ErrorKind::Type,
+            format!("string.join expects an array, got {}", other.preview()),
+        )),
+    }
+}
+
+fn trim(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.trim", &args, 0, 0)?;
+    Ok(RuntimeVal::String(str.trim().to_string()))
+}
+
+fn upper(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.upper", &args, 0, 0)?;
+    Ok(RuntimeVal::String(str.to_uppercase()))
+}
+
+fn lower(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.lower", &args, 0, 0)?;
+    Ok(RuntimeVal::String(str.to_lowercase()))
+}
+
+// Replaces every occurrence of the pattern.
+fn replace(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.replace", &args, 2, 2)?;
+    let from = string_arg(env, "string.replace", &args[0])?;
+    let to = string_arg(env, "string.replace", &args[1])?;
+    if from.is_empty() {
+        return Err(env.error(
+            ErrorKind::Value,
+            "string.replace pattern must not be empty".to_string(),
+        ));
+    }
+    let matches = str.matches(from.as_str()).count();
+    env.check_allocation(str.len() - matches * from.len() + matches.saturating_mul(to.len()))?;
+    Ok(RuntimeVal::String(str.replace(from.as_str(), to.as_str())))
+}
+
+fn starts_with(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.starts_with", &args, 1, 1)?;
+    let prefix = string_arg(env, "string.starts_with", &args[0])?;
+    Ok(Bool(str.starts_with(prefix.as_str())))
+}
+
+fn ends_with(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.ends_with", &args, 1, 1)?;
+    let suffix = string_arg(env, "string.ends_with", &args[0])?;
+    Ok(Bool(str.ends_with(suffix.as_str())))
+}
+
+fn contains(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.contains", &args, 1, 1)?;
+    let part = string_arg(env, "string.contains", &args[0])?;
+    Ok(Bool(str.contains(part.as_str())))
+}
+
+// The character index of the first occurrence, or -1.
+fn index_of(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.index_of", &args, 1, 1)?;
+    let part = string_arg(env, "string.index_of", &args[0])?;
+    let index = match str.find(part.as_str()) {
+        Some(byte_index) => str[..byte_index].chars().count() as isize,
+        None => -1,
+    };
+    Ok(Number(index))
+}
+
+// `slice(start, end)` by character; negative indices count from the end.
+fn slice(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.slice", &args, 1, 2)?;
+    let len = str.chars().count();
+    let start = clamp_index(integer_arg(env, "string.slice", &args[0])?, len);
+    let end = match args.get(1) {
+        Some(end) => clamp_index(integer_arg(env, "string.slice", end)?, len),
+        None => len,
+    };
+    let sliced: String = str
+        .chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect();
+    Ok(RuntimeVal::String(sliced))
+}
+
+// Repeats `fill` (a space by default) until the string is `width` characters long.
+fn padding(
+    env: &Environment,
+    name: &str,
+    str: &str,
+    args: &[RuntimeVal],
+) -> Result<String, RuntimeError> {
+    check_arity(env, name, args, 1, 2)?;
+    let width = integer_arg(env, name, &args[0])?;
+    let fill = match args.get(1) {
+        Some(fill) => string_arg(env, name, fill)?,
+        None => " ".to_string(),
+    };
+    if fill.is_empty() {
+        return Err(env.error(ErrorKind::Value, format!("{} fill must not be empty", name)));
+    }
+    let missing = usize::try_from(width)
+        .unwrap_or(0)
+        .saturating_sub(str.chars().count());
+    env.check_allocation(missing.saturating_mul(4))?;
+    Ok(fill.chars().cycle().take(missing).collect())
+}
+
+fn pad_start(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let padding = padding(env, "string.pad_start", str, &args)?;
+    Ok(RuntimeVal::String(padding + str))
+}
+
+fn pad_end(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    let padding = padding(env, "string.pad_end", str, &args)?;
+    Ok(RuntimeVal::String(str.to_string() + padding.as_str()))
+}
+
+fn repeat(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.repeat", &args, 1, 1)?;
+    let count = integer_arg(env, "string.repeat", &args[0])?;
+    match usize::try_from(count) {
+        Ok(count) => {
+            env.check_allocation(str.len().saturating_mul(count))?;
+            Ok(RuntimeVal::String(str.repeat(count)))
+        }
+        Err(_) => Err(env.error(
+            ErrorKind::Range,
+            format!("string.repeat count must not be negative, got {}", count),
+        )),
+    }
+}
+
+// The characters of the string, each as a one character string.
+fn chars(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.chars", &args, 0, 0)?;
+    strings(env, str, characters(str))
+}
+
+// `parse_int(radix)` with radix 10 by default; surrounding whitespace is ignored.
+fn parse_int(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.parse_int", &args, 0, 1)?;
+    let radix = match args.first() {
+        Some(radix) => integer_arg(env, "string.parse_int", radix)?,
+        None => 10,
+    };
+    if !(2..=36).contains(&radix) {
+        return Err(env.error(
+            ErrorKind::Range,
+            format!(
+                "string.parse_int radix must be between 2 and 36, got {}",
+                radix
+            ),
+        ));
+    }
+    match isize::from_str_radix(str.trim(), radix as u32) {
+        Ok(num) => Ok(Number(num)),
+        Err(_) => Err(env.error(
+            ErrorKind::Value,
+            format!("cannot parse {:?} as an integer", str),
+        )),
+    }
+}
+
+fn parse_float(
+    env: &mut Environment,
+    str: &str,
+    args: Vec<RuntimeVal>,
+) -> Result<RuntimeVal, RuntimeError> {
+    check_arity(env, "string.parse_float", &args, 0, 0)?;
+    match str.trim().parse::<f64>() {
+        Ok(num) => Ok(Float(num)),
+        Err(_) => Err(env.error(
+            ErrorKind::Value,
+            format!("cannot parse {:?} as a number", str),
+        )),
+    }
+}
@@ -1,21 +1,555 @@
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter, Write};
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+use indexmap::IndexMap;
+
+use crate::frontend::ast::{Ast, Pattern};
+use crate::frontend::lexer;
+use crate::runtime::environment::Environment;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::interpreter::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::runtime::limits::Meter;
+
+#[derive(Debug, Clone)]
 pub enum RuntimeVal {
     Number(isize),
+    Float(f64),
     Null,
     Bool(bool),
-    Object(HashMap<String, RuntimeVal>),
+    String(String),
+    Object(Rc<RefCell<Object>>),
+    Function(Rc<Function>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<Class>),
+    BoundMethod(Rc<BoundMethod>),
+    Array(Rc<RefCell<Vec<RuntimeVal>>>),
+    Enum(Rc<Enum>),
+    Variant(Rc<Variant>),
+}
+
+impl RuntimeVal {
+    pub fn object(properties: IndexMap<String, RuntimeVal>) -> Self {
+        RuntimeVal::Object(Rc::new(RefCell::new(Object {
+            class: None,
+            properties,
+        })))
+    }
+
+    pub fn array(elements: Vec<RuntimeVal>) -> Self {
+        RuntimeVal::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    // The kind of value, as named in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RuntimeVal::Number(_) => "int",
+            RuntimeVal::Float(_) => "float",
+            RuntimeVal::Null => "null",
+            RuntimeVal::Bool(_) => "bool",
+            RuntimeVal::String(_) => "string",
+            RuntimeVal::Object(_) => "object",
+            RuntimeVal::Function(_) | RuntimeVal::NativeFunction(_) => "function",
+            RuntimeVal::Class(_) => "class",
+            RuntimeVal::BoundMethod(_) => "method",
+            RuntimeVal::Array(_) => "array",
+            RuntimeVal::Enum(_) => "enum",
+            RuntimeVal::Variant(_) => "variant",
+        }
+    }
+
+    // `null` and `false` are the only falsy values.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, RuntimeVal::Null | RuntimeVal::Bool(false))
+    }
+}
+
+// Objects are shared by reference; `class` is set for instances created by calling a class.
+#[derive(Debug)]
+pub struct Object {
+    pub class: Option<Rc<Class>>,
+    pub properties: IndexMap<String, RuntimeVal>,
+}
+
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<Pattern>,
+    pub body: Vec<Ast>,
+    pub closure: Environment,
+    // Declared as a method of a class or object literal. Reading it from an object binds
+    // `this` to that object.
+    pub method: bool,
+}
+
+pub type NativeFn = dyn Fn(&mut Environment, Vec<RuntimeVal>) -> Result<RuntimeVal, RuntimeError>;
+
+pub struct NativeFunction {
+    pub name: String,
+    pub func: Box<NativeFn>,
+}
+
+pub struct Class {
+    pub name: String,
+    pub parent: Option<Rc<Class>>,
+    pub fields: Vec<(String, Option<Ast>)>,
+    pub methods: IndexMap<String, Rc<Function>>,
+    pub closure: Environment,
+}
+
+impl Class {
+    // Finds a method on this class or the closest ancestor defining it.
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.find_method(name)),
+        }
+    }
+}
+
+pub struct Enum {
+    pub name: String,
+    // Variant name to the names of its fields, in declaration order.
+    pub variants: IndexMap<String, Vec<String>>,
+}
+
+// A value of an enum: `Shape.Circle(5)`.
+#[derive(Debug)]
+pub struct Variant {
+    pub enumeration: Rc<Enum>,
+    pub name: String,
+    pub values: Vec<RuntimeVal>,
+}
+
+impl Variant {
+    pub fn field(&self, name: &str) -> Option<RuntimeVal> {
+        let fields = self.enumeration.variants.get(self.name.as_str())?;
+        let index = fields.iter().position(|field| field == name)?;
+        self.values.get(index).cloned()
+    }
+}
+
+// A method together with the value `this` refers to while it runs. Native methods
+// receive the receiver as their first argument.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: RuntimeVal,
+    pub method: RuntimeVal,
+}
+
+// Strings, arrays, objects and variants compare by their contents. Objects and arrays can
+// contain themselves, so a pair already being compared further up counts as equal.
+impl PartialEq for RuntimeVal {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut Comparing::default())
+    }
+}
+
+// The pairs of objects or arrays met while comparing two values.
+#[derive(Default)]
+struct Comparing {
+    open: Vec<(usize, usize)>,
+    // Pairs found equal, each compared only once even where the values share them many
+    // times over.
+    equal: HashSet<(usize, usize)>,
+}
+
+impl RuntimeVal {
+    fn equals(&self, other: &Self, comparing: &mut Comparing) -> bool {
+        let pair = match (self, other) {
+            (RuntimeVal::Object(a), RuntimeVal::Object(b)) => {
+                (Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)
+            }
+            (RuntimeVal::Array(a), RuntimeVal::Array(b)) => {
+                (Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)
+            }
+            _ => return self.equal_contents(other, comparing),
+        };
+        if pair.0 == pair.1 || comparing.open.contains(&pair) || comparing.equal.contains(&pair) {
+            return true;
+        }
+        comparing.open.push(pair);
+        let equal = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.equal_contents(other, comparing)
+        });
+        comparing.open.pop();
+        if equal {
+            comparing.equal.insert(pair);
+        }
+        equal
+    }
+
+    fn equal_contents(&self, other: &Self, comparing: &mut Comparing) -> bool {
+        match (self, other) {
+            (RuntimeVal::Number(a), RuntimeVal::Number(b)) => a == b,
+            (RuntimeVal::Float(a), RuntimeVal::Float(b)) => a == b,
+            (RuntimeVal::Null, RuntimeVal::Null) => true,
+            (RuntimeVal::Bool(a), RuntimeVal::Bool(b)) => a == b,
+            (RuntimeVal::String(a), RuntimeVal::String(b)) => a == b,
+            (RuntimeVal::Object(a), RuntimeVal::Object(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.class == b.class
+                    && a.properties.len() == b.properties.len()
+                    && a.properties.iter().all(|(key, value)| {
+                        b.properties
+                            .get(key)
+                            .is_some_and(|other| value.equals(other, comparing))
+                    })
+            }
+            (RuntimeVal::Array(a), RuntimeVal::Array(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b, comparing))
+            }
+            (RuntimeVal::Variant(a), RuntimeVal::Variant(b)) => {
+                a.enumeration == b.enumeration
+                    && a.name == b.name
+                    && a.values.len() == b.values.len()
+                    && a.values
+                        .iter()
+                        .zip(&b.values)
+                        .all(|(a, b)| a.equals(b, comparing))
+            }
+            (RuntimeVal::BoundMethod(a), RuntimeVal::BoundMethod(b)) => {
+                a.receiver.equals(&b.receiver, comparing) && a.method.equals(&b.method, comparing)
+            }
+            (RuntimeVal::Function(a), RuntimeVal::Function(b)) => a == b,
+            (RuntimeVal::NativeFunction(a), RuntimeVal::NativeFunction(b)) => a == b,
+            (RuntimeVal::Class(a), RuntimeVal::Class(b)) => a == b,
+            (RuntimeVal::Enum(a), RuntimeVal::Enum(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// Functions and classes compare by identity, like objects in most scripting languages.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Function {}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for NativeFunction {}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Class {}
+
+impl Debug for Class {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Class({})", self.name)
+    }
+}
+
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Enum {}
+
+impl Debug for Enum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Enum({})", self.name)
+    }
+}
+
+// Objects whose single line rendering is wider than this are printed over several lines.
+const MAX_INLINE_WIDTH: usize = 80;
+const INDENT: &str = "    ";
+
+// How a value is laid out over lines when written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    // Each object and array on one line if it fits, otherwise over several.
+    Fit,
+    // Each object and array over several lines.
+    Pretty,
+    // On one line, stopping once wider than `MAX_INLINE_WIDTH`: written only to find out
+    // whether a value fits.
+    Inline,
+}
+
+// Writing out one value.
+struct Writing<'a> {
+    // The objects and arrays being written; one containing itself is written as
+    // `[Circular]` inside itself.
+    open: Vec<usize>,
+    meter: Option<Meter<'a>>,
+}
+
+impl RuntimeVal {
+    // Renders the value as `Display` does, failing with `LimitExceeded` once that takes
+    // more time or memory than the run has left.
+    pub(crate) fn display(&self, env: &Environment) -> Result<String, RuntimeError> {
+        match self {
+            RuntimeVal::String(str) => Ok(str.clone()),
+            RuntimeVal::Object(_) | RuntimeVal::Array(_) | RuntimeVal::Variant(_) => {
+                let mut out = String::new();
+                let mut writing = Writing {
+                    open: vec![],
+                    meter: Some(env.meter()),
+                };
+                // Writing to a string only fails when the meter stops it.
+                match self.write_literal(&mut out, Layout::Fit, 0, &mut writing) {
+                    Ok(()) => Ok(out),
+                    Err(_) => Err(RuntimeError::LimitExceeded(
+                        writing.meter.and_then(|meter| meter.exceeded).unwrap(),
+                    )),
+                }
+            }
+            value => Ok(value.to_string()),
+        }
+    }
+
+    // A short rendering for error messages: one line, cut off once it is wider than
+    // `MAX_INLINE_WIDTH`, so that it takes little time however large the value is.
+    pub fn preview(&self) -> String {
+        let mut out = match self {
+            RuntimeVal::String(str) => str.chars().take(MAX_INLINE_WIDTH + 1).collect(),
+            RuntimeVal::Object(_) | RuntimeVal::Array(_) | RuntimeVal::Variant(_) => {
+                let mut out = String::new();
+                let mut writing = Writing {
+                    open: vec![],
+                    meter: None,
+                };
+                let _ = self.write_literal(&mut out, Layout::Inline, 0, &mut writing);
+                out
+            }
+            value => value.to_string(),
+        };
+        if out.chars().count() > MAX_INLINE_WIDTH {
+            out = out.chars().take(MAX_INLINE_WIDTH - 3).collect();
+            out.push_str("...");
+        }
+        out
+    }
+
+    // Renders the value the way it would be written in a v-lang literal.
+    fn write_literal(
+        &self,
+        out: &mut String,
+        layout: Layout,
+        depth: usize,
+        writing: &mut Writing,
+    ) -> std::fmt::Result {
+        if let Some(meter) = &mut writing.meter {
+            meter.visit(out.len()).map_err(|_| std::fmt::Error)?;
+        }
+        let container = match self {
+            RuntimeVal::Object(object) => Rc::as_ptr(object) as usize,
+            RuntimeVal::Array(elements) => Rc::as_ptr(elements) as usize,
+            value => return value.write_contents(out, layout, depth, writing),
+        };
+        if writing.open.contains(&container) {
+            return write!(out, "[Circular]");
+        }
+        writing.open.push(container);
+        let result = match layout {
+            Layout::Fit => self.write_fitted(out, depth, writing),
+            layout => self.write_contents(out, layout, depth, writing),
+        };
+        writing.open.pop();
+        result
+    }
+
+    // Writes an object or array on one line if it fits, otherwise over several.
+    fn write_fitted(
+        &self,
+        out: &mut String,
+        depth: usize,
+        writing: &mut Writing,
+    ) -> std::fmt::Result {
+        let mut inline = String::new();
+        self.write_contents(&mut inline, Layout::Inline, depth, writing)?;
+        let width = depth * INDENT.len() + inline.len();
+        if width <= MAX_INLINE_WIDTH && !inline.contains('\n') {
+            return write!(out, "{}", inline);
+        }
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.write_contents(out, Layout::Fit, depth, writing)
+        })
+    }
+
+    fn write_contents(
+        &self,
+        out: &mut String,
+        layout: Layout,
+        depth: usize,
+        writing: &mut Writing,
+    ) -> std::fmt::Result {
+        match self {
+            RuntimeVal::String(str) => write_quoted(out, str),
+            RuntimeVal::Object(object) => {
+                let object = object.borrow();
+                let map = &object.properties;
+                // Instances are prefixed with their class name: `Point { x: 1, y: 2 }`.
+                if let Some(class) = &object.class {
+                    write!(out, "{} ", class.name)?;
+                }
+                if map.is_empty() {
+                    return write!(out, "{{}}");
+                }
+                if layout == Layout::Inline {
+                    write!(out, "{{ ")?;
+                    for (index, (key, value)) in map.iter().enumerate() {
+                        if out.len() > MAX_INLINE_WIDTH {
+                            return Ok(());
+                        }
+                        if index > 0 {
+                            write!(out, ", ")?;
+                        }
+                        write_key(out, key)?;
+                        write!(out, ": ")?;
+                        value.write_literal(out, layout, depth + 1, writing)?;
+                    }
+                    return write!(out, " }}");
+                }
+                writeln!(out, "{{")?;
+                for (key, value) in map {
+                    write!(out, "{}", INDENT.repeat(depth + 1))?;
+                    write_key(out, key)?;
+                    write!(out, ": ")?;
+                    value.write_literal(out, layout, depth + 1, writing)?;
+                    writeln!(out, ",")?;
+                }
+                write!(out, "{}}}", INDENT.repeat(depth))
+            }
+            RuntimeVal::Array(elements) => {
+                let elements = elements.borrow();
+                if elements.is_empty() {
+                    return write!(out, "[]");
+                }
+                if layout == Layout::Inline {
+                    write!(out, "[")?;
+                    for (index, element) in elements.iter().enumerate() {
+                        if out.len() > MAX_INLINE_WIDTH {
+                            return Ok(());
+                        }
+                        if index > 0 {
+                            write!(out, ", ")?;
+                        }
+                        element.write_literal(out, layout, depth + 1, writing)?;
+                    }
+                    return write!(out, "]");
+                }
+                writeln!(out, "[")?;
+                for element in elements.iter() {
+                    write!(out, "{}", INDENT.repeat(depth + 1))?;
+                    element.write_literal(out, layout, depth + 1, writing)?;
+                    writeln!(out, ",")?;
+                }
+                write!(out, "{}]", INDENT.repeat(depth))
+            }
+            RuntimeVal::Variant(variant) => {
+                write!(out, "{}.{}", variant.enumeration.name, variant.name)?;
+                if variant.values.is_empty() {
+                    return Ok(());
+                }
+                // Variant fields stay on the variant's line.
+                let layout = match layout {
+                    Layout::Inline => Layout::Inline,
+                    _ => Layout::Fit,
+                };
+                write!(out, "(")?;
+                for (index, value) in variant.values.iter().enumerate() {
+                    if index > 0 {
+                        write!(out, ", ")?;
+                    }
+                    value.write_literal(out, layout, depth + 1, writing)?;
+                }
+                write!(out, ")")
+            }
+            value => write!(out, "{}", value),
+        }
+    }
+}
+
+fn write_key(out: &mut String, key: &str) -> std::fmt::Result {
+    if lexer::is_identifier(key) {
+        write!(out, "{}", key)
+    } else {
+        write_quoted(out, key)
+    }
+}
+
+fn write_quoted(out: &mut String, str: &str) -> std::fmt::Result {
+    write!(out, "\"")?;
+    for c in str.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\t' => write!(out, "\\t")?,
+            '\r' => write!(out, "\\r")?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
 }
 
 impl Display for RuntimeVal {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             RuntimeVal::Number(num) => write!(f, "{:#?}", num),
+            // Always shows a fractional part, so `3.0` prints differently from `3`.
+            RuntimeVal::Float(num) => write!(f, "{:?}", num),
             RuntimeVal::Null => write!(f, "null"),
             RuntimeVal::Bool(b_val) => write!(f, "{:#?}", b_val),
-            RuntimeVal::Object(map) => write!(f, "{:#?}", map),
+            RuntimeVal::String(str) => write!(f, "{}", str),
+            RuntimeVal::Object(_) | RuntimeVal::Array(_) | RuntimeVal::Variant(_) => {
+                // `{:#}` always spreads objects and arrays over several lines.
+                let mut out = String::new();
+                let layout = match f.alternate() {
+                    true => Layout::Pretty,
+                    false => Layout::Fit,
+                };
+                let mut writing = Writing {
+                    open: vec![],
+                    meter: None,
+                };
+                self.write_literal(&mut out, layout, 0, &mut writing)?;
+                write!(f, "{}", out)
+            }
+            RuntimeVal::Function(function) => {
+                let parameters: Vec<String> = function
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect();
+                write!(f, "fn {}({})", function.name, parameters.join(", "))
+            }
+            RuntimeVal::NativeFunction(function) => write!(f, "fn {}() [native]", function.name),
+            RuntimeVal::Class(class) => write!(f, "class {}", class.name),
+            RuntimeVal::BoundMethod(bound) => write!(f, "{}", bound.method),
+            RuntimeVal::Enum(enumeration) => write!(f, "enum {}", enumeration.name),
         }
     }
 }